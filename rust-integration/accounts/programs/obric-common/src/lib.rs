@@ -0,0 +1,5 @@
+//! Code the v2 and v3 account crates share, each re-exports `math` and builds its `pda` helpers on
+//! the ones here.
+
+pub mod math;
+pub mod pda;
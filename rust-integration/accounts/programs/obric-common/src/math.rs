@@ -0,0 +1,132 @@
+pub use uints::{U256, U512};
+
+/// Decimals of the fixed-point prices returned by `SSTradingPair`.
+pub const PRICE_DECIMALS: u32 = 18;
+
+// the lints fire on the code `construct_uint!` expands to
+#[allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+mod uints {
+    use uint::construct_uint;
+
+    construct_uint! {
+        pub struct U256(4);
+    }
+
+    construct_uint! {
+        pub struct U512(8);
+    }
+}
+
+impl U256 {
+    /// Narrows back to `u128`, or `None` if the value does not fit.
+    pub fn try_to_u128(self) -> Option<u128> {
+        if self.bits() > 128 {
+            None
+        } else {
+            Some(self.low_u128())
+        }
+    }
+}
+
+impl From<U256> for U512 {
+    fn from(value: U256) -> Self {
+        let mut bytes = [0u8; 32];
+        value.to_little_endian(&mut bytes);
+        U512::from_little_endian(&bytes)
+    }
+}
+
+/// `a * b / c`, rounded down. The product is carried in 256 bits so only the
+/// final quotient has to fit in `u128`.
+pub fn mul_div_floor(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    (U256::from(a) * U256::from(b) / U256::from(c)).try_to_u128()
+}
+
+/// `numerator / denominator` as a price of X in Y, converted from raw to human units and returned
+/// as a fixed-point number with `PRICE_DECIMALS` decimals, rounded down.
+pub fn fixed_point_price(
+    numerator: U256,
+    denominator: U256,
+    decimals_x: u8,
+    decimals_y: u8,
+) -> Option<u128> {
+    if denominator.is_zero() {
+        return None;
+    }
+    let ten = U512::from(10u8);
+    let numerator = U512::from(numerator)
+        .checked_mul(ten.checked_pow(U512::from(PRICE_DECIMALS + decimals_x as u32))?)?;
+    let denominator =
+        U512::from(denominator).checked_mul(ten.checked_pow(U512::from(decimals_y))?)?;
+    let price = numerator / denominator;
+    if price.bits() > 128 {
+        None
+    } else {
+        Some(price.low_u128())
+    }
+}
+
+/// Smallest curve coordinate `n` whose price `big_k / n^2`, in `fixed_point_price` units, is at
+/// most `price`. Prices are floored, so that is the first `n` with `n^2 > big_k * scale / (price + 1)`.
+pub fn min_coordinate_for_price_at_most(
+    big_k: U256,
+    price: u128,
+    decimals_x: u8,
+    decimals_y: u8,
+) -> Option<U256> {
+    let (scale_x, scale_y) = price_scales(decimals_x, decimals_y)?;
+    let numerator = U512::from(big_k).checked_mul(scale_x)?;
+    let denominator = U512::from(price)
+        .checked_add(U512::one())?
+        .checked_mul(scale_y)?;
+    ceil_sqrt((numerator / denominator).checked_add(U512::one())?)
+}
+
+/// Smallest curve coordinate `n` whose price `n^2 / big_k`, in `fixed_point_price` units, is at
+/// least `price`, i.e. the first `n` with `n^2 >= price * big_k / scale`.
+pub fn min_coordinate_for_price_at_least(
+    big_k: U256,
+    price: u128,
+    decimals_x: u8,
+    decimals_y: u8,
+) -> Option<U256> {
+    let (scale_x, scale_y) = price_scales(decimals_x, decimals_y)?;
+    let numerator = U512::from(price)
+        .checked_mul(U512::from(big_k))?
+        .checked_mul(scale_y)?;
+    let (quotient, remainder) = numerator.div_mod(scale_x);
+    let min_square = if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + U512::one()
+    };
+    ceil_sqrt(min_square)
+}
+
+/// `(10^(PRICE_DECIMALS + decimals_x), 10^decimals_y)`, the factors `fixed_point_price` applies.
+fn price_scales(decimals_x: u8, decimals_y: u8) -> Option<(U512, U512)> {
+    let ten = U512::from(10u8);
+    Some((
+        ten.checked_pow(U512::from(PRICE_DECIMALS + decimals_x as u32))?,
+        ten.checked_pow(U512::from(decimals_y))?,
+    ))
+}
+
+/// Smallest `n` with `n^2 >= value`, narrowed back to 256 bits.
+fn ceil_sqrt(value: U512) -> Option<U256> {
+    let root = value.integer_sqrt();
+    let root = if root * root < value {
+        root + U512::one()
+    } else {
+        root
+    };
+    if root.bits() > 256 {
+        return None;
+    }
+    let mut bytes = [0u8; 64];
+    root.to_little_endian(&mut bytes);
+    Some(U256::from_little_endian(&bytes[..32]))
+}
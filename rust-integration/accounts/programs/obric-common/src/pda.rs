@@ -0,0 +1,84 @@
+//! Program derived addresses seeded by a prefix and a mint pair, the scheme every per-pair account
+//! of both programs uses.
+
+use anchor_lang::prelude::Pubkey;
+use std::fmt;
+
+/// The address of `seed` for the mint pair with its canonical bump.
+pub fn mint_pair_address(
+    seed: &str,
+    program_id: &Pubkey,
+    mint_x: &Pubkey,
+    mint_y: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[seed.as_bytes(), mint_x.as_ref(), mint_y.as_ref()],
+        program_id,
+    )
+}
+
+/// Why an account is not the one the program would accept at a mint pair PDA.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PdaMismatch {
+    Owner {
+        key: Pubkey,
+        owner: Pubkey,
+    },
+    Address {
+        key: Pubkey,
+        bump: u8,
+        expected_key: Pubkey,
+        expected_bump: u8,
+    },
+}
+
+impl fmt::Display for PdaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Owner { key, owner } => {
+                write!(f, "trading pair {key} is owned by {owner}, not the program")
+            }
+            Self::Address {
+                key,
+                bump,
+                expected_key,
+                expected_bump,
+            } => write!(
+                f,
+                "trading pair {key} with bump {bump} is not the PDA {expected_key} with bump \
+                 {expected_bump} of its mints"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PdaMismatch {}
+
+/// Rejects an account at `key` that is not owned by the program or is not the `seed` PDA of its
+/// mints with the canonical `bump`, as the program would.
+pub fn verify_mint_pair_address(
+    seed: &str,
+    program_id: &Pubkey,
+    key: &Pubkey,
+    owner: &Pubkey,
+    mint_x: &Pubkey,
+    mint_y: &Pubkey,
+    bump: u8,
+) -> Result<(), PdaMismatch> {
+    if owner != program_id {
+        return Err(PdaMismatch::Owner {
+            key: *key,
+            owner: *owner,
+        });
+    }
+    let (expected_key, expected_bump) = mint_pair_address(seed, program_id, mint_x, mint_y);
+    if *key != expected_key || bump != expected_bump {
+        return Err(PdaMismatch::Address {
+            key: *key,
+            bump,
+            expected_key,
+            expected_bump,
+        });
+    }
+    Ok(())
+}
@@ -7,6 +7,15 @@ pub mod admin {
     declare_id!("obrrzdC5QzRHopGwe8jqUWkKa73AVLyAoEgRpa9V13i");
 }
 
+/// Where the program is deployed on each cluster. Localnet deploys this build, at `crate::ID`;
+/// there is no devnet deployment.
+pub mod deployments {
+    pub mod mainnet {
+        use anchor_lang::declare_id;
+        declare_id!("obr3KRonApm4evaY3ww3C63KUmdARoeHkdgc8uBHLz4");
+    }
+}
+
 pub mod mints {
     pub mod sol {
         use anchor_lang::declare_id;
@@ -109,7 +118,6 @@ pub const SOLEND_OBLIGATION_SPACE: usize = 1300;
 pub const LARIX_OBLIGATION_SEED: &str = "larix_obligation";
 pub const LARIX_OBLIGATION_SPACE: usize = 1092;
 
-// TODO: update larix ids
 pub mod larix {
     pub mod market {
         use anchor_lang::declare_id;
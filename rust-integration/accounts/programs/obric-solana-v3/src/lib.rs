@@ -1,5 +1,7 @@
 pub mod consts;
 pub mod errors;
+pub use obric_common::math;
+pub mod pda;
 pub mod state;
 
 use anchor_lang::prelude::*;
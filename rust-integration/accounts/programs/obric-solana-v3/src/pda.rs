@@ -0,0 +1,44 @@
+//! Program derived addresses of the accounts the program creates for a mint pair. Each returns the
+//! address with its canonical bump.
+
+use crate::consts;
+use crate::state::SSTradingPair;
+use anchor_lang::prelude::Pubkey;
+use obric_common::pda::{mint_pair_address, verify_mint_pair_address, PdaMismatch};
+
+pub fn trading_pair_address(program_id: &Pubkey, mint_x: &Pubkey, mint_y: &Pubkey) -> (Pubkey, u8) {
+    mint_pair_address(consts::TRADING_PAIR_SEED, program_id, mint_x, mint_y)
+}
+
+/// Rejects a `pair` at `key` that is not owned by the program or does not sit at the PDA of its
+/// mints with the canonical bump, as the program would when swapping against it.
+pub fn verify_trading_pair(
+    program_id: &Pubkey,
+    key: &Pubkey,
+    owner: &Pubkey,
+    pair: &SSTradingPair,
+) -> Result<(), PdaMismatch> {
+    verify_mint_pair_address(
+        consts::TRADING_PAIR_SEED,
+        program_id,
+        key,
+        owner,
+        &pair.mint_x,
+        &pair.mint_y,
+        pair.bump,
+    )
+}
+
+/// The seeds follow the trading pair's. This crate has no decoder for the account, its layout is
+/// not published with the program interface.
+pub fn fee_records_address(program_id: &Pubkey, mint_x: &Pubkey, mint_y: &Pubkey) -> (Pubkey, u8) {
+    mint_pair_address(consts::FEE_RECORDS_SEED, program_id, mint_x, mint_y)
+}
+
+pub fn larix_obligation_address(
+    program_id: &Pubkey,
+    mint_x: &Pubkey,
+    mint_y: &Pubkey,
+) -> (Pubkey, u8) {
+    mint_pair_address(consts::LARIX_OBLIGATION_SEED, program_id, mint_x, mint_y)
+}
@@ -1,6 +1,9 @@
 pub mod trading_pair;
 pub use trading_pair::*;
 
+pub mod trading_pair_view;
+pub use trading_pair_view::*;
+
 pub mod price_feed;
 pub use price_feed::*;
 
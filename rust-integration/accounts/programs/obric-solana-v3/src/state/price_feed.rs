@@ -12,7 +12,7 @@ pub struct PriceFeed(pyth_sdk::PriceFeed);
 impl PriceFeed {
     pub fn price_normalized(&self) -> Result<Price> {
         let p = self.0.get_price_unchecked();
-        let price = p.scale_to_exponent(-3).unwrap();
+        let price = p.scale_to_exponent(-3).ok_or(ObricError::PythError)?;
         require!(price.price > 0, ObricError::NegativePrice);
         Ok(price)
     }
@@ -1,7 +1,24 @@
 use anchor_lang::prelude::*;
-use num::pow;
-
-use crate::{consts, errors::ObricError};
+use num::checked_pow;
+use std::cmp::Ordering;
+
+use crate::{
+    consts,
+    errors::ObricError,
+    math::{
+        fixed_point_price, min_coordinate_for_price_at_least, min_coordinate_for_price_at_most,
+        mul_div_floor, U256,
+    },
+};
+
+/// (big_K, current_x_K, current_y_K, available_x, available_y), see `get_pool_values_for_quoting`.
+pub type PoolValues = (U256, u128, u128, u64, u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapDirection {
+    XToY,
+    YToX,
+}
 
 #[account]
 #[derive(Default, Debug)]
@@ -63,45 +80,104 @@ impl SSTradingPair {
         let x_decimals = self.decimals_x;
         let y_decimals = self.decimals_y;
         let (x_deci_mult, y_deci_mult) = if x_decimals > y_decimals {
-            (1 as u64, pow(10, usize::from(x_decimals - y_decimals)))
+            (
+                1 as u64,
+                checked_pow(10u64, usize::from(x_decimals - y_decimals))
+                    .ok_or(ObricError::NumOverflowing)?,
+            )
         } else if y_decimals > x_decimals {
-            (pow(10, usize::from(y_decimals - x_decimals)), 1 as u64)
+            (
+                checked_pow(10u64, usize::from(y_decimals - x_decimals))
+                    .ok_or(ObricError::NumOverflowing)?,
+                1 as u64,
+            )
         } else {
             (1 as u64, 1 as u64)
         };
 
-        self.mult_x = price_x.checked_mul(x_deci_mult).unwrap();
-        self.mult_y = price_y.checked_mul(y_deci_mult).unwrap();
+        self.mult_x = price_x
+            .checked_mul(x_deci_mult)
+            .ok_or(ObricError::NumOverflowing)?;
+        self.mult_y = price_y
+            .checked_mul(y_deci_mult)
+            .ok_or(ObricError::NumOverflowing)?;
 
         Ok(())
     }
 
-    pub fn update_target_y(&mut self, new_target_y: u64) -> Result<()> {
-        let target_y_k = (new_target_y as u128)
+    /**
+    `big_K` of the curve centered on `target_y`, in 256 bits.
+    */
+    pub fn big_k_at_target(&self, target_y: u64) -> Result<U256> {
+        let target_y_k = (target_y as u128)
             .checked_mul(self.concentration as u128)
-            .unwrap();
-        let target_x_k = target_y_k
-            .checked_mul(self.mult_y as u128)
-            .unwrap()
-            .checked_div(self.mult_x as u128)
-            .unwrap();
+            .ok_or(ObricError::NumOverflowing)?;
+        // rounded down: a smaller curve never quotes more than the exact one
+        let target_x_k = mul_div_floor(target_y_k, self.mult_y as u128, self.mult_x as u128)
+            .ok_or(ObricError::NumOverflowing)?;
+        Ok(U256::from(target_x_k) * U256::from(target_y_k))
+    }
+
+    /**
+    Fails when `big_K` outgrows the u128 `big_k` field, as the program does.
+    */
+    pub fn update_target_y(&mut self, new_target_y: u64) -> Result<()> {
+        let big_k = self
+            .big_k_at_target(new_target_y)?
+            .try_to_u128()
+            .ok_or(ObricError::NumOverflowing)?;
+
         self.target_y = new_target_y;
-        self.big_k = target_x_k.checked_mul(target_y_k).unwrap();
+        self.big_k = big_k;
 
         Ok(())
     }
 
-    pub fn compute_target_y(&self) -> u64 {
-        let deposit_x_value = self.deposit_x.checked_mul(self.mult_x).unwrap();
-        let borrow_x_value = self.borrow_x.checked_mul(self.mult_x).unwrap();
-        let deposit_y_value = self.deposit_y.checked_mul(self.mult_y).unwrap();
-        let borrow_y_value = self.borrow_y.checked_mul(self.mult_y).unwrap();
-        let deposit_value = deposit_x_value.checked_add(deposit_y_value).unwrap();
-        let borrow_value = borrow_x_value.checked_add(borrow_y_value).unwrap();
-        let net_value = deposit_value.checked_sub(borrow_value).unwrap();
-        let target_y = net_value.checked_div(self.mult_y).unwrap();
+    /**
+    `update_target_y` for quoting off chain, where pools whose `big_K` outgrows the u128 field still
+    move their target. `big_k` then saturates, quotes never read it and derive K from the target in
+    256 bits. Returns the exact `big_K`.
+    */
+    pub fn retarget(&mut self, new_target_y: u64) -> Result<U256> {
+        let big_k = self.big_k_at_target(new_target_y)?;
+
+        self.target_y = new_target_y;
+        self.big_k = big_k.try_to_u128().unwrap_or(u128::MAX);
+
+        Ok(big_k)
+    }
+
+    /**
+    Sets `concentration` and re-derives `big_K` from it at the current target, as `retarget` does.
+    */
+    pub fn set_concentration(&mut self, concentration: u64) -> Result<()> {
+        require!(concentration > 0, ObricError::InvalidConcentrationArg);
+        self.concentration = concentration;
+        self.retarget(self.target_y)?;
+        Ok(())
+    }
 
-        target_y
+    /**
+    Net value of the pool expressed in Y, rounded down.
+    */
+    pub fn compute_target_y(&self) -> Result<u64> {
+        let mult_x = self.mult_x as u128;
+        let mult_y = self.mult_y as u128;
+
+        let deposit_value = (self.deposit_x as u128 * mult_x)
+            .checked_add(self.deposit_y as u128 * mult_y)
+            .ok_or(ObricError::NumOverflowing)?;
+        let borrow_value = (self.borrow_x as u128 * mult_x)
+            .checked_add(self.borrow_y as u128 * mult_y)
+            .ok_or(ObricError::NumOverflowing)?;
+        let net_value = deposit_value
+            .checked_sub(borrow_value)
+            .ok_or(ObricError::NumOverflowing)?;
+        let target_y = net_value
+            .checked_div(mult_y)
+            .ok_or(ObricError::NumOverflowing)?;
+
+        Ok(u64::try_from(target_y).map_err(|_| ObricError::NumOverflowing)?)
     }
 
     /*
@@ -117,117 +193,309 @@ impl SSTradingPair {
     - target_y_K = deposited amount of Y * concentration
     - targeted deposit_x = targeted borrow_x = 0
     - targeted deposit_y = target_y
+
+    Curve coordinates are carried in u128 and K in 256 bits so large pools do not overflow. Every division rounds down,
+    as the program's u64 math does.
     */
-    pub fn get_pool_values_for_quoting(&self) -> Result<(u128, u64, u64, u64, u64)> {
-        // u64 or u128 here?
-        let target_y_k = self.concentration.checked_mul(self.target_y).unwrap();
-        let target_x_k = target_y_k
-            .checked_mul(self.mult_y)
-            .unwrap()
-            .checked_div(self.mult_x)
-            .unwrap();
+    pub fn get_pool_values_for_quoting(&self) -> Result<PoolValues> {
+        let target_y_k = (self.concentration as u128)
+            .checked_mul(self.target_y as u128)
+            .ok_or(ObricError::NumOverflowing)?;
+        let target_x_k = mul_div_floor(target_y_k, self.mult_y as u128, self.mult_x as u128)
+            .ok_or(ObricError::NumOverflowing)?;
         let current_y_k = target_y_k
-            .checked_add(self.deposit_y)
-            .unwrap()
-            .checked_sub(self.target_y)
-            .unwrap();
+            .checked_add(self.deposit_y as u128)
+            .ok_or(ObricError::NumOverflowing)?
+            .checked_sub(self.target_y as u128)
+            .ok_or(ObricError::NumOverflowing)?;
         let current_x_k = target_x_k
-            .checked_add(self.deposit_x)
-            .unwrap()
-            .checked_sub(self.borrow_x)
-            .unwrap();
-        let big_k = (current_x_k as u128)
-            .checked_mul(current_y_k as u128)
-            .unwrap();
+            .checked_add(self.deposit_x as u128)
+            .ok_or(ObricError::NumOverflowing)?
+            .checked_sub(self.borrow_x as u128)
+            .ok_or(ObricError::NumOverflowing)?;
+        let big_k = U256::from(current_x_k) * U256::from(current_y_k);
 
         let temp = target_x_k
-            .checked_div(self.concentration)
-            .unwrap()
-            .checked_add(self.deposit_x)
-            .unwrap();
-        let available_x = if temp > self.borrow_x {
-            temp.checked_sub(self.borrow_x).unwrap()
-        } else {
-            0
-        };
+            .checked_div(self.concentration as u128)
+            .ok_or(ObricError::NumOverflowing)?
+            .checked_add(self.deposit_x as u128)
+            .ok_or(ObricError::NumOverflowing)?;
+        let available_x =
+            u64::try_from(temp.saturating_sub(self.borrow_x as u128)).unwrap_or(u64::MAX);
         let available_y = self.deposit_y;
 
         Ok((big_k, current_x_k, current_y_k, available_x, available_y))
     }
 
     /**
-    Returns (output_to_user, fee_to_protocol)
+    Price of X in Y at the current point of the curve, before fees, in human units as a fixed-point number with
+    `PRICE_DECIMALS` decimals.
     */
-    pub fn quote_x_to_y(&self, input_x: u64) -> Result<(u64, u64, u64)> {
-        let (big_k, current_x_k, current_y_k, _available_x, available_y) =
+    pub fn spot_price_x_in_y(&self) -> Result<u128> {
+        self.spot_price_with_pool_values(&self.get_pool_values_for_quoting()?)
+    }
+
+    pub fn spot_price_with_pool_values(&self, pool_values: &PoolValues) -> Result<u128> {
+        let (_big_k, current_x_k, current_y_k, _available_x, _available_y) = *pool_values;
+        Ok(fixed_point_price(
+            U256::from(current_y_k),
+            U256::from(current_x_k),
+            self.decimals_x,
+            self.decimals_y,
+        )
+        .ok_or(ObricError::NumOverflowing)?)
+    }
+
+    /**
+    Price of X in Y where the curve ends up after swapping `input`, before fees, in the same units as
+    `spot_price_x_in_y`. This is the slope of curve-K, `K / x_K^2`, so it ignores the `available_*` checks of the
+    quote.
+    */
+    pub fn marginal_price_after(&self, input: u64, direction: SwapDirection) -> Result<u128> {
+        let (big_k, current_x_k, current_y_k, _available_x, _available_y) =
             self.get_pool_values_for_quoting()?;
+        let (numerator, denominator) = match direction {
+            SwapDirection::XToY => {
+                let new_x_k = U256::from(current_x_k) + U256::from(input);
+                (big_k, new_x_k * new_x_k)
+            }
+            SwapDirection::YToX => {
+                let new_y_k = U256::from(current_y_k) + U256::from(input);
+                (new_y_k * new_y_k, big_k)
+            }
+        };
+        Ok(
+            fixed_point_price(numerator, denominator, self.decimals_x, self.decimals_y)
+                .ok_or(ObricError::NumOverflowing)?,
+        )
+    }
+
+    /**
+    Largest input whose average fill, after fees, is at most `impact_bps` below the spot rate. Rounding is ignored:
+    on curve-K the fill of `input` is `in_K / (in_K + input)` of spot, so the input is
+    `in_K * (impact - fee) / (1 - impact)`. `None` when the fee alone exceeds the impact.
+    */
+    pub fn input_for_price_impact(
+        &self,
+        pool_values: &PoolValues,
+        impact_bps: u64,
+        direction: SwapDirection,
+    ) -> Result<Option<u64>> {
+        let (_big_k, current_x_k, current_y_k, _available_x, _available_y) = *pool_values;
+        let current_in_k = match direction {
+            SwapDirection::XToY => current_x_k,
+            SwapDirection::YToX => current_y_k,
+        };
+        let impact_millionth = (impact_bps as u128).saturating_mul(100);
+        let fee_millionth = self.fee_millionth as u128;
+        if impact_millionth <= fee_millionth || impact_millionth >= consts::MILLION as u128 {
+            return Ok(None);
+        }
+        let input = mul_div_floor(
+            current_in_k,
+            impact_millionth - fee_millionth,
+            consts::MILLION as u128 - impact_millionth,
+        )
+        .unwrap_or(u128::MAX);
+        Ok(Some(u64::try_from(input).unwrap_or(u64::MAX)))
+    }
+
+    /**
+    Largest input_x `quote_x_to_y` accepts, i.e. whose output before fee stays below `available_y`. 0 when the pool
+    has no Y to give.
+    */
+    pub fn max_input_x_to_y(&self) -> Result<u64> {
+        self.max_input_with_pool_values(&self.get_pool_values_for_quoting()?, SwapDirection::XToY)
+    }
+
+    /**
+    Largest input_y `quote_y_to_x` accepts, i.e. whose output before fee stays below `available_x`. 0 when the pool
+    has no X to give.
+    */
+    pub fn max_input_y_to_x(&self) -> Result<u64> {
+        self.max_input_with_pool_values(&self.get_pool_values_for_quoting()?, SwapDirection::YToX)
+    }
+
+    pub fn max_input_with_pool_values(
+        &self,
+        pool_values: &PoolValues,
+        direction: SwapDirection,
+    ) -> Result<u64> {
+        let (big_k, current_x_k, current_y_k, available_x, available_y) = *pool_values;
+        let (current_in_k, current_out_k, available_out) = match direction {
+            SwapDirection::XToY => (current_x_k, current_y_k, available_y),
+            SwapDirection::YToX => (current_y_k, current_x_k, available_x),
+        };
+        if available_out == 0 {
+            return Ok(0);
+        }
+
+        // output_before_fee = current_out_K - floor(K / new_in_K) stays below available_out as long as
+        // floor(K / new_in_K) >= min_new_out_k, i.e. new_in_K <= K / min_new_out_k
+        let min_new_out_k = (current_out_k + 1).saturating_sub(available_out as u128);
+        if min_new_out_k == 0 {
+            return Ok(u64::MAX);
+        }
+        let max_new_in_k = big_k / U256::from(min_new_out_k);
+        let max_input = max_new_in_k.saturating_sub(U256::from(current_in_k));
+        Ok(max_input.try_to_u128().map_or(u64::MAX, |max_input| {
+            u64::try_from(max_input).unwrap_or(u64::MAX)
+        }))
+    }
 
-        // 2. find new (x, y) on curve-K
-        let new_x_k = current_x_k.checked_add(input_x).unwrap();
-        let new_y_k: u64 = big_k
-            .checked_div(new_x_k as u128)
-            .unwrap()
-            .try_into()
-            .unwrap();
-
-        let output_before_fee_y = current_y_k.checked_sub(new_y_k).unwrap();
-        require!(
-            output_before_fee_y < available_y,
-            ObricError::InsufficientActiveY
-        );
-
-        let fee_y = output_before_fee_y
-            .checked_mul(self.fee_millionth)
-            .unwrap()
-            .checked_div(consts::MILLION)
-            .unwrap();
-        let output_after_fee_y = output_before_fee_y.checked_sub(fee_y).unwrap();
-
-        let protocol_fee_y = fee_y
-            .checked_mul(self.protocol_fee_share_thousandth)
-            .unwrap()
-            .checked_div(1000)
-            .unwrap();
-        let lp_fee_y = fee_y.checked_sub(protocol_fee_y).unwrap();
-
-        Ok((output_after_fee_y, protocol_fee_y, lp_fee_y))
+    /**
+    Direction and smallest input whose `marginal_price_after` reaches `target_price`, in `spot_price_x_in_y` units.
+    Selling X moves the price down and selling Y moves it up. Solved on curve-K: the new coordinate is
+    `sqrt(K / target)` for X and `sqrt(K * target)` for Y. `(XToY, 0)` when the pool is already at the target,
+    `None` when the input it takes is above `max_input_*`.
+    */
+    pub fn input_for_target_price(
+        &self,
+        target_price: u128,
+    ) -> Result<Option<(SwapDirection, u64)>> {
+        let pool_values = self.get_pool_values_for_quoting()?;
+        let (big_k, current_x_k, current_y_k, _available_x, _available_y) = pool_values;
+        let spot_price = self.spot_price_with_pool_values(&pool_values)?;
+        let (direction, current_in_k, new_in_k) = match target_price.cmp(&spot_price) {
+            Ordering::Equal => return Ok(Some((SwapDirection::XToY, 0))),
+            Ordering::Less => (
+                SwapDirection::XToY,
+                current_x_k,
+                min_coordinate_for_price_at_most(
+                    big_k,
+                    target_price,
+                    self.decimals_x,
+                    self.decimals_y,
+                ),
+            ),
+            Ordering::Greater => (
+                SwapDirection::YToX,
+                current_y_k,
+                min_coordinate_for_price_at_least(
+                    big_k,
+                    target_price,
+                    self.decimals_x,
+                    self.decimals_y,
+                ),
+            ),
+        };
+        let input = new_in_k
+            .ok_or(ObricError::NumOverflowing)?
+            .saturating_sub(U256::from(current_in_k));
+
+        let max_input = self.max_input_with_pool_values(&pool_values, direction)?;
+        match input
+            .try_to_u128()
+            .and_then(|input| u64::try_from(input).ok())
+        {
+            Some(input) if input <= max_input => Ok(Some((direction, input))),
+            _ => Ok(None),
+        }
+    }
+
+    /**
+    Returns (output_to_user, fee_to_protocol, fee_to_lp)
+    */
+    pub fn quote_x_to_y(&self, input_x: u64) -> Result<(u64, u64, u64)> {
+        self.quote_with_pool_values(
+            &self.get_pool_values_for_quoting()?,
+            input_x,
+            SwapDirection::XToY,
+        )
     }
 
     /**
-    Returns (output_to_user, fee_to_protocol, fee_to_reserve_x)
+    Returns (output_to_user, fee_to_protocol, fee_to_lp)
     */
     pub fn quote_y_to_x(&self, input_y: u64) -> Result<(u64, u64, u64)> {
-        let (big_k, current_x_k, current_y_k, available_x, _available_y) =
-            self.get_pool_values_for_quoting()?;
+        self.quote_with_pool_values(
+            &self.get_pool_values_for_quoting()?,
+            input_y,
+            SwapDirection::YToX,
+        )
+    }
+
+    /**
+    Quotes from values computed once by `get_pool_values_for_quoting`, for callers quoting many sizes against the
+    same pool state. Returns (output_to_user, fee_to_protocol, fee_to_lp)
+    */
+    pub fn quote_with_pool_values(
+        &self,
+        pool_values: &PoolValues,
+        input: u64,
+        direction: SwapDirection,
+    ) -> Result<(u64, u64, u64)> {
+        let (big_k, current_x_k, current_y_k, available_x, available_y) = *pool_values;
+        match direction {
+            SwapDirection::XToY => {
+                // 2. find new (x, y) on curve-K
+                let new_x_k = current_x_k
+                    .checked_add(input as u128)
+                    .ok_or(ObricError::NumOverflowing)?;
+                let new_y_k = big_k
+                    .checked_div(U256::from(new_x_k))
+                    .and_then(U256::try_to_u128)
+                    .ok_or(ObricError::NumOverflowing)?;
+
+                let output_before_fee_y = current_y_k
+                    .checked_sub(new_y_k)
+                    .ok_or(ObricError::NumOverflowing)?;
+                require!(
+                    output_before_fee_y < available_y as u128,
+                    ObricError::InsufficientActiveY
+                );
+
+                self.split_fee(output_before_fee_y as u64)
+            }
+            SwapDirection::YToX => {
+                // 2. find new (x, y) on curve-K
+                let new_y_k = current_y_k
+                    .checked_add(input as u128)
+                    .ok_or(ObricError::NumOverflowing)?;
+                let new_x_k = big_k
+                    .checked_div(U256::from(new_y_k))
+                    .and_then(U256::try_to_u128)
+                    .ok_or(ObricError::NumOverflowing)?;
+
+                let output_before_fee_x = current_x_k
+                    .checked_sub(new_x_k)
+                    .ok_or(ObricError::NumOverflowing)?;
+                require!(
+                    output_before_fee_x < available_x as u128,
+                    ObricError::InsufficientActiveX
+                );
+
+                self.split_fee(output_before_fee_x as u64)
+            }
+        }
+    }
 
-        // 2. find new (x, y) on curve-K
-        let new_y_k = current_y_k.checked_add(input_y).unwrap();
-        let new_x_k: u64 = big_k
-            .checked_div(new_y_k as u128)
-            .unwrap()
-            .try_into()
-            .unwrap();
-
-        let output_before_fee_x = current_x_k.checked_sub(new_x_k).unwrap();
-        require!(
-            output_before_fee_x < available_x,
-            ObricError::InsufficientActiveX
-        );
-
-        let fee_x = output_before_fee_x
-            .checked_mul(self.fee_millionth)
-            .unwrap()
-            .checked_div(consts::MILLION)
-            .unwrap();
-        let output_after_fee_x = output_before_fee_x.checked_sub(fee_x).unwrap();
-
-        let protocol_fee_x = fee_x
-            .checked_mul(self.protocol_fee_share_thousandth)
-            .unwrap()
-            .checked_div(1000)
-            .unwrap();
-        let lp_fee_x = fee_x.checked_sub(protocol_fee_x).unwrap();
-
-        Ok((output_after_fee_x, protocol_fee_x, lp_fee_x))
+    /**
+    Splits a curve output into (output_to_user, fee_to_protocol, fee_to_lp). Both fees are rounded down.
+    */
+    fn split_fee(&self, output_before_fee: u64) -> Result<(u64, u64, u64)> {
+        let fee = mul_div_floor(
+            output_before_fee as u128,
+            self.fee_millionth as u128,
+            consts::MILLION as u128,
+        )
+        .ok_or(ObricError::NumOverflowing)?;
+        let fee = u64::try_from(fee).map_err(|_| ObricError::NumOverflowing)?;
+        let output_after_fee = output_before_fee
+            .checked_sub(fee)
+            .ok_or(ObricError::NumOverflowing)?;
+
+        let protocol_fee = mul_div_floor(
+            fee as u128,
+            self.protocol_fee_share_thousandth as u128,
+            1000,
+        )
+        .ok_or(ObricError::NumOverflowing)?;
+        let protocol_fee = u64::try_from(protocol_fee).map_err(|_| ObricError::NumOverflowing)?;
+        let lp_fee = fee
+            .checked_sub(protocol_fee)
+            .ok_or(ObricError::NumOverflowing)?;
+
+        Ok((output_after_fee, protocol_fee, lp_fee))
     }
 }
@@ -0,0 +1,134 @@
+use crate::state::SSTradingPair;
+use anchor_lang::{
+    error::{Error, ErrorCode},
+    prelude::*,
+    Discriminator,
+};
+use bytemuck::{Pod, Zeroable};
+
+/// `SSTradingPair` as it lies in the account after the 8-byte discriminator. Borsh packs fields
+/// without padding, so the view is `packed` and can be cast from the account data in place.
+/// `is_initialized` is kept as the raw byte, `SSTradingPair::try_from` rejects anything but 0 and 1
+/// like Borsh does.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SSTradingPairView {
+    pub is_initialized: u8,
+
+    pub x_price_feed_id: Pubkey,
+    pub y_price_feed_id: Pubkey,
+
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+
+    pub reserve_x_ctoken: Pubkey,
+    pub reserve_y_ctoken: Pubkey,
+
+    pub protocol_fee_x: Pubkey,
+    pub protocol_fee_y: Pubkey,
+
+    pub bump: u8,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+
+    pub deposit_x: u64,
+    pub borrow_x: u64,
+
+    pub deposit_y: u64,
+    pub borrow_y: u64,
+
+    pub target_y: u64,
+
+    pub concentration: u64,
+    pub big_k: u128,
+
+    pub cumulative_volume: u64,
+
+    pub mult_x: u64,
+    pub mult_y: u64,
+    pub fee_millionth: u64,
+    pub rebate_percentage: u64,
+    pub protocol_fee_share_thousandth: u64,
+
+    pub decimals_x: u8,
+    pub decimals_y: u8,
+
+    pub volume_records: [u64; 8],
+
+    pub padding: [u8; 6],
+    pub volume_time_records: [i64; 8],
+    pub padding2: [u64; 23],
+}
+
+impl SSTradingPairView {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Borrows the view from the account data, checking the discriminator and length like
+    /// `SSTradingPair::try_deserialize`. Trailing bytes are ignored.
+    pub fn load(data: &[u8]) -> Result<&Self> {
+        if data.len() < SSTradingPair::DISCRIMINATOR.len() {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        let (discriminator, data) = data.split_at(SSTradingPair::DISCRIMINATOR.len());
+        if discriminator != SSTradingPair::DISCRIMINATOR.as_slice() {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        let data = data
+            .get(..Self::LEN)
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        Ok(bytemuck::from_bytes(data))
+    }
+}
+
+impl SSTradingPair {
+    /// Decodes a trading pair account. V3 pairs carry no `version` field, the discriminator alone
+    /// names the layout.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        Self::try_from(SSTradingPairView::load(data)?)
+    }
+}
+
+impl TryFrom<&SSTradingPairView> for SSTradingPair {
+    type Error = Error;
+
+    fn try_from(view: &SSTradingPairView) -> Result<Self> {
+        let is_initialized = match view.is_initialized {
+            0 => false,
+            1 => true,
+            _ => return Err(ErrorCode::AccountDidNotDeserialize.into()),
+        };
+        Ok(Self {
+            is_initialized,
+            x_price_feed_id: view.x_price_feed_id,
+            y_price_feed_id: view.y_price_feed_id,
+            reserve_x: view.reserve_x,
+            reserve_y: view.reserve_y,
+            reserve_x_ctoken: view.reserve_x_ctoken,
+            reserve_y_ctoken: view.reserve_y_ctoken,
+            protocol_fee_x: view.protocol_fee_x,
+            protocol_fee_y: view.protocol_fee_y,
+            bump: view.bump,
+            mint_x: view.mint_x,
+            mint_y: view.mint_y,
+            deposit_x: view.deposit_x,
+            borrow_x: view.borrow_x,
+            deposit_y: view.deposit_y,
+            borrow_y: view.borrow_y,
+            target_y: view.target_y,
+            concentration: view.concentration,
+            big_k: view.big_k,
+            cumulative_volume: view.cumulative_volume,
+            mult_x: view.mult_x,
+            mult_y: view.mult_y,
+            fee_millionth: view.fee_millionth,
+            rebate_percentage: view.rebate_percentage,
+            protocol_fee_share_thousandth: view.protocol_fee_share_thousandth,
+            decimals_x: view.decimals_x,
+            decimals_y: view.decimals_y,
+            volume_records: view.volume_records,
+            padding: view.padding,
+            volume_time_records: view.volume_time_records,
+            padding2: view.padding2,
+        })
+    }
+}
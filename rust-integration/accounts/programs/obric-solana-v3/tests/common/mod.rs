@@ -0,0 +1,90 @@
+//! Pool strategies and helpers shared by the property suites, each of which uses a subset.
+#![allow(dead_code)]
+
+use obric_solana_v3::state::SSTradingPair;
+use proptest::{prelude::*, test_runner::FileFailurePersistence};
+
+/// Persists the shrunk failures of one suite to `path`, which is checked in so every past
+/// counterexample is replayed before new cases are generated.
+pub fn config(path: &'static str) -> ProptestConfig {
+    ProptestConfig {
+        failure_persistence: Some(Box::new(FileFailurePersistence::Direct(path))),
+        ..ProptestConfig::default()
+    }
+}
+
+pub type Quote = Result<(u64, u64, u64), anchor_lang::error::Error>;
+
+pub fn quote(pair: &SSTradingPair, input: u64, x_to_y: bool) -> Quote {
+    if x_to_y {
+        pair.quote_x_to_y(input)
+    } else {
+        pair.quote_y_to_x(input)
+    }
+}
+
+/// Books a swap the way the program does: the input repays X borrowings before it is deposited,
+/// X paid out beyond the deposit is borrowed, and only the LP fee stays in the pool.
+pub fn swap(pair: &SSTradingPair, input: u64, x_to_y: bool) -> Option<(SSTradingPair, u64)> {
+    let (output, protocol_fee, _lp_fee) = quote(pair, input, x_to_y).ok()?;
+    let mut next = pair.clone();
+    if x_to_y {
+        let repaid = input.min(next.borrow_x);
+        next.borrow_x -= repaid;
+        next.deposit_x += input - repaid;
+        next.deposit_y -= output + protocol_fee;
+    } else {
+        next.deposit_y += input;
+        let withdrawn = (output + protocol_fee).min(next.deposit_x);
+        next.deposit_x -= withdrawn;
+        next.borrow_x += output + protocol_fee - withdrawn;
+    }
+    Some((next, output))
+}
+
+/// Pools freshly re-targeted by `update`, with X either deposited or borrowed.
+pub fn pool() -> impl Strategy<Value = SSTradingPair> {
+    (
+        1u64..=1_000,
+        1u64..=1_000_000_000,
+        1u64..=1_000_000_000,
+        0u64..=20_000,
+        0u64..=1_000,
+        1_000u64..=1_000_000_000_000_000,
+        0u64..=1_000_000_000_000_000,
+        0u64..=1_000_000_000_000_000,
+    )
+        .prop_filter_map(
+            "pool must re-target and quote",
+            |(
+                concentration,
+                mult_x,
+                mult_y,
+                fee_millionth,
+                share,
+                deposit_y,
+                deposit_x,
+                borrow_x,
+            )| {
+                let mut pair = SSTradingPair {
+                    concentration,
+                    mult_x,
+                    mult_y,
+                    fee_millionth,
+                    protocol_fee_share_thousandth: share,
+                    deposit_x,
+                    borrow_x,
+                    deposit_y,
+                    ..Default::default()
+                };
+                let target_y = pair.compute_target_y().ok()?;
+                pair.update_target_y(target_y).ok()?;
+                pair.get_pool_values_for_quoting().ok()?;
+                Some(pair)
+            },
+        )
+}
+
+pub fn input() -> impl Strategy<Value = u64> {
+    (0u32..48, any::<u64>()).prop_map(|(bits, v)| v >> (63 - bits.min(63)))
+}
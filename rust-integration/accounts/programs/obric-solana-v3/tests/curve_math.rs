@@ -0,0 +1,269 @@
+//! Differential tests of the v3 curve math against an arbitrary-precision model of the program's
+//! formulas: the same steps and floor divisions, without the u64 limits.
+
+use num::{BigUint, CheckedSub, ToPrimitive};
+use obric_solana_v3::state::SSTradingPair;
+
+const MILLION: u64 = 1_000_000;
+
+fn big(v: impl Into<u128>) -> BigUint {
+    BigUint::from(v.into())
+}
+
+/// splitmix64, enough to spread cases over the whole range without extra dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Log-uniform in `[1, 2^bits)` so small and huge magnitudes are equally likely.
+    fn magnitude(&mut self, bits: u32) -> u64 {
+        let width = self.next() % bits as u64 + 1;
+        let mask = if width == 64 {
+            u64::MAX
+        } else {
+            (1 << width) - 1
+        };
+        (self.next() & mask).max(1)
+    }
+}
+
+struct Reference {
+    current_x_k: BigUint,
+    current_y_k: BigUint,
+    big_k: BigUint,
+    available_x: BigUint,
+    available_y: BigUint,
+}
+
+impl Reference {
+    /// `None` when the current point falls off the curve, which the program rejects too.
+    fn new(pair: &SSTradingPair) -> Option<Self> {
+        let target_y_k = big(pair.concentration) * big(pair.target_y);
+        let target_x_k = &target_y_k * big(pair.mult_y) / big(pair.mult_x);
+        let current_y_k = (&target_y_k + big(pair.deposit_y)).checked_sub(&big(pair.target_y))?;
+        let current_x_k = (&target_x_k + big(pair.deposit_x)).checked_sub(&big(pair.borrow_x))?;
+        let available_x = (&target_x_k / big(pair.concentration) + big(pair.deposit_x))
+            .checked_sub(&big(pair.borrow_x))
+            .unwrap_or_default();
+
+        Some(Self {
+            big_k: &current_x_k * &current_y_k,
+            current_x_k,
+            current_y_k,
+            available_x,
+            available_y: big(pair.deposit_y),
+        })
+    }
+
+    /// `quote_x_to_y`/`quote_y_to_x` as the program computes them. `None` when the program would
+    /// reject the swap for lack of liquidity.
+    fn quote(&self, pair: &SSTradingPair, input: u64, x_to_y: bool) -> Option<(u64, u64, u64)> {
+        let (current_in, current_out, available) = if x_to_y {
+            (&self.current_x_k, &self.current_y_k, &self.available_y)
+        } else {
+            (&self.current_y_k, &self.current_x_k, &self.available_x)
+        };
+        let new_in = current_in + big(input);
+        let new_out = &self.big_k / new_in;
+        let output_before_fee = current_out - new_out;
+        // outputs are paid in u64, so availability beyond that is moot
+        if output_before_fee >= available.clone().min(big(u64::MAX)) {
+            return None;
+        }
+
+        let fee = &output_before_fee * big(pair.fee_millionth) / big(MILLION);
+        let output_after_fee = output_before_fee.checked_sub(&fee)?;
+        let protocol_fee = &fee * big(pair.protocol_fee_share_thousandth) / big(1000u64);
+        let lp_fee = fee.checked_sub(&protocol_fee)?;
+
+        Some((
+            output_after_fee.to_u64().unwrap(),
+            protocol_fee.to_u64().unwrap(),
+            lp_fee.to_u64().unwrap(),
+        ))
+    }
+}
+
+fn random_pair(rng: &mut Rng) -> Option<SSTradingPair> {
+    let mut pair = SSTradingPair {
+        concentration: rng.magnitude(20),
+        mult_x: rng.magnitude(48),
+        mult_y: rng.magnitude(48),
+        deposit_x: rng.magnitude(64),
+        deposit_y: rng.magnitude(64),
+        fee_millionth: rng.next() % 10_000,
+        protocol_fee_share_thousandth: rng.next() % 1001,
+        ..Default::default()
+    };
+    pair.borrow_x = rng.next() % pair.deposit_x.saturating_mul(2);
+
+    let target_y = pair.compute_target_y().ok()?;
+    // big_k only has to fit its u128 field on chain, quoting works without it
+    pair.retarget(target_y).ok()?;
+    // the program keeps deposit_y at or above target_y, borrowing X instead
+    if pair.deposit_y < pair.target_y {
+        return None;
+    }
+    Some(pair)
+}
+
+#[test]
+fn pool_values_match_reference() {
+    let mut rng = Rng(7);
+    let mut checked = 0;
+    while checked < 5_000 {
+        let Some(pair) = random_pair(&mut rng) else {
+            continue;
+        };
+        let (Ok((big_k, current_x_k, current_y_k, available_x, available_y)), Some(reference)) =
+            (pair.get_pool_values_for_quoting(), Reference::new(&pair))
+        else {
+            assert!(pair.get_pool_values_for_quoting().is_err());
+            assert!(Reference::new(&pair).is_none());
+            continue;
+        };
+
+        assert_eq!(big(current_x_k), reference.current_x_k);
+        assert_eq!(big(current_y_k), reference.current_y_k);
+        assert_eq!(big_k.to_string(), reference.big_k.to_string());
+        assert_eq!(
+            big(available_x),
+            reference.available_x.clone().min(big(u64::MAX))
+        );
+        assert_eq!(big(available_y), reference.available_y);
+        checked += 1;
+    }
+}
+
+#[test]
+fn quotes_match_reference() {
+    let mut rng = Rng(11);
+    let mut checked = 0;
+    while checked < 20_000 {
+        let Some(pair) = random_pair(&mut rng) else {
+            continue;
+        };
+        let Some(reference) = Reference::new(&pair) else {
+            continue;
+        };
+        let input = rng.magnitude(64);
+
+        for x_to_y in [true, false] {
+            let actual = if x_to_y {
+                pair.quote_x_to_y(input)
+            } else {
+                pair.quote_y_to_x(input)
+            };
+            match reference.quote(&pair, input, x_to_y) {
+                Some(expected) => assert_eq!(actual.unwrap(), expected),
+                None => assert!(actual.is_err()),
+            }
+        }
+        checked += 1;
+    }
+}
+
+#[test]
+fn quotes_round_down_like_the_program() {
+    let mut rng = Rng(13);
+    let mut checked = 0;
+    while checked < 20_000 {
+        let Some(pair) = random_pair(&mut rng) else {
+            continue;
+        };
+        let Ok((_, current_x_k, current_y_k, _, _)) = pair.get_pool_values_for_quoting() else {
+            continue;
+        };
+        let input = rng.magnitude(64);
+        let Ok((output, protocol_fee, lp_fee)) = pair.quote_x_to_y(input) else {
+            continue;
+        };
+
+        // exact output is current_y_K - K / (current_x_K + input), floor(K / new_x_K) puts the
+        // gross output at most a unit above it
+        let big_k = big(current_x_k) * big(current_y_k);
+        let gross = big(output) + big(protocol_fee) + big(lp_fee);
+        let new_x_k = big(current_x_k) + big(input);
+        let new_y_k = big(current_y_k) - &gross;
+        assert!(&new_y_k * &new_x_k <= big_k, "new coordinate rounded up");
+        assert!(
+            (new_y_k + 1u32) * &new_x_k > big_k,
+            "new coordinate more than a unit under K / new_x_K"
+        );
+        let fee = (big(protocol_fee) + big(lp_fee)) * big(MILLION);
+        let exact = gross * big(pair.fee_millionth);
+        assert!(
+            fee <= exact && exact < fee + big(MILLION),
+            "fee not rounded down"
+        );
+        checked += 1;
+    }
+}
+
+#[test]
+fn large_pools_no_longer_overflow() {
+    // concentration * target_y and deposit_x * mult_x both exceed u64::MAX here
+    let mut pair = SSTradingPair {
+        concentration: 100_000,
+        mult_x: 200_000_000_000,
+        mult_y: 1_000_000_000,
+        deposit_x: 50_000_000_000_000_000,
+        deposit_y: 400_000_000_000_000_000,
+        borrow_x: 50_000_000_000_000_000,
+        fee_millionth: 300,
+        protocol_fee_share_thousandth: 200,
+        ..Default::default()
+    };
+    let target_y = pair.compute_target_y().unwrap();
+    assert_eq!(target_y, 400_000_000_000_000_000);
+
+    // K itself no longer fits the u128 `big_k` field, the quote path does not need it
+    assert!(pair.update_target_y(target_y).is_err());
+    let big_k = pair.retarget(target_y).unwrap();
+    assert!(big_k.try_to_u128().is_none());
+    assert_eq!((pair.target_y, pair.big_k), (target_y, u128::MAX));
+
+    let (output, protocol_fee, lp_fee) = pair.quote_x_to_y(1_000_000_000).unwrap();
+    let reference = Reference::new(&pair).unwrap();
+    assert_eq!(
+        reference.quote(&pair, 1_000_000_000, true),
+        Some((output, protocol_fee, lp_fee))
+    );
+    assert!(pair.quote_y_to_x(1_000_000_000_000).is_ok());
+}
+
+#[test]
+fn degenerate_pools_error_instead_of_panicking() {
+    let pair = SSTradingPair {
+        mult_x: 0,
+        mult_y: 1,
+        concentration: 10,
+        target_y: 1,
+        ..Default::default()
+    };
+    assert!(pair.get_pool_values_for_quoting().is_err());
+    assert!(pair.quote_x_to_y(1).is_err());
+
+    let pair = SSTradingPair {
+        mult_x: 1,
+        mult_y: 1,
+        concentration: 0,
+        ..Default::default()
+    };
+    assert!(pair.quote_y_to_x(1).is_err());
+    assert!(pair.compute_target_y().is_ok());
+
+    let pair = SSTradingPair {
+        mult_x: 1,
+        mult_y: 0,
+        ..Default::default()
+    };
+    assert!(pair.compute_target_y().is_err());
+}
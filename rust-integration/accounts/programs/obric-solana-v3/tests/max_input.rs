@@ -0,0 +1,24 @@
+//! `max_input_*` of the v3 `SSTradingPair` against the quotes it accepts.
+
+mod common;
+
+use common::{pool, quote};
+use obric_solana_v3::state::SwapDirection;
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/max_input.txt"))]
+
+    #[test]
+    fn max_input_is_the_largest_accepted(pair in pool(), x_to_y: bool) {
+        let pool_values = pair.get_pool_values_for_quoting().unwrap();
+        let direction = if x_to_y { SwapDirection::XToY } else { SwapDirection::YToX };
+        let max_input = pair.max_input_with_pool_values(&pool_values, direction).unwrap();
+        if max_input > 0 {
+            prop_assert!(quote(&pair, max_input, x_to_y).is_ok());
+        }
+        if max_input < u64::MAX {
+            prop_assert!(quote(&pair, max_input + 1, x_to_y).is_err());
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! The PDA helpers against the seeds the program checks: each address is off the curve and
+//! re-derives from its bump, and the accounts of one mint pair never collide.
+
+use anchor_lang::prelude::Pubkey;
+use obric_solana_v3::{consts, pda};
+use proptest::prelude::*;
+
+fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn addresses_rederive_from_their_bump(program_id in pubkey(), mint_x in pubkey(), mint_y in pubkey()) {
+        let derived = [
+            (consts::TRADING_PAIR_SEED, pda::trading_pair_address(&program_id, &mint_x, &mint_y)),
+            (consts::FEE_RECORDS_SEED, pda::fee_records_address(&program_id, &mint_x, &mint_y)),
+            (consts::LARIX_OBLIGATION_SEED, pda::larix_obligation_address(&program_id, &mint_x, &mint_y)),
+        ];
+        for (seed, (address, bump)) in derived {
+            prop_assert!(!address.is_on_curve());
+            let rederived = Pubkey::create_program_address(
+                &[seed.as_bytes(), mint_x.as_ref(), mint_y.as_ref(), &[bump]],
+                &program_id,
+            );
+            prop_assert_eq!(rederived, Ok(address));
+        }
+        prop_assert_ne!(derived[0].1 .0, derived[1].1 .0);
+        prop_assert_ne!(derived[0].1 .0, derived[2].1 .0);
+        prop_assert_ne!(derived[1].1 .0, derived[2].1 .0);
+    }
+
+    #[test]
+    fn pair_address_depends_on_mint_order(program_id in pubkey(), mint_x in pubkey(), mint_y in pubkey()) {
+        prop_assume!(mint_x != mint_y);
+        prop_assert_ne!(
+            pda::trading_pair_address(&program_id, &mint_x, &mint_y).0,
+            pda::trading_pair_address(&program_id, &mint_y, &mint_x).0
+        );
+    }
+}
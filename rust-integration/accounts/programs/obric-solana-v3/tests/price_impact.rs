@@ -0,0 +1,35 @@
+//! `input_for_price_impact` of the v3 `SSTradingPair` against the fills it sizes.
+
+mod common;
+
+use common::pool;
+use num::BigUint;
+use obric_solana_v3::{math::PRICE_DECIMALS, state::SwapDirection};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/price_impact.txt"))]
+
+    #[test]
+    fn impact_input_stays_within_threshold(pair in pool(), impact_bps in 1u64..=500, x_to_y: bool) {
+        let pool_values = pair.get_pool_values_for_quoting().unwrap();
+        let spot = pair.spot_price_with_pool_values(&pool_values).unwrap();
+        let direction = if x_to_y { SwapDirection::XToY } else { SwapDirection::YToX };
+        let Some(input) = pair.input_for_price_impact(&pool_values, impact_bps, direction).unwrap() else {
+            prop_assert!(impact_bps * 100 <= pair.fee_millionth);
+            return Ok(());
+        };
+        let Ok((output, _protocol_fee, _lp_fee)) = pair.quote_with_pool_values(&pool_values, input, direction) else {
+            return Ok(());
+        };
+        // the fill at that size is no worse than the threshold, give or take the rounding of a few units
+        let scale = BigUint::from(10u128.pow(PRICE_DECIMALS));
+        let (output, input) = (BigUint::from(output + 3), BigUint::from(input));
+        let kept = BigUint::from(10_000 - impact_bps);
+        if x_to_y {
+            prop_assert!(output * &scale * 10_000u32 >= input * BigUint::from(spot) * kept);
+        } else {
+            prop_assert!(output * BigUint::from(spot + 1) * 10_000u32 >= input * scale * kept);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Spot and marginal prices of the v3 `SSTradingPair` against the fills it quotes.
+
+mod common;
+
+use common::pool;
+use obric_solana_v3::{math::PRICE_DECIMALS, state::SwapDirection};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/prices.txt"))]
+
+    #[test]
+    fn prices_bracket_the_average_fill(pair in pool(), amount in 1u64..=u32::MAX as u64) {
+        let scale = 10u128.pow(PRICE_DECIMALS);
+        let spot = pair.spot_price_x_in_y().unwrap();
+        let after_sell = pair.marginal_price_after(amount, SwapDirection::XToY).unwrap();
+        let after_buy = pair.marginal_price_after(amount, SwapDirection::YToX).unwrap();
+        prop_assert!(after_sell <= spot && spot <= after_buy);
+
+        // the new coordinate is rounded down, the gross output can be a unit above the curve
+        if let Ok((output, protocol_fee, lp_fee)) = pair.quote_x_to_y(amount) {
+            let gross = (output + protocol_fee + lp_fee) as u128;
+            if gross > 0 {
+                prop_assert!((gross - 1) * scale / amount as u128 <= spot);
+                prop_assert!(gross * scale / amount as u128 >= after_sell);
+            }
+        }
+        if let Ok((output, protocol_fee, lp_fee)) = pair.quote_y_to_x(amount) {
+            let gross = (output + protocol_fee + lp_fee) as u128;
+            if let Some(average) = (amount as u128 * scale).checked_div(gross.saturating_sub(1)) {
+                prop_assert!(average >= spot);
+            }
+            if let Some(average) = (amount as u128 * scale).checked_div(gross) {
+                prop_assert!(average <= after_buy);
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Property tests for the v3 `SSTradingPair` quote math.
+//!
+//! Failing cases are shrunk and persisted under `proptest-regressions/`, one file per suite, which is
+//! checked in so every past counterexample is replayed before new cases are generated. v3 pools have
+//! no `target_y_based_lock`; the lock property lives in the v2 suite only.
+
+mod common;
+
+use common::{input, pool, quote, swap};
+use obric_solana_v3::{consts::MILLION, state::SSTradingPair};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/quote_invariants.txt"))]
+
+    #[test]
+    fn output_is_monotone_in_input(pair in pool(), a in input(), b in input(), x_to_y: bool) {
+        let (small, large) = (a.min(b), a.max(b));
+        if let Ok((large_out, large_protocol_fee, large_lp_fee)) = quote(&pair, large, x_to_y) {
+            // anything smaller than an accepted trade is accepted too
+            let (small_out, small_protocol_fee, small_lp_fee) = quote(&pair, small, x_to_y).unwrap();
+            prop_assert!(small_out <= large_out);
+            prop_assert!(
+                small_out + small_protocol_fee + small_lp_fee
+                    <= large_out + large_protocol_fee + large_lp_fee
+            );
+        }
+    }
+
+    #[test]
+    fn output_never_reaches_available(pair in pool(), amount in input(), x_to_y: bool) {
+        let (_, _, _, available_x, available_y) = pair.get_pool_values_for_quoting().unwrap();
+        let available = if x_to_y { available_y } else { available_x };
+        if let Ok((output, protocol_fee, lp_fee)) = quote(&pair, amount, x_to_y) {
+            prop_assert!(output + protocol_fee + lp_fee < available);
+        }
+    }
+
+    #[test]
+    fn round_trip_never_profits(pair in pool(), amount in input(), x_to_y: bool) {
+        let Some((after_first, first_out)) = swap(&pair, amount, x_to_y) else {
+            return Ok(());
+        };
+        let Ok((back, _, _)) = quote(&after_first, first_out, !x_to_y) else {
+            return Ok(());
+        };
+        // Each leg floors its new curve coordinate, as the program does, so it can pay out up to one base
+        // unit of its output token over the curve. The round trip can then return one unit of each token
+        // more than the curve would: a unit of the input, and a unit of the first output, which buys
+        // in_K / out_K of the input back at the price after the first leg. That is many units when the
+        // first output is the dearer token, see `round_trip_can_return_a_unit_of_each_token`.
+        let (_big_k, current_x_k, current_y_k, _available_x, _available_y) =
+            after_first.get_pool_values_for_quoting().unwrap();
+        let (in_k, out_k) = if x_to_y {
+            (current_x_k, current_y_k)
+        } else {
+            (current_y_k, current_x_k)
+        };
+        let output_unit = in_k.div_ceil(out_k.max(1));
+        prop_assert!(
+            back as u128 <= amount as u128 + 1 + output_unit,
+            "{} in, {} back, a unit of the first output buys {}",
+            amount,
+            back,
+            output_unit
+        );
+    }
+
+    #[test]
+    fn fee_matches_fee_millionth(pair in pool(), amount in input(), x_to_y: bool) {
+        if let Ok((output, protocol_fee, lp_fee)) = quote(&pair, amount, x_to_y) {
+            let fee = (protocol_fee + lp_fee) as u128;
+            let gross = output as u128 + fee;
+            let charged = fee * MILLION as u128;
+            let exact = gross * pair.fee_millionth as u128;
+            // fee is rounded down
+            prop_assert!(charged <= exact && exact < charged + MILLION as u128);
+            prop_assert!(protocol_fee as u128 * 1000 <= fee * pair.protocol_fee_share_thousandth as u128);
+        }
+    }
+}
+
+/// Why `round_trip_never_profits` allows a unit of each token: a Y unit is worth 1,000 X units
+/// here, so a single unit of X gets the unit of Y the first leg rounds up, which buys 1,001 X back.
+#[test]
+fn round_trip_can_return_a_unit_of_each_token() {
+    let mut pair = SSTradingPair {
+        concentration: 1,
+        mult_x: 1,
+        mult_y: 1_000,
+        deposit_y: 1_000,
+        ..Default::default()
+    };
+    let target_y = pair.compute_target_y().unwrap();
+    pair.update_target_y(target_y).unwrap();
+
+    let (after_first, first_out) = swap(&pair, 1, true).unwrap();
+    assert_eq!(first_out, 1);
+    assert_eq!(
+        quote(&after_first, first_out, false).unwrap(),
+        (1_001, 0, 0)
+    );
+}
@@ -0,0 +1,44 @@
+//! `input_for_target_price` of the v3 `SSTradingPair` against a brute-force search.
+
+mod common;
+
+use common::pool;
+use obric_solana_v3::state::{SSTradingPair, SwapDirection};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/target_price.txt"))]
+
+    #[test]
+    fn target_price_input_matches_brute_force(
+        pair in pool(),
+        steps in 1u64..=2_000,
+        x_to_y: bool,
+        decimals_x in 0u8..=9,
+        decimals_y in 0u8..=9,
+    ) {
+        let pair = SSTradingPair { decimals_x, decimals_y, ..pair };
+        let direction = if x_to_y { SwapDirection::XToY } else { SwapDirection::YToX };
+        let marginal = |input| pair.marginal_price_after(input, direction).unwrap();
+        let target = marginal(steps);
+        let solved = pair.input_for_target_price(target).unwrap();
+        let spot = pair.spot_price_x_in_y().unwrap();
+        if target == spot {
+            prop_assert_eq!(solved, Some((SwapDirection::XToY, 0)));
+            return Ok(());
+        }
+
+        let reaches = |input| if x_to_y { marginal(input) <= target } else { marginal(input) >= target };
+        let expected = (0..=steps).find(|&input| reaches(input)).unwrap();
+        let max_input = if x_to_y {
+            pair.max_input_x_to_y().unwrap()
+        } else {
+            pair.max_input_y_to_x().unwrap()
+        };
+        if expected <= max_input {
+            prop_assert_eq!(solved, Some((direction, expected)));
+        } else {
+            prop_assert_eq!(solved, None);
+        }
+    }
+}
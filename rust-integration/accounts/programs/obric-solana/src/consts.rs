@@ -3,6 +3,18 @@ pub mod admin {
     declare_id!("obriQD1zbpyLz95G5n7nJe6a4DPjpFwa5XYPoNm113y");
 }
 
+/// Where the program is deployed on each cluster. Localnet deploys this build, at `crate::ID`.
+pub mod deployments {
+    pub mod mainnet {
+        use anchor_lang::declare_id;
+        declare_id!("obriQD1zbpyLz95G5n7nJe6a4DPjpFwa5XYPoNm113y");
+    }
+    pub mod devnet {
+        use anchor_lang::declare_id;
+        declare_id!("AbvTEuipYnGYgZKijb2HgE6i8xMEkWdYKo5weXir4Rz2");
+    }
+}
+
 pub const TRADING_PAIR_SEED: &str = "trading_pair";
 pub const FEE_RECORDS_SEED: &str = "fee_records";
 
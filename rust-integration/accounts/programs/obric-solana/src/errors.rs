@@ -29,4 +29,6 @@ pub enum ObricError {
 
     InvalidRoutesForSwap,
     PoolLocked,
+    #[msg("Trading pair layout version is newer than this crate supports")]
+    UnsupportedLayoutVersion,
 }
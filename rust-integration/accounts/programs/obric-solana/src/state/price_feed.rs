@@ -58,7 +58,7 @@ pub fn parse_dove_price(
 ) -> Result<(u64, i64)> {
     let time = doves_price_feed.timestamp;
 
-    if (time + age as i64) < current_time {
+    if time.saturating_add(age as i64) < current_time {
         return Err(ObricError::PythError.into());
     }
 
@@ -73,7 +73,7 @@ pub fn parse_dove_price_unchecked(
     let mut expo = doves_price_feed.expo;
     let time = doves_price_feed.timestamp;
 
-    let wanted_expo = -(decimals as i8);
+    let wanted_expo = -i8::try_from(decimals).map_err(|_| ObricError::PythError)?;
 
     if expo > wanted_expo {
         return Err(ObricError::PythError.into());
@@ -1,6 +1,23 @@
-use crate::{consts::MILLION, errors::ObricError};
+use crate::{
+    consts::MILLION,
+    errors::ObricError,
+    math::{
+        fixed_point_price, min_coordinate_for_price_at_least, min_coordinate_for_price_at_most,
+        U256,
+    },
+};
 use anchor_lang::prelude::*;
-use num::{integer::Roots, pow};
+use num::{checked_pow, integer::Roots};
+use std::cmp::Ordering;
+
+/// (big_K, current_x_K, current_y_K, target_x, target_y), see `get_pool_values_for_quoting`.
+pub type PoolValues = (u128, u128, u128, u64, u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapDirection {
+    XToY,
+    YToX,
+}
 
 #[account]
 #[derive(Default, Debug, Copy)]
@@ -68,9 +85,17 @@ impl SSTradingPair {
         y_decimals: u8,
     ) -> Result<()> {
         let (x_deci_mult, y_deci_mult) = if x_decimals > y_decimals {
-            (1 as u64, pow(10, usize::from(x_decimals - y_decimals)))
+            (
+                1 as u64,
+                checked_pow(10u64, usize::from(x_decimals - y_decimals))
+                    .ok_or(ObricError::NumOverflowing)?,
+            )
         } else if y_decimals > x_decimals {
-            (pow(10, usize::from(y_decimals - x_decimals)), 1 as u64)
+            (
+                checked_pow(10u64, usize::from(y_decimals - x_decimals))
+                    .ok_or(ObricError::NumOverflowing)?,
+                1 as u64,
+            )
         } else {
             (1 as u64, 1 as u64)
         };
@@ -84,6 +109,24 @@ impl SSTradingPair {
 
         Ok(())
     }
+
+    /**
+    Sets `concentration` and rescales `big_K` with it. `big_K` is `(concentration * target_x)^2` at the price the pair
+    was set up at, so it is scaled by the squared ratio of the two concentrations and keeps that price.
+     */
+    pub fn set_concentration(&mut self, concentration: u64) -> Result<()> {
+        require!(
+            concentration > 0 && self.concentration > 0,
+            ObricError::InvalidConcentrationArg
+        );
+        let (new, old) = (U256::from(concentration), U256::from(self.concentration));
+        self.big_k = (U256::from(self.big_k) * new * new / (old * old))
+            .try_to_u128()
+            .ok_or(ObricError::NumOverflowing)?;
+        self.concentration = concentration;
+        Ok(())
+    }
+
     pub fn get_target_xy(&self, current_x: u64, current_y: u64) -> Result<(u64, u64)> {
         let value_x = (current_x as u128)
             .checked_mul(self.mult_x as u128)
@@ -108,29 +151,9 @@ impl SSTradingPair {
         Ok((target_x, target_y))
     }
     /**
-    Returns (output_to_user, fee)
+    Returns (big_K, current_x_K, current_y_K), the point on curve-K the pool quotes from.
      */
-    pub fn quote_x_to_y(
-        &self,
-        input_x: u64,
-        current_x: u64,
-        current_y: u64,
-    ) -> Result<(u64, u64)> {
-        if input_x == 0 {
-            return Ok((0u64, 0u64));
-        }
-
-        let (target_x, _target_y) = self.get_target_xy(current_x, current_y)?;
-
-        // perform lock-checking
-        if self.target_y_based_lock {
-            let allow_swap = abs_diff(current_x + input_x, target_x)? < abs_diff(current_x, target_x)?;
-
-            if !allow_swap {
-                return Ok((0u64, 0u64));
-            }
-        }
-
+    pub fn get_curve_values(&self, current_x: u64) -> Result<(u128, u128, u128)> {
         // 0. get target_x on curve-K
         let big_k = self.big_k;
         //target_x_K = sqrt(big_k / p), where p = mult_x / mult_y
@@ -141,38 +164,262 @@ impl SSTradingPair {
             .ok_or(ObricError::NumOverflowing)?)
         .sqrt();
 
-        // 1. find current (x,y) on curve-K
-        let current_x_k = (target_x_k - target_x as u128)
+        // 1. find current (x, y) on curve-K
+        let current_x_k = target_x_k
+            .checked_sub(self.target_x as u128)
+            .ok_or(ObricError::NumOverflowing)?
             .checked_add(current_x as u128)
             .ok_or(ObricError::NumOverflowing)?;
         let current_y_k = big_k
             .checked_div(current_x_k)
             .ok_or(ObricError::NumOverflowing)?;
 
-        // 2. find new (x, y) on curve-K
-        let new_x_k = current_x_k
-            .checked_add(input_x as u128)
-            .ok_or(ObricError::NumOverflowing)?;
-        let new_y_k = big_k
-            .checked_div(new_x_k)
-            .ok_or(ObricError::NumOverflowing)?;
+        Ok((big_k, current_x_k, current_y_k))
+    }
 
-        let output_before_fee_y: u64 = (current_y_k
-            .checked_sub(new_y_k)
-            .ok_or(ObricError::NumOverflowing)?) as u64;
-        if output_before_fee_y >= current_y {
-            return Ok((0u64, 0u64));
+    /**
+    Returns (big_K, current_x_K, current_y_K, target_x, target_y), everything a quote needs besides the reserves.
+     */
+    pub fn get_pool_values_for_quoting(
+        &self,
+        current_x: u64,
+        current_y: u64,
+    ) -> Result<PoolValues> {
+        let (target_x, target_y) = self.get_target_xy(current_x, current_y)?;
+        let (big_k, current_x_k, current_y_k) = self.get_curve_values(current_x)?;
+        Ok((big_k, current_x_k, current_y_k, target_x, target_y))
+    }
+
+    /**
+    Price of X in Y at the current point of the curve, before fees, in human units as a fixed-point number with
+    `PRICE_DECIMALS` decimals.
+     */
+    pub fn spot_price_x_in_y(
+        &self,
+        current_x: u64,
+        x_decimals: u8,
+        y_decimals: u8,
+    ) -> Result<u128> {
+        let (big_k, current_x_k, current_y_k) = self.get_curve_values(current_x)?;
+        self.spot_price_with_pool_values(
+            &(big_k, current_x_k, current_y_k, 0, 0),
+            x_decimals,
+            y_decimals,
+        )
+    }
+
+    pub fn spot_price_with_pool_values(
+        &self,
+        pool_values: &PoolValues,
+        x_decimals: u8,
+        y_decimals: u8,
+    ) -> Result<u128> {
+        let (_big_k, current_x_k, current_y_k, _target_x, _target_y) = *pool_values;
+        Ok(fixed_point_price(
+            U256::from(current_y_k),
+            U256::from(current_x_k),
+            x_decimals,
+            y_decimals,
+        )
+        .ok_or(ObricError::NumOverflowing)?)
+    }
+
+    /**
+    Price of X in Y where the curve ends up after swapping `input`, before fees, in the same units as
+    `spot_price_x_in_y`. This is the slope of curve-K, `K / x_K^2`, so it ignores the reserve and lock checks of
+    the quote.
+     */
+    pub fn marginal_price_after(
+        &self,
+        input: u64,
+        direction: SwapDirection,
+        current_x: u64,
+        x_decimals: u8,
+        y_decimals: u8,
+    ) -> Result<u128> {
+        let (big_k, current_x_k, current_y_k) = self.get_curve_values(current_x)?;
+        let (numerator, denominator) = match direction {
+            SwapDirection::XToY => {
+                let new_x_k = U256::from(current_x_k) + U256::from(input);
+                (U256::from(big_k), new_x_k * new_x_k)
+            }
+            SwapDirection::YToX => {
+                let new_y_k = U256::from(current_y_k) + U256::from(input);
+                (new_y_k * new_y_k, U256::from(big_k))
+            }
+        };
+        Ok(
+            fixed_point_price(numerator, denominator, x_decimals, y_decimals)
+                .ok_or(ObricError::NumOverflowing)?,
+        )
+    }
+
+    /**
+    Largest input whose average fill, after fees, is at most `impact_bps` below the spot rate. Rounding is ignored:
+    on curve-K the fill of `input` is `in_K / (in_K + input)` of spot, so the input is
+    `in_K * (impact - fee) / (1 - impact)`. `None` when the fee alone exceeds the impact.
+     */
+    pub fn input_for_price_impact(
+        &self,
+        pool_values: &PoolValues,
+        impact_bps: u64,
+        direction: SwapDirection,
+    ) -> Result<Option<u64>> {
+        let (_big_k, current_x_k, current_y_k, _target_x, _target_y) = *pool_values;
+        let current_in_k = match direction {
+            SwapDirection::XToY => current_x_k,
+            SwapDirection::YToX => current_y_k,
+        };
+        let impact_millionth = (impact_bps as u128).saturating_mul(100);
+        let fee_millionth = self.fee_millionth as u128;
+        if impact_millionth <= fee_millionth || impact_millionth >= MILLION as u128 {
+            return Ok(None);
         }
-        let fee_y = output_before_fee_y
-            .checked_mul(self.fee_millionth)
+        let input = U256::from(current_in_k) * U256::from(impact_millionth - fee_millionth)
+            / U256::from(MILLION as u128 - impact_millionth);
+        Ok(Some(input.try_to_u128().map_or(u64::MAX, |input| {
+            u64::try_from(input).unwrap_or(u64::MAX)
+        })))
+    }
+
+    /**
+    Largest input_x `quote_x_to_y` does not reject for the reserve or the lock. 0 when the pool takes no X at all.
+     */
+    pub fn max_input_x_to_y(&self, current_x: u64, current_y: u64) -> Result<u64> {
+        self.max_input_with_pool_values(
+            &self.get_pool_values_for_quoting(current_x, current_y)?,
+            SwapDirection::XToY,
+            current_x,
+            current_y,
+        )
+    }
+
+    /**
+    Largest input_y `quote_y_to_x` does not reject for the reserve or the lock. 0 when the pool takes no Y at all.
+     */
+    pub fn max_input_y_to_x(&self, current_x: u64, current_y: u64) -> Result<u64> {
+        self.max_input_with_pool_values(
+            &self.get_pool_values_for_quoting(current_x, current_y)?,
+            SwapDirection::YToX,
+            current_x,
+            current_y,
+        )
+    }
+
+    pub fn max_input_with_pool_values(
+        &self,
+        pool_values: &PoolValues,
+        direction: SwapDirection,
+        current_x: u64,
+        current_y: u64,
+    ) -> Result<u64> {
+        let (big_k, current_x_k, current_y_k, target_x, target_y) = *pool_values;
+        let (current_in, target_in, current_in_k, current_out_k, current_out) = match direction {
+            SwapDirection::XToY => (current_x, target_x, current_x_k, current_y_k, current_y),
+            SwapDirection::YToX => (current_y, target_y, current_y_k, current_x_k, current_x),
+        };
+        if current_out == 0 {
+            return Ok(0);
+        }
+
+        // output_before_fee = current_out_K - K / new_in_K stays below current_out as long as
+        // K / new_in_K >= min_new_out_k, i.e. new_in_K <= K / min_new_out_k
+        let min_new_out_k = (current_out_k + 1).saturating_sub(current_out as u128);
+        let mut max_input = match big_k.checked_div(min_new_out_k) {
+            Some(max_new_in_k) => {
+                u64::try_from(max_new_in_k.saturating_sub(current_in_k)).unwrap_or(u64::MAX)
+            }
+            // any new_out_K will do
+            None => u64::MAX,
+        };
+
+        // the input has to move current_in strictly closer to target_in
+        if self.target_y_based_lock {
+            let lock_cap = if current_in < target_in {
+                2 * (target_in - current_in) as u128 - 1
+            } else {
+                0
+            };
+            max_input = max_input.min(u64::try_from(lock_cap).unwrap_or(u64::MAX));
+        }
+
+        Ok(max_input)
+    }
+
+    /**
+    Direction and smallest input whose `marginal_price_after` reaches `target_price`, in `spot_price_x_in_y` units.
+    Selling X moves the price down and selling Y moves it up. Solved on curve-K: the new coordinate is
+    `sqrt(K / target)` for X and `sqrt(K * target)` for Y. `(XToY, 0)` when the pool is already at the target,
+    `None` when the input it takes is above `max_input_*`.
+     */
+    pub fn input_for_target_price(
+        &self,
+        target_price: u128,
+        current_x: u64,
+        current_y: u64,
+        x_decimals: u8,
+        y_decimals: u8,
+    ) -> Result<Option<(SwapDirection, u64)>> {
+        let pool_values = self.get_pool_values_for_quoting(current_x, current_y)?;
+        let (big_k, current_x_k, current_y_k, _target_x, _target_y) = pool_values;
+        let spot_price = self.spot_price_with_pool_values(&pool_values, x_decimals, y_decimals)?;
+        let (direction, current_in_k, new_in_k) = match target_price.cmp(&spot_price) {
+            Ordering::Equal => return Ok(Some((SwapDirection::XToY, 0))),
+            Ordering::Less => (
+                SwapDirection::XToY,
+                current_x_k,
+                min_coordinate_for_price_at_most(
+                    U256::from(big_k),
+                    target_price,
+                    x_decimals,
+                    y_decimals,
+                ),
+            ),
+            Ordering::Greater => (
+                SwapDirection::YToX,
+                current_y_k,
+                min_coordinate_for_price_at_least(
+                    U256::from(big_k),
+                    target_price,
+                    x_decimals,
+                    y_decimals,
+                ),
+            ),
+        };
+        let input = new_in_k
             .ok_or(ObricError::NumOverflowing)?
-            .checked_div(MILLION)
-            .ok_or(ObricError::NumOverflowing)?;
-        let output_after_fee_y = output_before_fee_y
-            .checked_sub(fee_y)
-            .ok_or(ObricError::NumOverflowing)?;
+            .saturating_sub(U256::from(current_in_k));
+
+        let max_input =
+            self.max_input_with_pool_values(&pool_values, direction, current_x, current_y)?;
+        match input
+            .try_to_u128()
+            .and_then(|input| u64::try_from(input).ok())
+        {
+            Some(input) if input <= max_input => Ok(Some((direction, input))),
+            _ => Ok(None),
+        }
+    }
 
-        Ok((output_after_fee_y, fee_y))
+    /**
+    Returns (output_to_user, fee)
+     */
+    pub fn quote_x_to_y(
+        &self,
+        input_x: u64,
+        current_x: u64,
+        current_y: u64,
+    ) -> Result<(u64, u64)> {
+        if input_x == 0 {
+            return Ok((0u64, 0u64));
+        }
+        self.quote_with_pool_values(
+            &self.get_pool_values_for_quoting(current_x, current_y)?,
+            input_x,
+            SwapDirection::XToY,
+            current_x,
+            current_y,
+        )
     }
 
     /**
@@ -187,61 +434,75 @@ impl SSTradingPair {
         if input_y == 0 {
             return Ok((0u64, 0u64));
         }
+        self.quote_with_pool_values(
+            &self.get_pool_values_for_quoting(current_x, current_y)?,
+            input_y,
+            SwapDirection::YToX,
+            current_x,
+            current_y,
+        )
+    }
 
-        let (target_x, target_y) = self.get_target_xy(current_x, current_y)?;
+    /**
+    Quotes from values computed once by `get_pool_values_for_quoting`, for callers quoting many sizes against the
+    same reserves. Returns (output_to_user, fee)
+     */
+    pub fn quote_with_pool_values(
+        &self,
+        pool_values: &PoolValues,
+        input: u64,
+        direction: SwapDirection,
+        current_x: u64,
+        current_y: u64,
+    ) -> Result<(u64, u64)> {
+        if input == 0 {
+            return Ok((0u64, 0u64));
+        }
+        let (big_k, current_x_k, current_y_k, target_x, target_y) = *pool_values;
+        let (current_in, target_in, current_in_k, current_out_k, current_out) = match direction {
+            SwapDirection::XToY => (current_x, target_x, current_x_k, current_y_k, current_y),
+            SwapDirection::YToX => (current_y, target_y, current_y_k, current_x_k, current_x),
+        };
 
         // perform lock-checking
         if self.target_y_based_lock {
-            let allow_swap = abs_diff(current_y + input_y, target_y)? < abs_diff(current_y, target_y)?;
+            let new_in = current_in
+                .checked_add(input)
+                .ok_or(ObricError::NumOverflowing)?;
+            let allow_swap = abs_diff(new_in, target_in)? < abs_diff(current_in, target_in)?;
 
             if !allow_swap {
                 return Ok((0u64, 0u64));
             }
         }
 
-        // 0. get target_x on curve-K
-        let big_k = self.big_k;
-        //target_x_K = sqrt(big_k / p), where p = mult_x / mult_y
-        let target_x_k = (big_k
-            .checked_mul(self.mult_y as u128)
-            .ok_or(ObricError::NumOverflowing)?
-            .checked_div(self.mult_x as u128)
-            .ok_or(ObricError::NumOverflowing)?)
-        .sqrt();
-
-        // 1. find current (x, y) on curve-K
-        let current_x_k = (target_x_k - target_x as u128)
-            .checked_add(current_x as u128)
-            .ok_or(ObricError::NumOverflowing)?;
-        let current_y_k = big_k
-            .checked_div(current_x_k)
-            .ok_or(ObricError::NumOverflowing)?;
-
         // 2. find new (x, y) on curve-K
-        let new_y_k = current_y_k
-            .checked_add(input_y as u128)
+        let new_in_k = current_in_k
+            .checked_add(input as u128)
             .ok_or(ObricError::NumOverflowing)?;
-        let new_x_k = big_k
-            .checked_div(new_y_k)
+        let new_out_k = big_k
+            .checked_div(new_in_k)
             .ok_or(ObricError::NumOverflowing)?;
 
-        let output_before_fee_x: u64 = (current_x_k
-            .checked_sub(new_x_k)
-            .ok_or(ObricError::NumOverflowing)?) as u64;
-        if output_before_fee_x >= current_x {
+        let output_before_fee = u64::try_from(
+            current_out_k
+                .checked_sub(new_out_k)
+                .ok_or(ObricError::NumOverflowing)?,
+        )
+        .unwrap_or(u64::MAX);
+        if output_before_fee >= current_out {
             return Ok((0u64, 0u64));
         }
-
-        let fee_x = output_before_fee_x
+        let fee = output_before_fee
             .checked_mul(self.fee_millionth)
             .ok_or(ObricError::NumOverflowing)?
             .checked_div(MILLION)
             .ok_or(ObricError::NumOverflowing)?;
-        let output_after_fee_x = output_before_fee_x
-            .checked_sub(fee_x)
+        let output_after_fee = output_before_fee
+            .checked_sub(fee)
             .ok_or(ObricError::NumOverflowing)?;
 
-        Ok((output_after_fee_x, fee_x))
+        Ok((output_after_fee, fee))
     }
 }
 
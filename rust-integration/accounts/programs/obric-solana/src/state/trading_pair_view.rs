@@ -0,0 +1,157 @@
+use crate::errors::ObricError;
+use crate::state::SSTradingPair;
+use anchor_lang::{
+    error::{Error, ErrorCode},
+    prelude::*,
+    Discriminator,
+};
+use bytemuck::{Pod, Zeroable};
+
+/// `SSTradingPair` as it lies in the account after the 8-byte discriminator. Borsh packs fields
+/// without padding, so the view is `packed` and can be cast from the account data in place. Bools
+/// are kept as the raw byte, `SSTradingPair::try_from` rejects anything but 0 and 1 like Borsh does.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SSTradingPairView {
+    pub is_initialized: u8,
+
+    pub x_price_feed_id: Pubkey,
+    pub y_price_feed_id: Pubkey,
+
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+
+    pub reference_oracle: Pubkey,
+    pub second_reference_oracle: Pubkey,
+
+    pub bump: u8,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+
+    pub concentration: u64,
+    pub big_k: u128,
+    pub target_x: u64,
+
+    pub cumulative_volume: u64,
+
+    pub mult_x: u64,
+    pub mult_y: u64,
+    pub fee_millionth: u64,
+
+    pub padding1: [u64; 2],
+
+    pub volume_record: [u64; 8],
+    pub volume_time_record: [i64; 8],
+
+    pub version: u16,
+    pub feed_max_age_x: u8,
+    pub feed_max_age_y: u8,
+
+    pub price_decimals: u8,
+
+    pub padding: [u8; 3],
+
+    pub mint_sslp_x: Pubkey,
+    pub mint_sslp_y: Pubkey,
+    pub secondary_price_x: Pubkey,
+    pub secondary_price_y: Pubkey,
+
+    pub whirl_mult: u32,
+    pub whirl_divisor: u16,
+    pub whirl_enabled: u8,
+
+    pub target_y_based_lock: u8,
+    pub reference_target_y: u64,
+
+    pub padding2: [u64; 5],
+}
+
+impl SSTradingPairView {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Borrows the view from the account data, checking the discriminator and length like
+    /// `SSTradingPair::try_deserialize`. Trailing bytes are ignored.
+    pub fn load(data: &[u8]) -> Result<&Self> {
+        if data.len() < SSTradingPair::DISCRIMINATOR.len() {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        let (discriminator, data) = data.split_at(SSTradingPair::DISCRIMINATOR.len());
+        if discriminator != SSTradingPair::DISCRIMINATOR.as_slice() {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        let data = data
+            .get(..Self::LEN)
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        Ok(bytemuck::from_bytes(data))
+    }
+}
+
+impl SSTradingPair {
+    /// Highest `version` laid out like `SSTradingPairView`. An upgrade that carves new fields out
+    /// of the padding bumps the version and adds its own view to `decode`.
+    pub const LATEST_VERSION: u16 = 1;
+
+    /// Decodes a trading pair account with the layout its `version` field names. Versions newer
+    /// than `LATEST_VERSION` fail with `UnsupportedLayoutVersion` rather than being read with a
+    /// layout they may no longer match.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let view = SSTradingPairView::load(data)?;
+        match view.version {
+            0..=Self::LATEST_VERSION => Self::try_from(view),
+            version => Err(error!(ObricError::UnsupportedLayoutVersion)
+                .with_values((version, Self::LATEST_VERSION))),
+        }
+    }
+}
+
+fn to_bool(byte: u8) -> Result<bool> {
+    match byte {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(ErrorCode::AccountDidNotDeserialize.into()),
+    }
+}
+
+impl TryFrom<&SSTradingPairView> for SSTradingPair {
+    type Error = Error;
+
+    fn try_from(view: &SSTradingPairView) -> Result<Self> {
+        Ok(Self {
+            is_initialized: to_bool(view.is_initialized)?,
+            x_price_feed_id: view.x_price_feed_id,
+            y_price_feed_id: view.y_price_feed_id,
+            reserve_x: view.reserve_x,
+            reserve_y: view.reserve_y,
+            reference_oracle: view.reference_oracle,
+            second_reference_oracle: view.second_reference_oracle,
+            bump: view.bump,
+            mint_x: view.mint_x,
+            mint_y: view.mint_y,
+            concentration: view.concentration,
+            big_k: view.big_k,
+            target_x: view.target_x,
+            cumulative_volume: view.cumulative_volume,
+            mult_x: view.mult_x,
+            mult_y: view.mult_y,
+            fee_millionth: view.fee_millionth,
+            padding1: view.padding1,
+            volume_record: view.volume_record,
+            volume_time_record: view.volume_time_record,
+            version: view.version,
+            feed_max_age_x: view.feed_max_age_x,
+            feed_max_age_y: view.feed_max_age_y,
+            price_decimals: view.price_decimals,
+            padding: view.padding,
+            mint_sslp_x: view.mint_sslp_x,
+            mint_sslp_y: view.mint_sslp_y,
+            secondary_price_x: view.secondary_price_x,
+            secondary_price_y: view.secondary_price_y,
+            whirl_mult: view.whirl_mult,
+            whirl_divisor: view.whirl_divisor,
+            whirl_enabled: to_bool(view.whirl_enabled)?,
+            target_y_based_lock: to_bool(view.target_y_based_lock)?,
+            reference_target_y: view.reference_target_y,
+            padding2: view.padding2,
+        })
+    }
+}
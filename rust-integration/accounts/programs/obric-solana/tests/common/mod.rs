@@ -0,0 +1,108 @@
+//! Pool strategies and helpers shared by the property suites, each of which uses a subset.
+#![allow(dead_code)]
+
+use num::integer::Roots;
+use obric_solana::state::SSTradingPair;
+use proptest::{prelude::*, test_runner::FileFailurePersistence};
+
+/// Persists the shrunk failures of one suite to `path`, which is checked in so every past
+/// counterexample is replayed before new cases are generated.
+pub fn config(path: &'static str) -> ProptestConfig {
+    ProptestConfig {
+        failure_persistence: Some(Box::new(FileFailurePersistence::Direct(path))),
+        ..ProptestConfig::default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Pool {
+    pub pair: SSTradingPair,
+    pub current_x: u64,
+    pub current_y: u64,
+}
+
+impl Pool {
+    pub fn try_quote(
+        &self,
+        input: u64,
+        x_to_y: bool,
+    ) -> Result<(u64, u64), anchor_lang::error::Error> {
+        if x_to_y {
+            self.pair
+                .quote_x_to_y(input, self.current_x, self.current_y)
+        } else {
+            self.pair
+                .quote_y_to_x(input, self.current_x, self.current_y)
+        }
+    }
+
+    pub fn quote(&self, input: u64, x_to_y: bool) -> (u64, u64) {
+        self.try_quote(input, x_to_y)
+            .expect("generated pools always quote")
+    }
+
+    /// Moves the reserves the way the swap instruction does: the fee stays in the pool.
+    pub fn swap(&self, input: u64, x_to_y: bool) -> (Self, u64) {
+        let (output, _fee) = self.quote(input, x_to_y);
+        let mut next = self.clone();
+        if x_to_y {
+            next.current_x += input;
+            next.current_y -= output;
+        } else {
+            next.current_y += input;
+            next.current_x -= output;
+        }
+        (next, output)
+    }
+}
+
+/// Pools whose curve is built from `concentration * target_x`, as the admin instruction sets it.
+pub fn pool(lock: bool) -> impl Strategy<Value = Pool> {
+    (
+        1u64..=1_000,
+        1_000u64..=1_000_000_000_000,
+        1u64..=1_000_000_000,
+        1u64..=1_000_000_000,
+        0u64..=20_000,
+        0u64..=200,
+        1_000u64..=1_000_000_000_000_000,
+    )
+        .prop_filter_map(
+            "curve must fit in u128 and sit above target_x",
+            move |(concentration, target_x, mult_x, mult_y, fee_millionth, x_pct, current_y)| {
+                let target_x_k = (concentration as u128).checked_mul(target_x as u128)?;
+                let big_k = target_x_k
+                    .checked_mul(target_x_k)?
+                    .checked_mul(mult_x as u128)?
+                    / mult_y as u128;
+                let recomputed_target_x_k =
+                    (big_k.checked_mul(mult_y as u128)? / mult_x as u128).sqrt();
+                if big_k == 0 || recomputed_target_x_k < target_x as u128 {
+                    return None;
+                }
+
+                let pair = SSTradingPair {
+                    concentration,
+                    big_k,
+                    target_x,
+                    mult_x,
+                    mult_y,
+                    fee_millionth,
+                    target_y_based_lock: lock,
+                    ..Default::default()
+                };
+                let current_x = u64::try_from(target_x as u128 * x_pct as u128 / 100).ok()?;
+                pair.get_target_xy(current_x, current_y).ok()?;
+
+                Some(Pool {
+                    pair,
+                    current_x,
+                    current_y,
+                })
+            },
+        )
+}
+
+pub fn input() -> impl Strategy<Value = u64> {
+    (0u32..48, any::<u64>()).prop_map(|(bits, v)| v >> (63 - bits.min(63)))
+}
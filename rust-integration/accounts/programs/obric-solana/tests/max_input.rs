@@ -0,0 +1,37 @@
+//! `max_input_*` of the v2 `SSTradingPair` against the quotes it accepts.
+
+mod common;
+
+use common::pool;
+use obric_solana::state::SwapDirection;
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/max_input.txt"))]
+
+    #[test]
+    fn max_input_is_the_largest_accepted(pool in any::<bool>().prop_flat_map(pool), x_to_y: bool) {
+        let pool_values = pool.pair.get_pool_values_for_quoting(pool.current_x, pool.current_y).unwrap();
+        let direction = if x_to_y { SwapDirection::XToY } else { SwapDirection::YToX };
+        let max_input = pool
+            .pair
+            .max_input_with_pool_values(&pool_values, direction, pool.current_x, pool.current_y)
+            .unwrap();
+        if max_input < u64::MAX {
+            prop_assert_eq!(pool.quote(max_input + 1, x_to_y), (0, 0));
+        }
+        if max_input > 0 {
+            // accepted, so the quote pays out the curve output, which is only zero for dust
+            let (big_k, current_x_k, current_y_k, _target_x, _target_y) = pool_values;
+            let (current_in_k, current_out_k) = if x_to_y {
+                (current_x_k, current_y_k)
+            } else {
+                (current_y_k, current_x_k)
+            };
+            let new_in_k = current_in_k + max_input as u128;
+            let curve_output = current_out_k - big_k / new_in_k;
+            let (output, fee) = pool.quote(max_input, x_to_y);
+            prop_assert_eq!((output + fee) as u128, curve_output);
+        }
+    }
+}
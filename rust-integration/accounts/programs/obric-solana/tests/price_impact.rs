@@ -0,0 +1,37 @@
+//! `input_for_price_impact` of the v2 `SSTradingPair` against the fills it sizes.
+
+mod common;
+
+use common::pool;
+use num::BigUint;
+use obric_solana::{math::PRICE_DECIMALS, state::SwapDirection};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/price_impact.txt"))]
+
+    #[test]
+    fn impact_input_stays_within_threshold(pool in pool(false), impact_bps in 1u64..=500, x_to_y: bool) {
+        let pool_values = pool.pair.get_pool_values_for_quoting(pool.current_x, pool.current_y).unwrap();
+        let spot = pool.pair.spot_price_with_pool_values(&pool_values, 0, 0).unwrap();
+        let direction = if x_to_y { SwapDirection::XToY } else { SwapDirection::YToX };
+        let Some(input) = pool.pair.input_for_price_impact(&pool_values, impact_bps, direction).unwrap() else {
+            prop_assert!(impact_bps * 100 <= pool.pair.fee_millionth);
+            return Ok(());
+        };
+        let (output, _fee) = pool.quote(input, x_to_y);
+        if output == 0 {
+            return Ok(());
+        }
+        // the fill at that size is no worse than the threshold, give or take the rounding of a few units.
+        // current_y_K is rounded down, which is worth a unit of input and many units of X when Y is cheap
+        let scale = BigUint::from(10u128.pow(PRICE_DECIMALS));
+        let (output, input) = (BigUint::from(output + 3), BigUint::from(input.saturating_sub(2)));
+        let kept = BigUint::from(10_000 - impact_bps);
+        if x_to_y {
+            prop_assert!(output * &scale * 10_000u32 >= input * BigUint::from(spot) * kept);
+        } else {
+            prop_assert!(output * BigUint::from(spot + 1) * 10_000u32 >= input * scale * kept);
+        }
+    }
+}
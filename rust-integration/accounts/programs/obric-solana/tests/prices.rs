@@ -0,0 +1,40 @@
+//! Spot and marginal prices of the v2 `SSTradingPair` against the fills it quotes.
+
+mod common;
+
+use common::pool;
+use obric_solana::{math::PRICE_DECIMALS, state::SwapDirection};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/prices.txt"))]
+
+    #[test]
+    fn prices_bracket_the_average_fill(pool in pool(false), amount in 1u64..=u32::MAX as u64) {
+        let scale = 10u128.pow(PRICE_DECIMALS);
+        let spot = pool.pair.spot_price_x_in_y(pool.current_x, 0, 0).unwrap();
+        let after_sell = pool
+            .pair
+            .marginal_price_after(amount, SwapDirection::XToY, pool.current_x, 0, 0)
+            .unwrap();
+        let after_buy = pool
+            .pair
+            .marginal_price_after(amount, SwapDirection::YToX, pool.current_x, 0, 0)
+            .unwrap();
+        prop_assert!(after_sell <= spot && spot <= after_buy);
+
+        // current_y_K and the new coordinate are rounded down, the gross output can be a unit above the curve
+        // and 2 units under it
+        let (output, fee) = pool.quote(amount, true);
+        let gross = (output + fee) as u128;
+        if gross > 0 {
+            prop_assert!((gross - 1) * scale / amount as u128 <= spot);
+            prop_assert!((gross + 2) * scale / amount as u128 >= after_sell);
+        }
+        let (output, fee) = pool.quote(amount, false);
+        let gross = (output + fee) as u128;
+        if let Some(average) = (amount as u128 * scale).checked_div(gross.saturating_sub(1)) {
+            prop_assert!(average >= spot);
+        }
+    }
+}
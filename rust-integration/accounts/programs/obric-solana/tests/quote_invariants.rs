@@ -0,0 +1,114 @@
+//! Property tests for the v2 `SSTradingPair` quote math.
+//!
+//! Failing cases are shrunk and persisted under `proptest-regressions/`, one file per suite, which is
+//! checked in so every past counterexample is replayed before new cases are generated.
+
+mod common;
+
+use common::{input, pool, Pool};
+use obric_solana::{consts::MILLION, state::SSTradingPair};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/quote_invariants.txt"))]
+
+    #[test]
+    fn output_is_monotone_in_input(pool in pool(false), a in input(), b in input(), x_to_y: bool) {
+        let (small, large) = (a.min(b), a.max(b));
+        let (small_out, small_fee) = pool.quote(small, x_to_y);
+        let (large_out, large_fee) = pool.quote(large, x_to_y);
+        // quotes that hit the reserve are rejected with a zero output
+        if large_out > 0 {
+            prop_assert!(small_out <= large_out);
+            prop_assert!(small_out + small_fee <= large_out + large_fee);
+        }
+    }
+
+    #[test]
+    fn output_never_reaches_reserve(pool in pool(false), amount in input(), x_to_y: bool) {
+        let (output, fee) = pool.quote(amount, x_to_y);
+        let reserve = if x_to_y { pool.current_y } else { pool.current_x };
+        prop_assert!(output == 0 || output + fee < reserve);
+    }
+
+    #[test]
+    fn round_trip_never_profits(pool in pool(false), amount in input(), x_to_y: bool) {
+        let (after_first, first_out) = pool.swap(amount, x_to_y);
+        if first_out == 0 {
+            return Ok(());
+        }
+        // the pool may now be short of its target value, in which case the return leg is rejected
+        let Ok((back, _fee)) = after_first.try_quote(first_out, !x_to_y) else {
+            return Ok(());
+        };
+        // Each leg floors its new curve coordinate, as the program does, so it can pay out up to one base
+        // unit of its output token over the curve. The round trip can then return one unit of each token
+        // more than the curve would: a unit of the input, and a unit of the first output, which buys
+        // in_K / out_K of the input back at the price after the first leg. That is many units when the
+        // first output is the dearer token, see `round_trip_can_return_a_unit_of_each_token`.
+        let (_big_k, current_x_k, current_y_k, _target_x, _target_y) = after_first
+            .pair
+            .get_pool_values_for_quoting(after_first.current_x, after_first.current_y)
+            .unwrap();
+        let (in_k, out_k) = if x_to_y {
+            (current_x_k, current_y_k)
+        } else {
+            (current_y_k, current_x_k)
+        };
+        let output_unit = in_k.div_ceil(out_k.max(1));
+        prop_assert!(
+            back as u128 <= amount as u128 + 1 + output_unit,
+            "{} in, {} back, a unit of the first output buys {}",
+            amount,
+            back,
+            output_unit
+        );
+    }
+
+    #[test]
+    fn fee_matches_fee_millionth(pool in pool(false), amount in input(), x_to_y: bool) {
+        let (output, fee) = pool.quote(amount, x_to_y);
+        let gross = (output + fee) as u128;
+        let charged = fee as u128 * MILLION as u128;
+        let exact = gross * pool.pair.fee_millionth as u128;
+        // fee is rounded down
+        prop_assert!(charged <= exact && exact < charged + MILLION as u128);
+    }
+
+    #[test]
+    fn locked_direction_yields_nothing(pool in pool(true), amount in 1u64..=u32::MAX as u64, x_to_y: bool) {
+        let (target_x, target_y) = pool.pair.get_target_xy(pool.current_x, pool.current_y).unwrap();
+        let (current, target) = if x_to_y {
+            (pool.current_x, target_x)
+        } else {
+            (pool.current_y, target_y)
+        };
+        let moves_toward_target = (current + amount).abs_diff(target) < current.abs_diff(target);
+
+        let (output, fee) = pool.quote(amount, x_to_y);
+        if !moves_toward_target {
+            prop_assert_eq!((output, fee), (0, 0));
+        }
+    }
+}
+
+/// Why `round_trip_never_profits` allows a unit of each token: an X unit is worth about a dozen Y
+/// units on this curve, so the unit of X the first leg rounds up buys 9 Y more than were paid in.
+#[test]
+fn round_trip_can_return_a_unit_of_each_token() {
+    let pool = Pool {
+        pair: SSTradingPair {
+            concentration: 1,
+            big_k: 1_000,
+            target_x: 1_000,
+            mult_x: 1,
+            mult_y: 1_000,
+            ..Default::default()
+        },
+        current_x: 500,
+        current_y: 1_000_000,
+    };
+    let (after_first, first_out) = pool.swap(100, false);
+    assert_eq!(first_out, 491);
+    assert_eq!(after_first.quote(first_out, true), (109, 0));
+}
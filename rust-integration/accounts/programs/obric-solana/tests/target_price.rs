@@ -0,0 +1,54 @@
+//! `input_for_target_price` of the v2 `SSTradingPair` against a brute-force search.
+
+mod common;
+
+use common::pool;
+use obric_solana::state::SwapDirection;
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(common::config("proptest-regressions/target_price.txt"))]
+
+    #[test]
+    fn target_price_input_matches_brute_force(
+        pool in any::<bool>().prop_flat_map(pool),
+        steps in 1u64..=2_000,
+        x_to_y: bool,
+        x_decimals in 0u8..=9,
+        y_decimals in 0u8..=9,
+    ) {
+        let direction = if x_to_y { SwapDirection::XToY } else { SwapDirection::YToX };
+        let marginal = |input| {
+            pool.pair
+                .marginal_price_after(input, direction, pool.current_x, x_decimals, y_decimals)
+                .unwrap()
+        };
+        let target = marginal(steps);
+        let solved = pool
+            .pair
+            .input_for_target_price(target, pool.current_x, pool.current_y, x_decimals, y_decimals)
+            .unwrap();
+        let spot = pool.pair.spot_price_x_in_y(pool.current_x, x_decimals, y_decimals).unwrap();
+        if target == spot {
+            prop_assert_eq!(solved, Some((SwapDirection::XToY, 0)));
+            return Ok(());
+        }
+        // the floored spot can sit on the other side of a target only a few units away
+        if (target < spot) != x_to_y {
+            return Ok(());
+        }
+
+        let reaches = |input| if x_to_y { marginal(input) <= target } else { marginal(input) >= target };
+        let expected = (0..=steps).find(|&input| reaches(input)).unwrap();
+        let max_input = if x_to_y {
+            pool.pair.max_input_x_to_y(pool.current_x, pool.current_y).unwrap()
+        } else {
+            pool.pair.max_input_y_to_x(pool.current_x, pool.current_y).unwrap()
+        };
+        if expected <= max_input {
+            prop_assert_eq!(solved, Some((direction, expected)));
+        } else {
+            prop_assert_eq!(solved, None);
+        }
+    }
+}
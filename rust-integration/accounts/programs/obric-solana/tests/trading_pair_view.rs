@@ -0,0 +1,88 @@
+//! `SSTradingPairView` against the Borsh decoder on arbitrary account data: both accept the same
+//! bytes and decode them to the same pair. `SSTradingPair::decode` reads the same layout for every
+//! version up to `LATEST_VERSION` and refuses newer ones.
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator};
+use obric_solana::errors::ObricError;
+use obric_solana::state::{SSTradingPair, SSTradingPairView};
+use proptest::prelude::*;
+
+const ACCOUNT_LEN: usize = 666;
+
+/// Account data from random bytes. Most random bytes are not valid bools, so half of the cases get
+/// valid ones written in to reach the successful decode.
+fn account_data() -> impl Strategy<Value = Vec<u8>> {
+    (
+        proptest::collection::vec(any::<u8>(), SSTradingPairView::LEN),
+        proptest::option::of(any::<[bool; 3]>()),
+        0usize..16,
+    )
+        .prop_map(|(mut body, bools, trailing)| {
+            if let Some([is_initialized, whirl_enabled, target_y_based_lock]) = bools {
+                let view: &mut SSTradingPairView = bytemuck::from_bytes_mut(&mut body);
+                view.is_initialized = is_initialized as u8;
+                view.whirl_enabled = whirl_enabled as u8;
+                view.target_y_based_lock = target_y_based_lock as u8;
+            }
+            let mut data = SSTradingPair::DISCRIMINATOR.to_vec();
+            data.extend(body);
+            data.extend(vec![0xAB; trailing]);
+            data
+        })
+}
+
+fn decode_with_view(data: &[u8]) -> anchor_lang::Result<SSTradingPair> {
+    SSTradingPair::try_from(SSTradingPairView::load(data)?)
+}
+
+fn serialize(pair: &SSTradingPair) -> Vec<u8> {
+    let mut data = vec![];
+    pair.try_serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn view_covers_the_whole_account() {
+    assert_eq!(SSTradingPairView::LEN + 8, ACCOUNT_LEN);
+    assert_eq!(serialize(&SSTradingPair::default()).len(), ACCOUNT_LEN);
+}
+
+proptest! {
+    #[test]
+    fn view_agrees_with_borsh(data in account_data()) {
+        let borsh = SSTradingPair::try_deserialize(&mut data.as_slice());
+        let view = decode_with_view(&data);
+        prop_assert_eq!(borsh.is_ok(), view.is_ok());
+        if let (Ok(borsh), Ok(view)) = (borsh, view) {
+            prop_assert_eq!(serialize(&view), serialize(&borsh));
+            prop_assert_eq!(&serialize(&view)[..], &data[..ACCOUNT_LEN]);
+        }
+    }
+
+    #[test]
+    fn view_rejects_what_borsh_rejects(mut data in account_data(), cut in 0usize..ACCOUNT_LEN, flip in any::<u8>()) {
+        // a short account, or a discriminator of another account type
+        if flip % 2 == 0 {
+            data.truncate(cut);
+        } else {
+            data[flip as usize % 8] ^= flip | 1;
+        }
+        prop_assert!(SSTradingPair::try_deserialize(&mut data.as_slice()).is_err());
+        prop_assert!(decode_with_view(&data).is_err());
+    }
+
+    #[test]
+    fn decode_dispatches_on_version(mut data in account_data(), version in any::<u16>()) {
+        bytemuck::from_bytes_mut::<SSTradingPairView>(&mut data[8..ACCOUNT_LEN]).version = version;
+        let decoded = SSTradingPair::decode(&data);
+        if version <= SSTradingPair::LATEST_VERSION {
+            let view = decode_with_view(&data);
+            prop_assert_eq!(decoded.is_ok(), view.is_ok());
+            if let (Ok(decoded), Ok(view)) = (decoded, view) {
+                prop_assert_eq!(serialize(&decoded), serialize(&view));
+            }
+        } else {
+            prop_assert_eq!(decoded.unwrap_err(), ObricError::UnsupportedLayoutVersion.into());
+        }
+    }
+}
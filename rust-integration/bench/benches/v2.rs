@@ -0,0 +1,172 @@
+//! `ObricV2Amm` hot paths against offline fixtures. `update` is measured once in steady state and
+//! once for the first tick, which also unpacks the two mints. The quote ladder prices a range of
+//! sizes the way a router does after each `update`.
+
+use anchor_lang::AccountDeserialize;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use jupiter_amm_interface::{
+    Amm, AmmContext, ClockRef, KeyedAccount, QuoteParams, SwapMode, SwapParams,
+};
+use obric_bench::{v2_pool, Oracle, NOW};
+use obric_solana::state::{SSTradingPair, SSTradingPairView, SwapDirection};
+use obric_v2_sdk::obric_v2_amm::ObricV2Amm;
+use solana_sdk::{clock::Clock, pubkey::Pubkey};
+
+fn amm_context() -> AmmContext {
+    AmmContext {
+        clock_ref: ClockRef::from(Clock {
+            unix_timestamp: NOW,
+            ..Clock::default()
+        }),
+    }
+}
+
+fn v2(c: &mut Criterion) {
+    let amm_context = amm_context();
+    let mut group = c.benchmark_group("v2");
+
+    for (name, oracle) in [("pyth", Oracle::Pyth), ("doves", Oracle::Doves)] {
+        let fixture = v2_pool(oracle);
+        let keyed_account = KeyedAccount {
+            key: fixture.key,
+            account: fixture.account.clone(),
+            params: None,
+        };
+        let fresh = ObricV2Amm::from_keyed_account(&keyed_account, &amm_context).unwrap();
+        let mut updated = fresh.clone();
+        updated.update(&fixture.accounts).unwrap();
+
+        group.bench_function(format!("update, first tick, {name}"), |b| {
+            b.iter_batched(
+                || fresh.clone(),
+                |mut amm| {
+                    amm.update(black_box(&fixture.accounts)).unwrap();
+                    amm
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(format!("update, {name}"), |b| {
+            b.iter(|| updated.update(black_box(&fixture.accounts)).unwrap())
+        });
+    }
+
+    let fixture = v2_pool(Oracle::Pyth);
+    let keyed_account = KeyedAccount {
+        key: fixture.key,
+        account: fixture.account.clone(),
+        params: None,
+    };
+    group.bench_function("decode pair, borsh", |b| {
+        b.iter(|| {
+            SSTradingPair::try_deserialize(&mut black_box(&fixture.account.data[..])).unwrap()
+        })
+    });
+    group.bench_function("decode pair, view", |b| {
+        b.iter(|| {
+            SSTradingPair::try_from(
+                SSTradingPairView::load(black_box(&fixture.account.data)).unwrap(),
+            )
+            .unwrap()
+        })
+    });
+    group.bench_function("from_keyed_account", |b| {
+        b.iter(|| ObricV2Amm::from_keyed_account(black_box(&keyed_account), &amm_context).unwrap())
+    });
+
+    let mut amm = ObricV2Amm::from_keyed_account(&keyed_account, &amm_context).unwrap();
+    amm.update(&fixture.accounts).unwrap();
+    let (mint_x, mint_y) = (amm.state.mint_x, amm.state.mint_y);
+    // 1 SOL and 150 USDC
+    for (name, input_mint, output_mint, amount) in [
+        ("quote x to y", mint_x, mint_y, 1_000_000_000),
+        ("quote y to x", mint_y, mint_x, 150_000_000),
+    ] {
+        let quote_params = QuoteParams {
+            amount,
+            input_mint,
+            output_mint,
+            swap_mode: SwapMode::ExactIn,
+        };
+        assert!(amm.quote(&quote_params).unwrap().out_amount > 0);
+        group.bench_function(name, |b| {
+            b.iter(|| amm.quote(black_box(&quote_params)).unwrap())
+        });
+    }
+
+    group.bench_function("clone_amm", |b| b.iter(|| black_box(amm.clone_amm())));
+
+    let jupiter_program_id = Pubkey::new_unique();
+    let swap_params = SwapParams {
+        swap_mode: SwapMode::ExactIn,
+        in_amount: 1_000_000_000,
+        out_amount: 0,
+        source_mint: mint_x,
+        destination_mint: mint_y,
+        source_token_account: Pubkey::new_unique(),
+        destination_token_account: Pubkey::new_unique(),
+        token_transfer_authority: Pubkey::new_unique(),
+        quote_mint_to_referrer: None,
+        jupiter_program_id: &jupiter_program_id,
+        missing_dynamic_accounts_as_default: false,
+    };
+    group.bench_function("get_swap_and_account_metas", |b| {
+        b.iter(|| {
+            amm.get_swap_and_account_metas(black_box(&swap_params))
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+/// One `quote` per size with and without the curve values cached by `update`, and one `quote_many`
+/// call.
+fn v2_quote_ladder(c: &mut Criterion) {
+    let fixture = v2_pool(Oracle::Pyth);
+    let keyed_account = KeyedAccount {
+        key: fixture.key,
+        account: fixture.account.clone(),
+        params: None,
+    };
+    let mut cached = ObricV2Amm::from_keyed_account(&keyed_account, &amm_context()).unwrap();
+    cached.update(&fixture.accounts).unwrap();
+    let uncached = ObricV2Amm {
+        pool_values: None,
+        ..cached.clone()
+    };
+    // 0.01 to 100 SOL
+    let inputs: Vec<u64> = (1..=100u64).map(|i| i * i * 10_000_000).collect();
+
+    let mut group = c.benchmark_group("v2 quote 100 sizes x to y");
+    for (name, amm) in [("quote, uncached", &uncached), ("quote, cached", &cached)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for &amount in &inputs {
+                    black_box(
+                        amm.quote(&QuoteParams {
+                            amount: black_box(amount),
+                            input_mint: amm.state.mint_x,
+                            output_mint: amm.state.mint_y,
+                            swap_mode: SwapMode::ExactIn,
+                        })
+                        .unwrap(),
+                    );
+                }
+            })
+        });
+    }
+    group.bench_function("quote_many", |b| {
+        b.iter(|| {
+            black_box(
+                cached
+                    .quote_many(black_box(&inputs), SwapDirection::XToY)
+                    .unwrap(),
+            )
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, v2, v2_quote_ladder);
+criterion_main!(benches);
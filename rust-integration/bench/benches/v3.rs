@@ -0,0 +1,146 @@
+//! `ObricV3Amm` hot paths against offline fixtures. The quote ladder prices a range of sizes the
+//! way a router does after each `update`.
+
+use anchor_lang::AccountDeserialize;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use jupiter_amm_interface_v3::{Amm, KeyedAccount, QuoteParams, SwapParams};
+use obric_bench::v3_pool;
+use obric_solana_v3::state::{SSTradingPair, SSTradingPairView, SwapDirection};
+use obric_v3_sdk::obric_v3_amm::ObricV3Amm;
+use solana_sdk::pubkey::Pubkey;
+
+fn v3(c: &mut Criterion) {
+    let fixture = v3_pool();
+    let keyed_account = KeyedAccount {
+        key: fixture.key,
+        account: fixture.account.clone(),
+        params: None,
+    };
+    let mut group = c.benchmark_group("v3");
+
+    group.bench_function("decode pair, borsh", |b| {
+        b.iter(|| {
+            SSTradingPair::try_deserialize(&mut black_box(&fixture.account.data[..])).unwrap()
+        })
+    });
+    group.bench_function("decode pair, view", |b| {
+        b.iter(|| {
+            SSTradingPair::try_from(
+                SSTradingPairView::load(black_box(&fixture.account.data)).unwrap(),
+            )
+            .unwrap()
+        })
+    });
+    group.bench_function("from_keyed_account", |b| {
+        b.iter(|| ObricV3Amm::from_keyed_account(black_box(&keyed_account)).unwrap())
+    });
+
+    // every tick re-reads the pool, both feeds and both Larix reserves
+    group.bench_function("update", |b| {
+        b.iter_batched(
+            || ObricV3Amm::from_keyed_account(&keyed_account).unwrap(),
+            |mut amm| {
+                amm.update(black_box(&fixture.accounts)).unwrap();
+                amm
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    let mut amm = ObricV3Amm::from_keyed_account(&keyed_account).unwrap();
+    amm.update(&fixture.accounts).unwrap();
+    let (mint_x, mint_y) = (amm.state.mint_x, amm.state.mint_y);
+    // 1 SOL and 150 USDC
+    for (name, input_mint, output_mint, in_amount) in [
+        ("quote x to y", mint_x, mint_y, 1_000_000_000),
+        ("quote y to x", mint_y, mint_x, 150_000_000),
+    ] {
+        let quote_params = QuoteParams {
+            in_amount,
+            input_mint,
+            output_mint,
+        };
+        assert!(amm.quote(&quote_params).unwrap().out_amount > 0);
+        group.bench_function(name, |b| {
+            b.iter(|| amm.quote(black_box(&quote_params)).unwrap())
+        });
+    }
+
+    group.bench_function("clone_amm", |b| b.iter(|| black_box(amm.clone_amm())));
+
+    let jupiter_program_id = Pubkey::new_unique();
+    for (name, source_mint, destination_mint) in [
+        ("get_swap_and_account_metas x to y", mint_x, mint_y),
+        ("get_swap_and_account_metas y to x", mint_y, mint_x),
+    ] {
+        let swap_params = SwapParams {
+            in_amount: 1_000_000_000,
+            source_mint,
+            destination_mint,
+            source_token_account: Pubkey::new_unique(),
+            destination_token_account: Pubkey::new_unique(),
+            token_transfer_authority: Pubkey::new_unique(),
+            open_order_address: None,
+            quote_mint_to_referrer: None,
+            jupiter_program_id: &jupiter_program_id,
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                amm.get_swap_and_account_metas(black_box(&swap_params))
+                    .unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// One `quote` per size with and without the curve values cached by `update`, and one `quote_many`
+/// call.
+fn v3_quote_ladder(c: &mut Criterion) {
+    let fixture = v3_pool();
+    let keyed_account = KeyedAccount {
+        key: fixture.key,
+        account: fixture.account.clone(),
+        params: None,
+    };
+    let mut cached = ObricV3Amm::from_keyed_account(&keyed_account).unwrap();
+    cached.update(&fixture.accounts).unwrap();
+    let uncached = ObricV3Amm {
+        pool_values: None,
+        ..cached.clone()
+    };
+    // 0.01 to 100 SOL
+    let inputs: Vec<u64> = (1..=100u64).map(|i| i * i * 10_000_000).collect();
+
+    let mut group = c.benchmark_group("v3 quote 100 sizes x to y");
+    for (name, amm) in [("quote, uncached", &uncached), ("quote, cached", &cached)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for &in_amount in &inputs {
+                    black_box(
+                        amm.quote(&QuoteParams {
+                            in_amount: black_box(in_amount),
+                            input_mint: amm.state.mint_x,
+                            output_mint: amm.state.mint_y,
+                        })
+                        .unwrap(),
+                    );
+                }
+            })
+        });
+    }
+    group.bench_function("quote_many", |b| {
+        b.iter(|| {
+            black_box(
+                cached
+                    .quote_many(black_box(&inputs), SwapDirection::XToY)
+                    .unwrap(),
+            )
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, v3, v3_quote_ladder);
+criterion_main!(benches);
@@ -0,0 +1,244 @@
+//! Offline fixtures for the benchmarks, the tests and the fuzz seed corpus: one pool per sdk and
+//! every account its `update` reads, built from the real layouts so a run needs no RPC node and
+//! always measures the same state.
+//!
+//! `cargo bench -p obric-bench` runs the criterion benchmarks of the sdk hot paths and quote
+//! ladders in `benches/`.
+
+use anchor_lang::AccountSerialize;
+use pyth_sdk_solana::state::{AccountType, PriceStatus, SolanaPriceAccount, MAGIC, VERSION_2};
+use solana_sdk::{account::Account, program_pack::Pack, pubkey, pubkey::Pubkey};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use std::collections::HashMap;
+
+pub const PYTH_PROGRAM_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+
+/// 2023-11-14T22:13:20Z, every feed is published at this time.
+pub const NOW: i64 = 1_700_000_000;
+
+/// A trading pair and the accounts `get_accounts_to_update` lists for it, keyed by address.
+pub struct Fixture {
+    pub key: Pubkey,
+    pub account: Account,
+    pub accounts: HashMap<Pubkey, Account>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Oracle {
+    Pyth,
+    Doves,
+}
+
+pub fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+pub fn anchor_account<T: AccountSerialize>(owner: Pubkey, value: &T) -> Account {
+    let mut data = vec![];
+    value
+        .try_serialize(&mut data)
+        .expect("fixture account serializes");
+    account(owner, data)
+}
+
+pub fn packed_account<T: Pack>(owner: Pubkey, value: T) -> Account {
+    let mut data = vec![0; T::LEN];
+    T::pack(value, &mut data).expect("fixture account packs");
+    account(owner, data)
+}
+
+pub fn token_account(mint: Pubkey, amount: u64) -> Account {
+    packed_account(
+        spl_token::id(),
+        TokenAccount {
+            mint,
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        },
+    )
+}
+
+pub fn mint(decimals: u8) -> Account {
+    packed_account(
+        spl_token::id(),
+        Mint {
+            decimals,
+            is_initialized: true,
+            ..Default::default()
+        },
+    )
+}
+
+pub fn pyth_price(price: i64, expo: i32) -> Account {
+    let mut feed: SolanaPriceAccount = bytemuck::Zeroable::zeroed();
+    feed.magic = MAGIC;
+    feed.ver = VERSION_2;
+    feed.atype = AccountType::Price as u32;
+    feed.size = std::mem::size_of::<SolanaPriceAccount>() as u32;
+    feed.expo = expo;
+    feed.timestamp = NOW;
+    feed.agg.price = price;
+    feed.agg.status = PriceStatus::Trading;
+    feed.prev_price = price;
+    feed.prev_timestamp = NOW;
+    account(PYTH_PROGRAM_ID, bytemuck::bytes_of(&feed).to_vec())
+}
+
+pub fn doves_price(price: u64, expo: i8) -> Account {
+    anchor_account(
+        doves_cpi::ID,
+        &doves_cpi::PriceFeed {
+            pair: [0; 32],
+            signer: [0; 33],
+            price,
+            expo,
+            timestamp: NOW,
+            bump: 0,
+        },
+    )
+}
+
+/// A reserve with `available` and `borrowed` liquidity and `collateral` minted against them.
+pub fn larix_reserve(mint: Pubkey, available: u64, borrowed: u64, collateral: u64) -> Account {
+    let mut reserve = larix_lending::state::reserve::Reserve {
+        version: larix_lending::state::PROGRAM_VERSION,
+        ..Default::default()
+    };
+    reserve.liquidity.mint_pubkey = mint;
+    reserve.liquidity.available_amount = available;
+    reserve.liquidity.borrowed_amount_wads = larix_lending::math::Decimal::from(borrowed);
+    reserve.collateral.mint_total_supply = collateral;
+    reserve.liquidity.supply_pubkey = Pubkey::new_unique();
+    reserve.collateral.mint_pubkey = Pubkey::new_unique();
+    reserve.collateral.supply_pubkey = Pubkey::new_unique();
+    packed_account(larix_lending::id(), reserve)
+}
+
+/// A SOL/USDC v2 pool at its target, 150 USDC per SOL, with 1,000 SOL and 150,000 USDC in reserve.
+pub fn v2_pool(oracle: Oracle) -> Fixture {
+    let concentration = 50u128;
+    let target_x = 1_000_000_000_000u64;
+    let (mult_x, mult_y) = (150_000_000u64, 1_000_000_000u64);
+    let target_x_k = concentration * target_x as u128;
+    let mut pair = obric_solana::state::SSTradingPair {
+        is_initialized: true,
+        x_price_feed_id: Pubkey::new_unique(),
+        y_price_feed_id: Pubkey::new_unique(),
+        reserve_x: Pubkey::new_unique(),
+        reserve_y: Pubkey::new_unique(),
+        reference_oracle: Pubkey::new_unique(),
+        mint_x: Pubkey::new_unique(),
+        mint_y: Pubkey::new_unique(),
+        concentration: concentration as u64,
+        big_k: target_x_k * target_x_k * mult_x as u128 / mult_y as u128,
+        target_x,
+        mult_x,
+        mult_y,
+        fee_millionth: 300,
+        feed_max_age_x: 30,
+        feed_max_age_y: 30,
+        price_decimals: 6,
+        ..Default::default()
+    };
+    let (price_x, price_y) = match oracle {
+        Oracle::Pyth => (pyth_price(15_000_000_000, -8), pyth_price(100_000_000, -8)),
+        Oracle::Doves => (
+            doves_price(150_000_000_000, -9),
+            doves_price(1_000_000_000, -9),
+        ),
+    };
+
+    let program_id = obric_v2_sdk::obric_v2_amm::id();
+    let (key, bump) =
+        obric_solana::pda::trading_pair_address(&program_id, &pair.mint_x, &pair.mint_y);
+    pair.bump = bump;
+    let account = anchor_account(program_id, &pair);
+    let accounts = HashMap::from([
+        (key, account.clone()),
+        (
+            pair.reserve_x,
+            token_account(pair.mint_x, 1_000_000_000_000),
+        ),
+        (pair.reserve_y, token_account(pair.mint_y, 150_000_000_000)),
+        (pair.x_price_feed_id, price_x),
+        (pair.y_price_feed_id, price_y),
+        (pair.mint_x, mint(9)),
+        (pair.mint_y, mint(6)),
+    ]);
+    Fixture {
+        key,
+        account,
+        accounts,
+    }
+}
+
+/// A SOL/USDC v3 pool with 200 of its 1,000 SOL borrowed and 150,000 USDC deposited, priced at
+/// 150 USDC per SOL. The Larix SOL reserve lends 10,000 SOL and has 2,000 out, the USDC reserve
+/// lends 1,000,000 USDC, and neither collateral redeems 1:1.
+pub fn v3_pool() -> Fixture {
+    use obric_solana_v3::consts;
+
+    let mut pair = obric_solana_v3::state::SSTradingPair {
+        is_initialized: true,
+        x_price_feed_id: Pubkey::new_unique(),
+        y_price_feed_id: Pubkey::new_unique(),
+        reserve_x: Pubkey::new_unique(),
+        reserve_y: Pubkey::new_unique(),
+        reserve_x_ctoken: Pubkey::new_unique(),
+        reserve_y_ctoken: Pubkey::new_unique(),
+        protocol_fee_x: Pubkey::new_unique(),
+        protocol_fee_y: Pubkey::new_unique(),
+        mint_x: consts::mints::sol::ID,
+        mint_y: consts::mints::usdc::ID,
+        deposit_x: 1_000_000_000_000,
+        borrow_x: 200_000_000_000,
+        deposit_y: 150_000_000_000,
+        concentration: 50,
+        fee_millionth: 300,
+        protocol_fee_share_thousandth: 200,
+        rebate_percentage: 10,
+        decimals_x: 9,
+        decimals_y: 6,
+        ..Default::default()
+    };
+
+    let cluster = obric_v3_sdk::cluster::ClusterConfig::mainnet();
+    let (key, bump) =
+        obric_solana_v3::pda::trading_pair_address(&cluster.program_id, &pair.mint_x, &pair.mint_y);
+    pair.bump = bump;
+    let account = anchor_account(cluster.program_id, &pair);
+    let accounts = HashMap::from([
+        (key, account.clone()),
+        (pair.x_price_feed_id, pyth_price(15_000_000_000, -8)),
+        (pair.y_price_feed_id, pyth_price(100_000_000, -8)),
+        (
+            cluster
+                .larix_reserve(&pair.mint_x)
+                .expect("SOL has a Larix reserve"),
+            larix_reserve(
+                pair.mint_x,
+                10_000_000_000_000,
+                2_000_000_000_000,
+                11_000_000_000_000,
+            ),
+        ),
+        (
+            cluster
+                .larix_reserve(&pair.mint_y)
+                .expect("USDC has a Larix reserve"),
+            larix_reserve(pair.mint_y, 1_000_000_000_000, 0, 950_000_000_000),
+        ),
+    ]);
+    Fixture {
+        key,
+        account,
+        accounts,
+    }
+}
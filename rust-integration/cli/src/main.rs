@@ -0,0 +1,283 @@
+//! `obric-cli`: list, inspect, quote, snapshot, backtest, stress and optimize Obric v2 and v3
+//! pools, from an RPC node or offline from snapshot files.
+
+mod report;
+mod source;
+
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand};
+use obric::ObricPool;
+use obric_sim::backtest::{self, Params};
+use obric_sim::optimize::{self, FlowSizes, Grid, Market};
+use obric_sim::oracle;
+use obric_sim::pool::SimPool;
+use obric_sim::stress::{self, Baseline};
+use report::{print, PoolReport, PoolSummary, QuoteReport, SnapshotReport};
+use solana_sdk::pubkey::Pubkey;
+use source::Source;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    name = "obric-cli",
+    about = "List, inspect, quote, snapshot, backtest, stress and optimize Obric v2 and v3 pools"
+)]
+struct Cli {
+    /// RPC node to read the pools from.
+    #[arg(long, env = "SOLANA_RPC", global = true)]
+    rpc_url: Option<String>,
+    /// Read the pools from snapshot files instead, a directory stands for its `.json` and `.bin`
+    /// files.
+    #[arg(long = "snapshot", value_name = "PATH", global = true)]
+    snapshots: Vec<PathBuf>,
+    /// Print JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Every v2 and v3 trading pair.
+    List,
+    /// The decoded trading pair with its prices, target, lock and feed ages.
+    Show { pool: Pubkey },
+    /// Quote `amount` of `in_mint`, in its smallest unit.
+    Quote {
+        pool: Pubkey,
+        in_mint: Pubkey,
+        amount: u64,
+        /// `amount` is the output wanted, the quote shows the input it takes.
+        #[arg(long)]
+        exact_out: bool,
+        /// Quote as if the pool had this concentration, defaults to the pool's.
+        #[arg(long)]
+        concentration: Option<u64>,
+        /// Quote as if the pool had this fee, defaults to the pool's.
+        #[arg(long)]
+        fee_millionth: Option<u64>,
+    },
+    /// Save the pool and every account its quotes read.
+    Snapshot {
+        pool: Pubkey,
+        /// Bincode for a `.bin` file, JSON otherwise.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Replay a trade tape through the pool while its prices follow a price series.
+    Backtest {
+        pool: Pubkey,
+        /// `timestamp,price_x,price_y` CSV, or a JSON array of those records.
+        #[arg(long)]
+        prices: PathBuf,
+        /// `timestamp,side,amount` CSV with `side` `x_to_y` or `y_to_x`, or a JSON array.
+        #[arg(long)]
+        trades: PathBuf,
+        /// Defaults to the pool's.
+        #[arg(long)]
+        concentration: Option<u64>,
+        /// Defaults to the pool's.
+        #[arg(long)]
+        fee_millionth: Option<u64>,
+    },
+    /// Run a step jump, a slow depeg, an oracle freeze and a feed divergence against the pool
+    /// with an arbitrageur trading at the market price.
+    Stress {
+        pool: Pubkey,
+        #[arg(long, default_value_t = 60)]
+        steps: usize,
+        /// Seconds between steps.
+        #[arg(long, default_value_t = 10)]
+        interval: i64,
+        /// Size of every shock in basis points.
+        #[arg(long, default_value_t = 500)]
+        shock_bps: i64,
+        /// Steps the feeds trail the market by.
+        #[arg(long, default_value_t = 1)]
+        feed_lag: usize,
+        /// Print every step, not just the summary of each scenario.
+        #[arg(long)]
+        trace: bool,
+    },
+    /// Backtest a grid of concentrations and fees on price paths and trades drawn like the
+    /// pool's history, and recommend the best one at least as deep as the pool is now.
+    Optimize {
+        pool: Pubkey,
+        /// Price history to take the volatility from, same format as `backtest`.
+        #[arg(long, required_unless_present = "volatility")]
+        prices: Option<PathBuf>,
+        /// Volatility of the price of X in Y per square root of a second, instead of `--prices`.
+        #[arg(long, conflicts_with = "prices")]
+        volatility: Option<f64>,
+        /// Trade history to draw sizes and sides from, same format as `backtest`.
+        #[arg(long)]
+        trades: PathBuf,
+        #[arg(long, default_value_t = 360)]
+        steps: usize,
+        /// Seconds between steps.
+        #[arg(long, default_value_t = 10)]
+        interval: i64,
+        #[arg(long, default_value_t = 100)]
+        paths: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Price impact the depth of each point is measured at.
+        #[arg(long, default_value_t = 50)]
+        depth_bps: u64,
+        /// Defaults to a quarter to four times the pool's.
+        #[arg(long, value_delimiter = ',')]
+        concentrations: Vec<u64>,
+        /// Defaults to a quarter to four times the pool's.
+        #[arg(long, value_delimiter = ',')]
+        fees_millionth: Vec<u64>,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let source = Source::new(cli.rpc_url, &cli.snapshots)?;
+
+    match cli.command {
+        Command::List => {
+            let mut pools = vec![];
+            for pool in source.pools()? {
+                match PoolSummary::new(&pool) {
+                    Ok(summary) => pools.push(summary),
+                    Err(err) => eprintln!("skipping {}: {err:#}", pool.key),
+                }
+            }
+            print(&pools, cli.json)
+        }
+        Command::Show { pool } => {
+            let snapshot = source.snapshot(&pool)?;
+            print(&PoolReport::new(&snapshot.load()?, &snapshot)?, cli.json)
+        }
+        Command::Quote {
+            pool,
+            in_mint,
+            amount,
+            exact_out,
+            concentration,
+            fee_millionth,
+        } => {
+            let mut amm = source.snapshot(&pool)?.load()?;
+            if concentration.is_some() || fee_millionth.is_some() {
+                let current = Params::of(&SimPool::from_amm(&amm));
+                amm = amm.with_params(
+                    concentration.unwrap_or(current.concentration),
+                    fee_millionth.unwrap_or(current.fee_millionth),
+                )?;
+            }
+            let quote = if exact_out {
+                amm.quote_exact_out(&in_mint, amount)?
+            } else {
+                amm.quote(&in_mint, amount)?
+            };
+            print(
+                &QuoteReport::new(&amm, &in_mint, &quote, exact_out),
+                cli.json,
+            )
+        }
+        Command::Snapshot { pool, output } => {
+            let snapshot = source.snapshot(&pool)?;
+            // fail here rather than when the file is replayed
+            snapshot.load()?;
+            snapshot.write(&output)?;
+            print(&SnapshotReport::new(&snapshot, &output), cli.json)
+        }
+        Command::Backtest {
+            pool,
+            prices,
+            trades,
+            concentration,
+            fee_millionth,
+        } => {
+            let pool = SimPool::from_amm(&source.snapshot(&pool)?.load()?);
+            let current = Params::of(&pool);
+            let params = Params {
+                concentration: concentration.unwrap_or(current.concentration),
+                fee_millionth: fee_millionth.unwrap_or(current.fee_millionth),
+            };
+            let report = backtest::run(
+                &pool,
+                params,
+                &backtest::read_prices(&prices)?,
+                &backtest::read_trades(&trades)?,
+            )?;
+            print(&report, cli.json)
+        }
+        Command::Stress {
+            pool,
+            steps,
+            interval,
+            shock_bps,
+            feed_lag,
+            trace,
+        } => {
+            let snapshot = source.snapshot(&pool)?;
+            let baseline = Baseline {
+                feed_lag,
+                ..Baseline::from_snapshot(&snapshot, steps, interval)?
+            };
+            let mut reports = vec![];
+            for scenario in baseline.standard(shock_bps) {
+                let mut report = stress::run(&snapshot, &scenario)?;
+                if !trace {
+                    report.trace.clear();
+                }
+                reports.push(report);
+            }
+            print(&reports, cli.json)
+        }
+        Command::Optimize {
+            pool,
+            prices,
+            volatility,
+            trades,
+            steps,
+            interval,
+            paths,
+            seed,
+            depth_bps,
+            concentrations,
+            fees_millionth,
+        } => {
+            let snapshot = source.snapshot(&pool)?;
+            let pool = SimPool::from_amm(&snapshot.load()?);
+            let volatility = match (volatility, prices) {
+                (Some(volatility), _) => volatility,
+                (None, Some(prices)) => optimize::volatility(&backtest::read_prices(&prices)?)?,
+                (None, None) => bail!("either --prices or --volatility is needed"),
+            };
+            let (price_x, price_y) = oracle::snapshot_prices(&snapshot)?;
+            let market = Market {
+                price_x,
+                price_y,
+                volatility,
+                interval,
+                steps,
+                paths,
+                seed,
+            };
+            let around = Grid::around(Params::of(&pool));
+            let grid = Grid {
+                concentrations: if concentrations.is_empty() {
+                    around.concentrations
+                } else {
+                    concentrations
+                },
+                fees_millionth: if fees_millionth.is_empty() {
+                    around.fees_millionth
+                } else {
+                    fees_millionth
+                },
+            };
+            let flow = FlowSizes::from_trades(&backtest::read_trades(&trades)?);
+            print(
+                &optimize::optimize(&pool, &market, &flow, &grid, depth_bps)?,
+                cli.json,
+            )
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! What the commands print. Every report is serialized once, to JSON as is or to indented
+//! `key: value` text.
+
+use anyhow::Result;
+use jupiter_amm_interface::{AmmContext, ClockRef, KeyedAccount};
+use obric::{snapshot::Snapshot, ObricAmm, ObricPool, PoolQuote, PoolVersion};
+use obric_solana::state::parse_price_unchecked;
+use serde::Serialize;
+use serde_json::Value;
+use solana_sdk::{clock::Clock, pubkey::Pubkey};
+use std::path::Path;
+
+/// Decimals the v3 program normalizes feed prices to.
+const V3_PRICE_DECIMALS: u8 = 3;
+
+#[derive(Serialize)]
+pub struct PoolSummary {
+    pub key: String,
+    pub version: PoolVersion,
+    pub mint_x: String,
+    pub mint_y: String,
+}
+
+impl PoolSummary {
+    pub fn new(pool: &KeyedAccount) -> Result<Self> {
+        // only the pair is decoded, the clock is never read
+        let amm_context = AmmContext {
+            clock_ref: ClockRef::from(Clock::default()),
+        };
+        let amm = ObricAmm::from_keyed_account(pool, &amm_context)?;
+        let (mint_x, mint_y) = amm.mints();
+        Ok(Self {
+            key: pool.key.to_string(),
+            version: amm.version(),
+            mint_x: mint_x.to_string(),
+            mint_y: mint_y.to_string(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct PoolReport {
+    pub key: String,
+    pub version: PoolVersion,
+    pub program_id: String,
+    pub unix_timestamp: i64,
+    pub mint_x: String,
+    pub mint_y: String,
+    /// What the pool can pay out of each side.
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    /// `None` while the pool cannot quote.
+    pub spot_price_x_in_y: Option<String>,
+    pub concentration: u64,
+    pub fee_millionth: u64,
+    pub mult_x: u64,
+    pub mult_y: u64,
+    #[serde(flatten)]
+    pub pair: PairReport,
+    pub feeds: Vec<FeedReport>,
+}
+
+/// The fields only one of the layouts has.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum PairReport {
+    V2 {
+        target_x: u64,
+        /// `None` when the reserves are worth less than the X target.
+        target_y: Option<u64>,
+        target_y_based_lock: bool,
+        price_decimals: u8,
+        cumulative_volume: u64,
+    },
+    V3 {
+        target_y: u64,
+        deposit_x: u64,
+        borrow_x: u64,
+        deposit_y: u64,
+        borrow_y: u64,
+        protocol_fee_share_thousandth: u64,
+        rebate_percentage: u64,
+        cumulative_volume: u64,
+    },
+}
+
+#[derive(Serialize)]
+pub struct FeedReport {
+    pub address: String,
+    pub owner: Option<String>,
+    /// Normalized to the decimals the program prices with, `None` when the feed does not decode.
+    pub price: Option<u64>,
+    pub publish_time: Option<i64>,
+    pub age_secs: Option<i64>,
+    /// Oldest price the program accepts, v3 does not check.
+    pub max_age_secs: Option<u8>,
+}
+
+impl PoolReport {
+    pub fn new(amm: &ObricAmm, snapshot: &Snapshot) -> Result<Self> {
+        let reserves = amm.reserves()?;
+        let spot_price = match amm {
+            ObricAmm::V2(amm) => amm.spot_price_x_in_y_ui(),
+            ObricAmm::V3(amm) => amm.spot_price_x_in_y_ui(),
+        };
+        let accounts = snapshot.account_map();
+        let feed = |address: Pubkey, decimals: u8, max_age_secs: Option<u8>| {
+            let account = accounts.get(&address);
+            let price = account.and_then(|account| {
+                parse_price_unchecked((account.data.as_slice(), &account.owner), decimals).ok()
+            });
+            FeedReport {
+                address: address.to_string(),
+                owner: account.map(|account| account.owner.to_string()),
+                price: price.map(|(price, _)| price),
+                publish_time: price.map(|(_, publish_time)| publish_time),
+                age_secs: price
+                    .map(|(_, publish_time)| snapshot.clock.unix_timestamp - publish_time),
+                max_age_secs,
+            }
+        };
+
+        Ok(match amm {
+            ObricAmm::V2(amm) => {
+                let state = &amm.state;
+                Self {
+                    key: amm.key.to_string(),
+                    version: PoolVersion::V2,
+                    program_id: amm.cluster.program_id.to_string(),
+                    unix_timestamp: snapshot.clock.unix_timestamp,
+                    mint_x: state.mint_x.to_string(),
+                    mint_y: state.mint_y.to_string(),
+                    reserve_x: reserves.x,
+                    reserve_y: reserves.y,
+                    spot_price_x_in_y: spot_price.ok().map(|price| price.to_string()),
+                    concentration: state.concentration,
+                    fee_millionth: state.fee_millionth,
+                    mult_x: state.mult_x,
+                    mult_y: state.mult_y,
+                    pair: PairReport::V2 {
+                        target_x: state.target_x,
+                        target_y: state
+                            .get_target_xy(amm.current_x, amm.current_y)
+                            .ok()
+                            .map(|(_, target_y)| target_y),
+                        target_y_based_lock: state.target_y_based_lock,
+                        price_decimals: state.price_decimals,
+                        cumulative_volume: state.cumulative_volume,
+                    },
+                    feeds: vec![
+                        feed(
+                            state.x_price_feed_id,
+                            state.price_decimals,
+                            Some(state.feed_max_age_x),
+                        ),
+                        feed(
+                            state.y_price_feed_id,
+                            state.price_decimals,
+                            Some(state.feed_max_age_y),
+                        ),
+                    ],
+                }
+            }
+            ObricAmm::V3(amm) => {
+                let state = &amm.state;
+                Self {
+                    key: amm.key.to_string(),
+                    version: PoolVersion::V3,
+                    program_id: amm.cluster.program_id.to_string(),
+                    unix_timestamp: snapshot.clock.unix_timestamp,
+                    mint_x: state.mint_x.to_string(),
+                    mint_y: state.mint_y.to_string(),
+                    reserve_x: reserves.x,
+                    reserve_y: reserves.y,
+                    spot_price_x_in_y: spot_price.ok().map(|price| price.to_string()),
+                    concentration: state.concentration,
+                    fee_millionth: state.fee_millionth,
+                    mult_x: state.mult_x,
+                    mult_y: state.mult_y,
+                    pair: PairReport::V3 {
+                        target_y: state.target_y,
+                        deposit_x: state.deposit_x,
+                        borrow_x: state.borrow_x,
+                        deposit_y: state.deposit_y,
+                        borrow_y: state.borrow_y,
+                        protocol_fee_share_thousandth: state.protocol_fee_share_thousandth,
+                        rebate_percentage: state.rebate_percentage,
+                        cumulative_volume: state.cumulative_volume,
+                    },
+                    feeds: vec![
+                        feed(state.x_price_feed_id, V3_PRICE_DECIMALS, None),
+                        feed(state.y_price_feed_id, V3_PRICE_DECIMALS, None),
+                    ],
+                }
+            }
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct QuoteReport {
+    pub pool: String,
+    pub exact_out: bool,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub fee_amount: u64,
+    pub fee_mint: String,
+}
+
+impl QuoteReport {
+    pub fn new(amm: &ObricAmm, input_mint: &Pubkey, quote: &PoolQuote, exact_out: bool) -> Self {
+        let (mint_x, mint_y) = amm.mints();
+        let output_mint = if *input_mint == mint_x {
+            mint_y
+        } else {
+            mint_x
+        };
+        Self {
+            pool: ObricPool::key(amm).to_string(),
+            exact_out,
+            input_mint: input_mint.to_string(),
+            output_mint: output_mint.to_string(),
+            in_amount: quote.in_amount,
+            out_amount: quote.out_amount,
+            fee_amount: quote.fee_amount,
+            fee_mint: quote.fee_mint.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SnapshotReport {
+    pub pool: String,
+    pub path: String,
+    pub version: u16,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub accounts: usize,
+}
+
+impl SnapshotReport {
+    pub fn new(snapshot: &Snapshot, path: &Path) -> Self {
+        Self {
+            pool: snapshot.key.to_string(),
+            path: path.display().to_string(),
+            version: snapshot.version,
+            slot: snapshot.slot,
+            unix_timestamp: snapshot.clock.unix_timestamp,
+            accounts: snapshot.accounts.len(),
+        }
+    }
+}
+
+pub fn print(report: &impl Serialize, json: bool) -> Result<()> {
+    let value = serde_json::to_value(report)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else {
+        let mut text = String::new();
+        render(&value, 0, &mut text);
+        print!("{text}");
+    }
+    Ok(())
+}
+
+fn render(value: &Value, indent: usize, text: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                if value.is_object() || value.is_array() {
+                    text.push_str(&format!("{pad}{key}:\n"));
+                    render(value, indent + 2, text);
+                } else {
+                    text.push_str(&format!("{pad}{key}: {}\n", scalar(value)));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                if item.is_object() || item.is_array() {
+                    if index > 0 {
+                        text.push('\n');
+                    }
+                    render(item, indent, text);
+                } else {
+                    text.push_str(&format!("{pad}- {}\n", scalar(item)));
+                }
+            }
+        }
+        value => text.push_str(&format!("{pad}{}\n", scalar(value))),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[test]
+fn text_output_indents_nested_values() {
+    let value = serde_json::json!({
+        "key": "pool",
+        "price": null,
+        "feeds": [{"age_secs": 3}, {"age_secs": 4}],
+    });
+    let mut text = String::new();
+    render(&value, 0, &mut text);
+    assert_eq!(
+        text,
+        "key: pool\nprice: -\nfeeds:\n  age_secs: 3\n\n  age_secs: 4\n"
+    );
+}
@@ -0,0 +1,107 @@
+//! Where pools are read from: an RPC node, or snapshot files when offline. Either way a pool is
+//! handed out as a `Snapshot`, so every command quotes the same state it could save.
+
+use anchor_lang::Discriminator;
+use anyhow::{anyhow, bail, Context, Result};
+use jupiter_amm_interface::KeyedAccount;
+use obric::snapshot::{self, Snapshot};
+use obric_solana::state::SSTradingPair;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+use std::{fs, path::PathBuf};
+
+pub enum Source {
+    Rpc(RpcClient),
+    Snapshots(Vec<Snapshot>),
+}
+
+impl Source {
+    /// Snapshot files win over the RPC node, a directory stands for the `.json` and `.bin`
+    /// files in it.
+    pub fn new(rpc_url: Option<String>, paths: &[PathBuf]) -> Result<Self> {
+        if paths.is_empty() {
+            let rpc_url = rpc_url
+                .ok_or_else(|| anyhow!("pass --rpc-url, set SOLANA_RPC or pass --snapshot"))?;
+            return Ok(Self::Rpc(RpcClient::new(rpc_url)));
+        }
+        let mut snapshots = vec![];
+        for path in paths {
+            if path.is_dir() {
+                let mut files = vec![];
+                for entry in
+                    fs::read_dir(path).with_context(|| format!("reading {}", path.display()))?
+                {
+                    let file = entry?.path();
+                    if file
+                        .extension()
+                        .is_some_and(|extension| extension == "json" || extension == "bin")
+                    {
+                        files.push(file);
+                    }
+                }
+                files.sort();
+                for file in files {
+                    snapshots.push(Snapshot::read(&file)?);
+                }
+            } else {
+                snapshots.push(Snapshot::read(path)?);
+            }
+        }
+        Ok(Self::Snapshots(snapshots))
+    }
+
+    /// Every trading pair of both programs, undecoded.
+    pub fn pools(&self) -> Result<Vec<KeyedAccount>> {
+        match self {
+            Self::Rpc(client) => {
+                let mut pools = vec![];
+                for program_id in [
+                    obric_v2_sdk::obric_v2_amm::id(),
+                    obric_v3_sdk::constants::PROGRAM_ID,
+                ] {
+                    // both programs share the discriminator, the owner tells them apart
+                    let config = RpcProgramAccountsConfig {
+                        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                            0,
+                            SSTradingPair::DISCRIMINATOR.to_vec(),
+                        ))]),
+                        account_config: RpcAccountInfoConfig {
+                            encoding: Some(UiAccountEncoding::Base64),
+                            ..RpcAccountInfoConfig::default()
+                        },
+                        ..RpcProgramAccountsConfig::default()
+                    };
+                    let accounts = client
+                        .get_program_accounts_with_config(&program_id, config)
+                        .with_context(|| format!("listing the pairs of {program_id}"))?;
+                    pools.extend(accounts.into_iter().map(|(key, account)| KeyedAccount {
+                        key,
+                        account,
+                        params: None,
+                    }));
+                }
+                Ok(pools)
+            }
+            Self::Snapshots(snapshots) => snapshots
+                .iter()
+                .map(|snapshot| snapshot.keyed_account())
+                .collect(),
+        }
+    }
+
+    /// The pool and everything its `update` reads, recorded now from an RPC node.
+    pub fn snapshot(&self, pool: &Pubkey) -> Result<Snapshot> {
+        match self {
+            Self::Rpc(client) => snapshot::record(client, pool),
+            Self::Snapshots(snapshots) => {
+                match snapshots.iter().find(|snapshot| snapshot.key == *pool) {
+                    Some(snapshot) => Ok(snapshot.clone()),
+                    None => bail!("no snapshot of {pool} was passed"),
+                }
+            }
+        }
+    }
+}
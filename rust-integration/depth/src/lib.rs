@@ -0,0 +1,150 @@
+//! Depth profiles shared by the v2 and v3 sdks, which re-export this crate as `depth`.
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::fmt::Write;
+
+pub const DEFAULT_IMPACT_THRESHOLDS_BPS: [u64; 3] = [10, 50, 100];
+
+/// Decimals of the fixed-point prices both `SSTradingPair`s return.
+pub const PRICE_DECIMALS: u32 = 18;
+
+/// Largest mantissa a `Decimal` holds, 2^96 - 1.
+const MAX_MANTISSA: u128 = (1 << 96) - 1;
+
+/// How a pool fills one trade size.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DepthLevel {
+    pub input: u64,
+    pub output: u64,
+    pub fee: u64,
+    /// Price of X in Y the trade fills at after fees, `None` when nothing is paid out.
+    pub average_price: Option<Decimal>,
+    /// How much worse than spot the fill is, after fees.
+    pub price_impact_bps: Option<Decimal>,
+}
+
+/// Largest input filled within `impact_bps` of spot. `None` when the fee alone costs more, or when
+/// the pool runs out of liquidity first.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ImpactThreshold {
+    pub impact_bps: u64,
+    pub max_input: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DepthProfile {
+    pub pool: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    /// Price of X in Y before fees.
+    pub spot_price: Decimal,
+    pub levels: Vec<DepthLevel>,
+    pub thresholds: Vec<ImpactThreshold>,
+}
+
+impl DepthLevel {
+    pub fn unfilled(input: u64) -> Self {
+        Self {
+            input,
+            output: 0,
+            fee: 0,
+            average_price: None,
+            price_impact_bps: None,
+        }
+    }
+
+    /// `fixed_point_price` is the sdk's `fixed_point_price`, applied to a Y amount over an X amount.
+    pub fn new(
+        x_to_y: bool,
+        spot_price: Decimal,
+        input: u64,
+        output: u64,
+        fee: u64,
+        fixed_point_price: impl FnOnce(u64, u64) -> Option<u128>,
+    ) -> Self {
+        let (numerator, denominator) = if x_to_y {
+            (output, input)
+        } else {
+            (input, output)
+        };
+        let average_price = fixed_point_price(numerator, denominator)
+            .map(to_decimal)
+            .filter(|_| output > 0);
+        let price_impact_bps =
+            average_price.and_then(|average| price_impact_bps(x_to_y, spot_price, average));
+
+        Self {
+            input,
+            output,
+            fee,
+            average_price,
+            price_impact_bps,
+        }
+    }
+}
+
+/// Selling X fills below spot and buying X fills above it, either way the impact is how much less
+/// output the trade gets than it would at spot.
+fn price_impact_bps(x_to_y: bool, spot: Decimal, average: Decimal) -> Option<Decimal> {
+    let fill_ratio = if x_to_y {
+        average.checked_div(spot)?
+    } else {
+        spot.checked_div(average)?
+    };
+    (Decimal::ONE - fill_ratio).checked_mul(Decimal::from(10_000))
+}
+
+/// A fixed-point price with `PRICE_DECIMALS` decimals as a `Decimal`. At that scale the 96-bit
+/// mantissa stops at about 7.9e10, larger prices keep fewer decimals, rounded down.
+pub fn to_decimal(price: u128) -> Decimal {
+    let (mut mantissa, mut scale) = (price, PRICE_DECIMALS);
+    // u128::MAX is below 10^39, so it fits once 10 of the 18 decimals are gone
+    while mantissa > MAX_MANTISSA {
+        mantissa /= 10;
+        scale -= 1;
+    }
+    Decimal::from_i128_with_scale(mantissa as i128, scale)
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+impl DepthProfile {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Levels and thresholds as one table, told apart by the `kind` column.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "pool,input_mint,output_mint,spot_price,kind,input,output,fee,average_price,price_impact_bps\n",
+        );
+        let prefix = format!(
+            "{},{},{},{}",
+            self.pool, self.input_mint, self.output_mint, self.spot_price
+        );
+        for level in &self.levels {
+            let _ = writeln!(
+                csv,
+                "{prefix},level,{},{},{},{},{}",
+                level.input,
+                level.output,
+                level.fee,
+                optional(&level.average_price),
+                optional(&level.price_impact_bps),
+            );
+        }
+        for threshold in &self.thresholds {
+            let _ = writeln!(
+                csv,
+                "{prefix},threshold,{},,,,{}",
+                optional(&threshold.max_input),
+                threshold.impact_bps,
+            );
+        }
+        csv
+    }
+}
@@ -0,0 +1,41 @@
+use obric_depth::{to_decimal, DepthLevel, PRICE_DECIMALS};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+#[test]
+fn small_prices_keep_every_decimal() {
+    assert_eq!(
+        to_decimal(1),
+        Decimal::from_str("0.000000000000000001").unwrap()
+    );
+    let price = 123_456_789_012_345_678_901_234_567u128;
+    assert_eq!(
+        to_decimal(price),
+        Decimal::from_i128_with_scale(price as i128, PRICE_DECIMALS)
+    );
+}
+
+#[test]
+fn large_prices_drop_decimals_instead_of_failing() {
+    // 1e11 of Y per X, past the ~7.9e10 a Decimal holds with 18 decimals
+    let price = 100_000_000_000u128 * 10u128.pow(PRICE_DECIMALS) + 123_456_789_012_345_678;
+    assert_eq!(
+        to_decimal(price),
+        Decimal::from_str("100000000000.12345678901234567").unwrap()
+    );
+    assert_eq!(
+        to_decimal(u128::MAX).trunc(),
+        Decimal::from(u128::MAX / 10u128.pow(PRICE_DECIMALS))
+    );
+}
+
+#[test]
+fn levels_price_expensive_pairs() {
+    // 1 X buys 2e11 Y, quoted with the sdk's fixed-point price
+    let spot = to_decimal(200_000_000_000 * 10u128.pow(PRICE_DECIMALS));
+    let level = DepthLevel::new(true, spot, 1, 199_000_000_000, 0, |y, x| {
+        Some(y as u128 * 10u128.pow(PRICE_DECIMALS) / x as u128)
+    });
+    assert_eq!(level.average_price, Some(Decimal::from(199_000_000_000u64)));
+    assert_eq!(level.price_impact_bps, Some(Decimal::from(50)));
+}
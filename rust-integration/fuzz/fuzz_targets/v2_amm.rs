@@ -0,0 +1,83 @@
+#![no_main]
+
+use jupiter_amm_interface::{
+    Amm, AmmContext, ClockRef, KeyedAccount, QuoteParams, SwapMode, SwapParams,
+};
+use libfuzzer_sys::fuzz_target;
+use obric_fuzz::FuzzCase;
+use obric_v2_sdk::obric_v2_amm::{self, ObricV2Amm};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::atomic::Ordering;
+
+fuzz_target!(|data: &[u8]| {
+    let Some(case) = FuzzCase::decode(data) else {
+        return;
+    };
+    let program_id = obric_v2_amm::id();
+    let Some(mut account) = case.account(0, &program_id) else {
+        return;
+    };
+    let key = obric_fuzz::place_v2_pair(&mut account, &program_id);
+
+    let clock_ref = ClockRef::default();
+    clock_ref
+        .unix_timestamp
+        .store(case.unix_timestamp, Ordering::Relaxed);
+    let amm_context = AmmContext { clock_ref };
+    let keyed_account = KeyedAccount {
+        key,
+        account,
+        params: None,
+    };
+    let Ok(mut amm) = ObricV2Amm::from_keyed_account(&keyed_account, &amm_context) else {
+        return;
+    };
+
+    // the mints are only requested until the first successful update
+    for _ in 0..2 {
+        let account_map = case.account_map(&amm.get_accounts_to_update(), &program_id);
+        if amm.update(&account_map).is_err() {
+            return;
+        }
+    }
+
+    let (input_mint, output_mint) = if case.x_to_y {
+        (amm.state.mint_x, amm.state.mint_y)
+    } else {
+        (amm.state.mint_y, amm.state.mint_x)
+    };
+    if let Ok(quote) = amm.quote(&QuoteParams {
+        amount: case.amount,
+        input_mint,
+        output_mint,
+        swap_mode: SwapMode::ExactIn,
+    }) {
+        // a rejected quote is reported as zero output, anything else must leave the reserve non-empty
+        let reserve = if case.x_to_y {
+            amm.current_y
+        } else {
+            amm.current_x
+        };
+        assert!(
+            quote.out_amount == 0 || quote.out_amount < reserve,
+            "quote of {} drains a reserve of {}",
+            quote.out_amount,
+            reserve
+        );
+    }
+
+    let jupiter_program_id = Pubkey::new_unique();
+    let _ = amm.get_swap_and_account_metas(&SwapParams {
+        swap_mode: SwapMode::ExactIn,
+        in_amount: case.amount,
+        out_amount: 0,
+        source_mint: input_mint,
+        destination_mint: output_mint,
+        source_token_account: Pubkey::new_unique(),
+        destination_token_account: Pubkey::new_unique(),
+        token_transfer_authority: Pubkey::new_unique(),
+        quote_mint_to_referrer: None,
+        jupiter_program_id: &jupiter_program_id,
+        missing_dynamic_accounts_as_default: false,
+    });
+});
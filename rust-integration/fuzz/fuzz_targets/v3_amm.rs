@@ -0,0 +1,71 @@
+#![no_main]
+
+use jupiter_amm_interface_v3::{Amm, KeyedAccount, QuoteParams, SwapParams};
+use libfuzzer_sys::fuzz_target;
+use obric_fuzz::FuzzCase;
+use obric_v3_sdk::{constants::PROGRAM_ID, obric_v3_amm::ObricV3Amm};
+use solana_sdk::pubkey::Pubkey;
+
+fuzz_target!(|data: &[u8]| {
+    let Some(case) = FuzzCase::decode(data) else {
+        return;
+    };
+    let Some(mut account) = case.account(0, &PROGRAM_ID) else {
+        return;
+    };
+    let key = obric_fuzz::place_v3_pair(&mut account, &PROGRAM_ID);
+
+    let keyed_account = KeyedAccount {
+        key,
+        account,
+        params: None,
+    };
+    let Ok(mut amm) = ObricV3Amm::from_keyed_account(&keyed_account) else {
+        return;
+    };
+
+    let account_map = case.account_map(&amm.get_accounts_to_update(), &PROGRAM_ID);
+    if amm.update(&account_map).is_err() {
+        return;
+    }
+
+    let (input_mint, output_mint) = if case.x_to_y {
+        (amm.state.mint_x, amm.state.mint_y)
+    } else {
+        (amm.state.mint_y, amm.state.mint_x)
+    };
+    let quote_params = QuoteParams {
+        in_amount: case.amount,
+        input_mint,
+        output_mint,
+    };
+    if let Ok(quote) = amm.quote(&quote_params) {
+        let (_, _, _, available_x, available_y) = amm.state.get_pool_values_for_quoting().unwrap();
+        let available = if case.x_to_y {
+            available_y
+        } else {
+            available_x
+        };
+        assert!(
+            quote.out_amount == 0 || quote.out_amount < available,
+            "quote of {} drains {} available",
+            quote.out_amount,
+            available
+        );
+        let breakdown = amm.quote_breakdown(&quote_params).unwrap();
+        assert_eq!(breakdown.net_output, quote.out_amount);
+    }
+
+    let jupiter_program_id = Pubkey::new_unique();
+    let _ = amm.get_swap_and_account_metas(&SwapParams {
+        in_amount: case.amount,
+        source_mint: input_mint,
+        destination_mint: output_mint,
+        source_token_account: Pubkey::new_unique(),
+        destination_token_account: Pubkey::new_unique(),
+        token_transfer_authority: Pubkey::new_unique(),
+        open_order_address: None,
+        quote_mint_to_referrer: None,
+        jupiter_program_id: &jupiter_program_id,
+    });
+});
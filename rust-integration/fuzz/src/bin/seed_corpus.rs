@@ -0,0 +1,22 @@
+//! Writes the seed corpus for every fuzz target to `corpus/<target>/`.
+//!
+//! Run from the `fuzz` directory: `cargo run --bin seed_corpus`.
+
+use obric_fuzz::{seeds, FuzzCase};
+use std::{fs, io, path::Path};
+
+fn write_corpus(target: &str, cases: &[FuzzCase]) -> io::Result<()> {
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir)?;
+    for (index, case) in cases.iter().enumerate() {
+        fs::write(dir.join(format!("seed-{index}")), case.encode())?;
+    }
+    println!("{}: {} seeds in {}", target, cases.len(), dir.display());
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    write_corpus("v2_amm", &seeds::v2_cases())?;
+    write_corpus("v3_amm", &seeds::v3_cases())?;
+    Ok(())
+}
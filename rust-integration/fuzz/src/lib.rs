@@ -0,0 +1,180 @@
+//! Shared input format for the Obric fuzz targets.
+//!
+//! A case is a small header followed by a list of accounts. The accounts are handed out in the
+//! order the AMM lists them in `get_accounts_to_update`, so the first one is always the trading pair
+//! itself. Keeping the format this simple lets `seed_corpus` write well-formed seeds from real
+//! account layouts, which libFuzzer then mutates byte by byte.
+
+pub mod seeds;
+
+use obric_bench::PYTH_PROGRAM_ID;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::collections::HashMap;
+
+/// Who owns a fuzzed account. Only the tag is encoded, the fuzzer picks among real owners.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Owner {
+    System,
+    Obric,
+    Token,
+    Pyth,
+    Doves,
+    Larix,
+}
+
+impl Owner {
+    const ALL: [Owner; 6] = [
+        Owner::System,
+        Owner::Obric,
+        Owner::Token,
+        Owner::Pyth,
+        Owner::Doves,
+        Owner::Larix,
+    ];
+
+    fn from_tag(tag: u8) -> Self {
+        Self::ALL[tag as usize % Self::ALL.len()]
+    }
+
+    fn tag(self) -> u8 {
+        Self::ALL.iter().position(|owner| *owner == self).unwrap() as u8
+    }
+
+    /// The owner `pubkey` maps back to, `System` for programs the fuzzer does not know.
+    pub fn of(pubkey: &Pubkey, obric_program: &Pubkey) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|owner| owner.pubkey(obric_program) == *pubkey)
+            .unwrap_or(Owner::System)
+    }
+
+    /// `obric_program` is the program under test, v2 and v3 live at different addresses.
+    pub fn pubkey(self, obric_program: &Pubkey) -> Pubkey {
+        match self {
+            Owner::System => solana_sdk::system_program::id(),
+            Owner::Obric => *obric_program,
+            Owner::Token => spl_token::id(),
+            Owner::Pyth => PYTH_PROGRAM_ID,
+            Owner::Doves => doves_cpi::ID,
+            Owner::Larix => larix_lending::id(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FuzzAccount {
+    pub owner: Owner,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FuzzCase {
+    pub x_to_y: bool,
+    pub unix_timestamp: i64,
+    pub amount: u64,
+    pub accounts: Vec<FuzzAccount>,
+}
+
+const HEADER_LEN: usize = 1 + 8 + 8;
+
+impl FuzzCase {
+    /// Layout: `flags: u8 | unix_timestamp: i64 | amount: u64` then repeated
+    /// `owner: u8 | len: u16 | data[len]`, all little endian. A truncated trailing account keeps
+    /// whatever bytes are left.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_LEN {
+            return None;
+        }
+        let (header, mut rest) = bytes.split_at(HEADER_LEN);
+        let x_to_y = header[0] & 1 == 1;
+        let unix_timestamp = i64::from_le_bytes(header[1..9].try_into().ok()?);
+        let amount = u64::from_le_bytes(header[9..17].try_into().ok()?);
+
+        let mut accounts = vec![];
+        while rest.len() >= 3 {
+            let owner = Owner::from_tag(rest[0]);
+            let len = u16::from_le_bytes([rest[1], rest[2]]) as usize;
+            let data = &rest[3..];
+            let len = len.min(data.len());
+            accounts.push(FuzzAccount {
+                owner,
+                data: data[..len].to_vec(),
+            });
+            rest = &data[len..];
+        }
+
+        Some(Self {
+            x_to_y,
+            unix_timestamp,
+            amount,
+            accounts,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.push(self.x_to_y as u8);
+        bytes.extend(self.unix_timestamp.to_le_bytes());
+        bytes.extend(self.amount.to_le_bytes());
+        for account in &self.accounts {
+            let len = u16::try_from(account.data.len()).expect("account larger than u16::MAX");
+            bytes.push(account.owner.tag());
+            bytes.extend(len.to_le_bytes());
+            bytes.extend(&account.data);
+        }
+        bytes
+    }
+
+    pub fn account(&self, index: usize, obric_program: &Pubkey) -> Option<Account> {
+        self.accounts.get(index).map(|account| Account {
+            lamports: 1_000_000_000,
+            data: account.data.clone(),
+            owner: account.owner.pubkey(obric_program),
+            executable: false,
+            rent_epoch: 0,
+        })
+    }
+
+    /// Pairs `keys` with the case accounts by position. Keys past the last account are left out,
+    /// as if the RPC node did not return them.
+    pub fn account_map(&self, keys: &[Pubkey], obric_program: &Pubkey) -> HashMap<Pubkey, Account> {
+        keys.iter()
+            .enumerate()
+            .filter_map(|(index, key)| Some((*key, self.account(index, obric_program)?)))
+            .collect()
+    }
+}
+
+/// `from_keyed_account` only accepts a pair at the PDA of its mints with the canonical bump, which
+/// mutated bytes never hit. Writes the canonical bump into a decodable v2 pair and returns its
+/// PDA, so the fuzzer gets past the check and into the quoting.
+pub fn place_v2_pair(account: &mut Account, obric_program: &Pubkey) -> Pubkey {
+    use obric_solana::state::SSTradingPairView;
+
+    let Ok(view) = SSTradingPairView::load(&account.data) else {
+        return Pubkey::new_unique();
+    };
+    let (key, bump) =
+        obric_solana::pda::trading_pair_address(obric_program, &view.mint_x, &view.mint_y);
+    bytemuck::from_bytes_mut::<SSTradingPairView>(
+        &mut account.data[8..8 + SSTradingPairView::LEN],
+    )
+    .bump = bump;
+    key
+}
+
+/// `place_v2_pair` for a v3 pair.
+pub fn place_v3_pair(account: &mut Account, obric_program: &Pubkey) -> Pubkey {
+    use obric_solana_v3::state::SSTradingPairView;
+
+    let Ok(view) = SSTradingPairView::load(&account.data) else {
+        return Pubkey::new_unique();
+    };
+    let (key, bump) =
+        obric_solana_v3::pda::trading_pair_address(obric_program, &view.mint_x, &view.mint_y);
+    bytemuck::from_bytes_mut::<SSTradingPairView>(
+        &mut account.data[8..8 + SSTradingPairView::LEN],
+    )
+    .bump = bump;
+    key
+}
@@ -0,0 +1,96 @@
+//! Seed corpus cases built from the `obric_bench` fixtures, so libFuzzer starts from the real
+//! layouts and from inputs that get past deserialization.
+
+use crate::{FuzzAccount, FuzzCase, Owner};
+use anchor_lang::AccountDeserialize;
+use obric_bench::{anchor_account, v2_pool, v3_pool, Fixture, Oracle, NOW};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+fn fuzz_account(account: &Account, obric_program: &Pubkey) -> FuzzAccount {
+    FuzzAccount {
+        owner: Owner::of(&account.owner, obric_program),
+        data: account.data.clone(),
+    }
+}
+
+/// The fixture accounts in `keys` order, as the fuzz targets hand them out.
+fn fuzz_accounts(fixture: &Fixture, keys: &[Pubkey], obric_program: &Pubkey) -> Vec<FuzzAccount> {
+    keys.iter()
+        .map(|key| fuzz_account(&fixture.accounts[key], obric_program))
+        .collect()
+}
+
+/// Seeds in `ObricV2Amm::get_accounts_to_update` order.
+pub fn v2_cases() -> Vec<FuzzCase> {
+    use jupiter_amm_interface::{Amm, AmmContext, ClockRef, KeyedAccount};
+    use obric_v2_sdk::obric_v2_amm::{self, ObricV2Amm};
+
+    let program_id = obric_v2_amm::id();
+    let accounts = |fixture: &Fixture| {
+        let amm = ObricV2Amm::from_keyed_account(
+            &KeyedAccount {
+                key: fixture.key,
+                account: fixture.account.clone(),
+                params: None,
+            },
+            &AmmContext {
+                clock_ref: ClockRef::default(),
+            },
+        )
+        .expect("fixture pair decodes");
+        fuzz_accounts(fixture, &amm.get_accounts_to_update(), &program_id)
+    };
+    let pyth = accounts(&v2_pool(Oracle::Pyth));
+    let doves = accounts(&v2_pool(Oracle::Doves));
+
+    // pair, reserve_x, reserve_y, price_x, price_y, mint_x, mint_y
+    let mut mixed = pyth.clone();
+    mixed[4] = doves[4].clone();
+    let mut locked = pyth.clone();
+    let mut pair =
+        obric_solana::state::SSTradingPair::try_deserialize(&mut &locked[0].data[..]).unwrap();
+    pair.target_y_based_lock = true;
+    locked[0] = fuzz_account(&anchor_account(program_id, &pair), &program_id);
+
+    let case = |x_to_y, amount, accounts: &Vec<FuzzAccount>| FuzzCase {
+        x_to_y,
+        unix_timestamp: NOW,
+        amount,
+        accounts: accounts.clone(),
+    };
+    vec![
+        case(true, 1_000_000_000, &pyth),
+        case(false, 150_000_000, &pyth),
+        case(true, 1_000_000_000, &doves),
+        case(false, u64::MAX, &mixed),
+        case(true, 1_000_000_000, &locked),
+    ]
+}
+
+/// Seeds in `ObricV3Amm::get_accounts_to_update` order.
+pub fn v3_cases() -> Vec<FuzzCase> {
+    use jupiter_amm_interface_v3::{Amm, KeyedAccount};
+    use obric_v3_sdk::{constants::PROGRAM_ID, obric_v3_amm::ObricV3Amm};
+
+    let fixture = v3_pool();
+    let amm = ObricV3Amm::from_keyed_account(&KeyedAccount {
+        key: fixture.key,
+        account: fixture.account.clone(),
+        params: None,
+    })
+    .expect("fixture pair decodes");
+    let accounts = fuzz_accounts(&fixture, &amm.get_accounts_to_update(), &PROGRAM_ID);
+
+    let case = |x_to_y, amount| FuzzCase {
+        x_to_y,
+        unix_timestamp: NOW,
+        amount,
+        accounts: accounts.clone(),
+    };
+    vec![
+        case(true, 1_000_000_000),
+        case(false, 150_000_000),
+        case(true, u64::MAX),
+        case(false, 1),
+    ]
+}
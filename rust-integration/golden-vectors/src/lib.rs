@@ -0,0 +1,446 @@
+//! Swaps recorded on mainnet for the v2 and v3 sdks to replay against their quotes.
+//!
+//! Each vector holds the accounts an amm reads, snapshotted just before a swap landed, the swap
+//! instruction args and the balance change of the user's destination token account. The sdks
+//! only supply how to quote a vector and how to decode their swap instruction, recording and
+//! replaying live here.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::{RpcBlockConfig, RpcTransactionConfig};
+use solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature,
+};
+use solana_transaction_status::{
+    TransactionDetails, UiLoadedAddresses, UiTransactionEncoding, UiTransactionTokenBalance,
+};
+use std::{fs, path::Path, str::FromStr, thread, time::Duration};
+
+/// Largest page `getSignaturesForAddress` returns.
+const SIGNATURE_PAGE: usize = 1000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GoldenVector {
+    pub signature: String,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub pair: String,
+    /// Pre-swap state of every account in `get_accounts_to_update` order.
+    pub accounts: Vec<RecordedAccount>,
+    pub swap: SwapArgs,
+    pub destination: TokenBalanceChange,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedAccount {
+    pub address: String,
+    pub owner: String,
+    pub lamports: u64,
+    /// base64
+    pub data: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SwapArgs {
+    pub x_to_y: bool,
+    pub input_amount: u64,
+    pub min_output_amount: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenBalanceChange {
+    pub address: String,
+    pub mint: String,
+    pub pre: u64,
+    pub post: u64,
+}
+
+impl GoldenVector {
+    pub fn observed_output(&self) -> Result<u64> {
+        self.destination
+            .post
+            .checked_sub(self.destination.pre)
+            .ok_or_else(|| anyhow!("destination balance went down"))
+    }
+
+    pub fn pair(&self) -> Result<Pubkey> {
+        Ok(Pubkey::from_str(&self.pair)?)
+    }
+
+    /// The recorded accounts, collected into whichever map the amm updates from.
+    pub fn accounts<T: FromIterator<(Pubkey, Account)>>(&self) -> Result<T> {
+        self.accounts
+            .iter()
+            .map(|recorded| {
+                let account = Account {
+                    lamports: recorded.lamports,
+                    data: STANDARD.decode(&recorded.data)?,
+                    owner: Pubkey::from_str(&recorded.owner)?,
+                    executable: false,
+                    rent_epoch: 0,
+                };
+                Ok((Pubkey::from_str(&recorded.address)?, account))
+            })
+            .collect()
+    }
+}
+
+/// How to find a swap of one program in a transaction.
+#[derive(Clone, Copy)]
+pub struct SwapInstruction {
+    pub program_id: Pubkey,
+    /// The args of `data`, `None` when it is not a swap.
+    pub decode: fn(&[u8]) -> Result<Option<SwapArgs>>,
+    /// Positions of user_token_account_x and user_token_account_y in the swap accounts.
+    pub user_token_accounts: [usize; 2],
+}
+
+pub fn load(dir: &Path) -> Result<Vec<GoldenVector>> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let json = fs::read_to_string(path)?;
+            serde_json::from_str(&json).with_context(|| format!("parsing {}", path.display()))
+        })
+        .collect()
+}
+
+/// Quotes every vector in `dir` and panics with the exact discrepancy, in base units of the
+/// output token, when a quote differs from the observed transfer, and when the corpus lacks a
+/// direction.
+pub fn replay(dir: &Path, quote: impl Fn(&GoldenVector) -> Result<u64>) {
+    let vectors = load(dir).unwrap();
+    for (x_to_y, direction) in [(true, "x to y"), (false, "y to x")] {
+        assert!(
+            vectors.iter().any(|vector| vector.swap.x_to_y == x_to_y),
+            "{} has no {direction} swap, record some with record_golden_vectors",
+            dir.display()
+        );
+    }
+    let mut mismatches = vec![];
+    for vector in &vectors {
+        let observed = vector.observed_output().unwrap();
+        match quote(vector) {
+            Ok(quoted) if quoted == observed => {}
+            Ok(quoted) => mismatches.push(format!(
+                "{}: quoted {}, observed {}, off by {}",
+                vector.signature,
+                quoted,
+                observed,
+                quoted as i128 - observed as i128
+            )),
+            Err(err) => mismatches.push(format!("{}: quote failed: {err}", vector.signature)),
+        }
+    }
+    println!("replayed {} golden vectors", vectors.len());
+    assert!(mismatches.is_empty(), "\n{}", mismatches.join("\n"));
+}
+
+/// Records `wanted` vectors per direction for every `(pair, accounts the amm reads)` and writes
+/// them to `dir`, one file per transaction.
+pub fn record(
+    client: &RpcClient,
+    instruction: SwapInstruction,
+    pools: &[(Pubkey, Vec<Pubkey>)],
+    wanted: usize,
+    dir: &Path,
+) {
+    let recorder = Recorder {
+        client,
+        instruction,
+    };
+    for (pair, keys) in pools {
+        let mut recorded = [0usize; 2];
+        while recorded.iter().any(|count| *count < wanted) {
+            let vector = match recorder.record(pair, keys) {
+                Ok(vector) => vector,
+                Err(err) => {
+                    println!("{pair}: {err}");
+                    break;
+                }
+            };
+            let direction = usize::from(vector.swap.x_to_y);
+            if recorded[direction] >= wanted {
+                continue;
+            }
+            recorded[direction] += 1;
+
+            let path = dir.join(format!("{}.json", vector.signature));
+            fs::write(&path, serde_json::to_string_pretty(&vector).unwrap()).unwrap();
+            println!("recorded {}", path.display());
+        }
+    }
+}
+
+fn token_balance(balances: &[UiTransactionTokenBalance], index: usize) -> Option<u64> {
+    balances
+        .iter()
+        .find(|balance| balance.account_index as usize == index)
+        .and_then(|balance| balance.ui_token_amount.amount.parse().ok())
+}
+
+/// Static keys followed by the ones loaded from lookup tables, the order instruction indices use.
+fn account_keys(static_keys: &[Pubkey], loaded: Option<UiLoadedAddresses>) -> Result<Vec<Pubkey>> {
+    let mut keys = static_keys.to_vec();
+    if let Some(loaded) = loaded {
+        for key in loaded.writable.iter().chain(&loaded.readonly) {
+            keys.push(Pubkey::from_str(key)?);
+        }
+    }
+    Ok(keys)
+}
+
+/// A swap `next_swap` found, before it is checked against the snapshot.
+struct ObservedSwap {
+    signature: String,
+    slot: u64,
+    unix_timestamp: i64,
+    swap: SwapArgs,
+    destination: TokenBalanceChange,
+}
+
+struct Recorder<'a> {
+    client: &'a RpcClient,
+    instruction: SwapInstruction,
+}
+
+impl Recorder<'_> {
+    fn snapshot(&self, keys: &[Pubkey]) -> Result<(u64, Vec<RecordedAccount>)> {
+        let response = self
+            .client
+            .get_multiple_accounts_with_commitment(keys, CommitmentConfig::confirmed())?;
+        let accounts = keys
+            .iter()
+            .zip(response.value)
+            .filter_map(|(address, account)| {
+                account.map(|account| RecordedAccount {
+                    address: address.to_string(),
+                    owner: account.owner.to_string(),
+                    lamports: account.lamports,
+                    data: STANDARD.encode(&account.data),
+                })
+            })
+            .collect();
+        Ok((response.context.slot, accounts))
+    }
+
+    /// The first swap on `pair` after `snapshot_slot`, if it is a direct, top-level Obric swap
+    /// whose destination account nothing else in the transaction touches and which no earlier
+    /// instruction of the transaction moves the `recorded` accounts for.
+    fn next_swap(
+        &self,
+        pair: &Pubkey,
+        snapshot_slot: u64,
+        recorded: &[Pubkey],
+    ) -> Result<Option<ObservedSwap>> {
+        let signatures = self.client.get_signatures_for_address_with_config(
+            pair,
+            GetConfirmedSignaturesForAddress2Config {
+                limit: Some(50),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..Default::default()
+            },
+        )?;
+        let Some(first) = signatures
+            .iter()
+            .rev()
+            .find(|status| status.slot > snapshot_slot)
+        else {
+            return Ok(None);
+        };
+        if first.err.is_some() {
+            return Ok(None);
+        }
+
+        let tx = self.client.get_transaction_with_config(
+            &Signature::from_str(&first.signature)?,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+        let meta = tx
+            .transaction
+            .meta
+            .ok_or_else(|| anyhow!("transaction has no meta"))?;
+        let decoded = tx
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| anyhow!("transaction does not decode"))?;
+        let keys = decoded.message.static_account_keys();
+        let instructions = decoded.message.instructions();
+        let all_keys = account_keys(keys, meta.loaded_addresses.clone().into())?;
+
+        let mut swaps = instructions.iter().enumerate().filter(|(_, ix)| {
+            keys.get(ix.program_id_index as usize) == Some(&self.instruction.program_id)
+        });
+        let (Some((swap_index, swap)), None) = (swaps.next(), swaps.next()) else {
+            return Ok(None);
+        };
+        let Some(args) = (self.instruction.decode)(&swap.data)? else {
+            return Ok(None);
+        };
+
+        let [account_x, account_y] = self.instruction.user_token_accounts;
+        let destination = if args.x_to_y { account_y } else { account_x };
+        let Some(&destination) = swap.accounts.get(destination) else {
+            return Ok(None);
+        };
+        let touched_elsewhere = instructions
+            .iter()
+            .enumerate()
+            .any(|(index, ix)| index != swap_index && ix.accounts.contains(&destination));
+        let destination = destination as usize;
+        if touched_elsewhere || destination >= keys.len() {
+            return Ok(None);
+        }
+        // every account an instruction writes, through CPIs too, is listed on the top-level instruction
+        let recorded_touched_before = instructions[..swap_index].iter().any(|ix| {
+            ix.accounts.iter().any(|&index| {
+                all_keys
+                    .get(index as usize)
+                    .is_some_and(|key| recorded.contains(key))
+            })
+        });
+        if recorded_touched_before {
+            return Ok(None);
+        }
+
+        let pre_balances: Option<Vec<_>> = meta.pre_token_balances.into();
+        let post_balances: Option<Vec<_>> = meta.post_token_balances.into();
+        let (Some(pre_balances), Some(post_balances)) = (pre_balances, post_balances) else {
+            return Ok(None);
+        };
+        let (Some(pre), Some(post)) = (
+            token_balance(&pre_balances, destination),
+            token_balance(&post_balances, destination),
+        ) else {
+            return Ok(None);
+        };
+        let mint = pre_balances
+            .iter()
+            .find(|balance| balance.account_index as usize == destination)
+            .map(|balance| balance.mint.clone())
+            .unwrap_or_default();
+
+        Ok(Some(ObservedSwap {
+            signature: first.signature.clone(),
+            slot: tx.slot,
+            unix_timestamp: tx.block_time.ok_or_else(|| anyhow!("no block time"))?,
+            swap: args,
+            destination: TokenBalanceChange {
+                address: keys[destination].to_string(),
+                mint,
+                pre,
+                post,
+            },
+        }))
+    }
+
+    /// Whether nothing wrote `accounts` between `snapshot_slot` and the swap `signature`, neither in
+    /// the slots in between nor earlier in the swap's own block, so the snapshot is exactly the
+    /// state the swap executed against. Failed transactions only charge their fee payer and are
+    /// skipped, every other transaction that lists an account counts as a write.
+    fn unchanged_until_swap(
+        &self,
+        accounts: &[RecordedAccount],
+        snapshot_slot: u64,
+        signature: &str,
+        swap_slot: u64,
+    ) -> Result<bool> {
+        let mut in_swap_slot = vec![];
+        for account in accounts {
+            let signatures = self.client.get_signatures_for_address_with_config(
+                &Pubkey::from_str(&account.address)?,
+                GetConfirmedSignaturesForAddress2Config {
+                    limit: Some(SIGNATURE_PAGE),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    ..Default::default()
+                },
+            )?;
+            // a full page that does not reach back to the snapshot can hide earlier writes
+            if signatures.len() == SIGNATURE_PAGE
+                && signatures
+                    .last()
+                    .is_some_and(|status| status.slot > snapshot_slot)
+            {
+                return Ok(false);
+            }
+            for status in signatures {
+                if status.slot <= snapshot_slot
+                    || status.slot > swap_slot
+                    || status.err.is_some()
+                    || status.signature == signature
+                {
+                    continue;
+                }
+                if status.slot < swap_slot {
+                    return Ok(false);
+                }
+                in_swap_slot.push(status.signature);
+            }
+        }
+        if in_swap_slot.is_empty() {
+            return Ok(true);
+        }
+
+        // signatures come back in block order
+        let block = self.client.get_block_with_config(
+            swap_slot,
+            RpcBlockConfig {
+                transaction_details: Some(TransactionDetails::Signatures),
+                rewards: Some(false),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+                ..Default::default()
+            },
+        )?;
+        let signatures = block
+            .signatures
+            .ok_or_else(|| anyhow!("block {swap_slot} has no signatures"))?;
+        let Some(swap_position) = signatures.iter().position(|other| other == signature) else {
+            return Ok(false);
+        };
+        Ok(!signatures[..swap_position]
+            .iter()
+            .any(|other| in_swap_slot.contains(other)))
+    }
+
+    /// Snapshots `keys` every slot and records the next direct swap on `pair` that executed
+    /// against the snapshot state.
+    fn record(&self, pair: &Pubkey, keys: &[Pubkey]) -> Result<GoldenVector> {
+        for _ in 0..10_000 {
+            let (snapshot_slot, accounts) = self.snapshot(keys)?;
+            thread::sleep(Duration::from_millis(400));
+            let Some(observed) = self.next_swap(pair, snapshot_slot, keys)? else {
+                continue;
+            };
+            if self.unchanged_until_swap(
+                &accounts,
+                snapshot_slot,
+                &observed.signature,
+                observed.slot,
+            )? {
+                return Ok(GoldenVector {
+                    signature: observed.signature,
+                    slot: observed.slot,
+                    unix_timestamp: observed.unix_timestamp,
+                    pair: pair.to_string(),
+                    accounts,
+                    swap: observed.swap,
+                    destination: observed.destination,
+                });
+            }
+        }
+        bail!("no direct swap on {pair} executed against a snapshot in time")
+    }
+}
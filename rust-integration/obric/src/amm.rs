@@ -0,0 +1,294 @@
+use crate::{ObricPool, PoolQuote, PoolState, PoolVersion, Reserves, SwapInstructionParams};
+use anchor_lang::Discriminator;
+use anyhow::{bail, Result};
+use jupiter_amm_interface::{
+    AccountMap, Amm, AmmContext, KeyedAccount, Quote, QuoteParams, Swap, SwapAndAccountMetas,
+    SwapMode, SwapParams,
+};
+use jupiter_amm_interface_v3 as amm_v3;
+use obric_v2_sdk::{cluster as v2_cluster, obric_v2_amm::ObricV2Amm};
+use obric_v3_sdk::{
+    cluster as v3_cluster, constants::SWAP_Y_TO_X_ACCOUNTS_LEN, obric_v3_amm::ObricV3Amm,
+};
+use solana_sdk::{account::Account, instruction::Instruction, pubkey::Pubkey};
+use std::collections::HashMap;
+
+/// A v2 or v3 pool, told apart by the program that owns the trading pair.
+#[derive(Clone)]
+pub enum ObricAmm {
+    V2(Box<ObricV2Amm>),
+    V3(Box<ObricV3Amm>),
+}
+
+impl ObricAmm {
+    /// `from_keyed_account_with_clusters` on mainnet.
+    pub fn from_keyed_account(
+        keyed_account: &KeyedAccount,
+        amm_context: &AmmContext,
+    ) -> Result<Self> {
+        Self::from_keyed_account_with_clusters(
+            keyed_account,
+            amm_context,
+            &v2_cluster::Cluster::Mainnet,
+            &v3_cluster::Cluster::Mainnet,
+        )
+    }
+
+    /// Both programs name the account `SSTradingPair`, so the discriminator only tells a trading
+    /// pair from other accounts and the owner picks the sdk.
+    pub fn from_keyed_account_with_clusters(
+        keyed_account: &KeyedAccount,
+        amm_context: &AmmContext,
+        v2_cluster: &v2_cluster::Cluster,
+        v3_cluster: &v3_cluster::Cluster,
+    ) -> Result<Self> {
+        if !keyed_account
+            .account
+            .data
+            .starts_with(&obric_solana::state::SSTradingPair::DISCRIMINATOR)
+        {
+            bail!("{} is not an Obric trading pair", keyed_account.key);
+        }
+        let owner = keyed_account.account.owner;
+        if owner == v2_cluster.config()?.program_id {
+            Ok(Self::V2(Box::new(
+                ObricV2Amm::from_keyed_account_with_cluster(
+                    keyed_account,
+                    amm_context,
+                    v2_cluster,
+                )?,
+            )))
+        } else if owner == v3_cluster.config()?.program_id {
+            let keyed_account = amm_v3::KeyedAccount {
+                key: keyed_account.key,
+                account: keyed_account.account.clone(),
+                params: keyed_account.params.clone(),
+            };
+            Ok(Self::V3(Box::new(
+                ObricV3Amm::from_keyed_account_with_cluster(&keyed_account, v3_cluster)?,
+            )))
+        } else {
+            bail!(
+                "{} is owned by {owner}, neither Obric program",
+                keyed_account.key
+            )
+        }
+    }
+
+    /// A copy of the pool as if an admin had set `concentration` and `fee_millionth`, see the
+    /// `with_params` of each sdk.
+    pub fn with_params(&self, concentration: u64, fee_millionth: u64) -> Result<Self> {
+        Ok(match self {
+            Self::V2(amm) => Self::V2(Box::new(amm.with_params(concentration, fee_millionth)?)),
+            Self::V3(amm) => Self::V3(Box::new(amm.with_params(concentration, fee_millionth)?)),
+        })
+    }
+
+    /// A copy of the pool as if the feeds had published `price_x` and `price_y`, see the
+    /// `with_prices` of each sdk.
+    pub fn with_prices(&self, price_x: u64, price_y: u64) -> Result<Self> {
+        Ok(match self {
+            Self::V2(amm) => Self::V2(Box::new(amm.with_prices(price_x, price_y)?)),
+            Self::V3(amm) => Self::V3(Box::new(amm.with_prices(price_x, price_y)?)),
+        })
+    }
+
+    fn pool(&self) -> &dyn ObricPool {
+        match self {
+            Self::V2(amm) => amm.as_ref(),
+            Self::V3(amm) => amm.as_ref(),
+        }
+    }
+
+    fn pool_mut(&mut self) -> &mut dyn ObricPool {
+        match self {
+            Self::V2(amm) => amm.as_mut(),
+            Self::V3(amm) => amm.as_mut(),
+        }
+    }
+}
+
+impl ObricPool for ObricAmm {
+    fn version(&self) -> PoolVersion {
+        self.pool().version()
+    }
+
+    fn key(&self) -> Pubkey {
+        self.pool().key()
+    }
+
+    fn program_id(&self) -> Pubkey {
+        self.pool().program_id()
+    }
+
+    fn mints(&self) -> (Pubkey, Pubkey) {
+        self.pool().mints()
+    }
+
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        self.pool().accounts_to_update()
+    }
+
+    fn update(&mut self, accounts: &HashMap<Pubkey, Account>) -> Result<()> {
+        self.pool_mut().update(accounts)
+    }
+
+    fn reserves(&self) -> Result<Reserves> {
+        self.pool().reserves()
+    }
+
+    fn spot_price_x_in_y(&self) -> Result<u128> {
+        self.pool().spot_price_x_in_y()
+    }
+
+    fn quote(&self, input_mint: &Pubkey, in_amount: u64) -> Result<PoolQuote> {
+        self.pool().quote(input_mint, in_amount)
+    }
+
+    fn quote_with_params(
+        &self,
+        input_mint: &Pubkey,
+        in_amount: u64,
+        concentration: u64,
+        fee_millionth: u64,
+    ) -> Result<PoolQuote> {
+        self.pool()
+            .quote_with_params(input_mint, in_amount, concentration, fee_millionth)
+    }
+
+    fn price_decimals(&self) -> u8 {
+        self.pool().price_decimals()
+    }
+
+    fn quote_at_prices(
+        &self,
+        input_mint: &Pubkey,
+        in_amount: u64,
+        price_x: u64,
+        price_y: u64,
+    ) -> Result<PoolQuote> {
+        self.pool()
+            .quote_at_prices(input_mint, in_amount, price_x, price_y)
+    }
+
+    fn simulate_swap(&mut self, input_mint: &Pubkey, in_amount: u64) -> Result<PoolQuote> {
+        self.pool_mut().simulate_swap(input_mint, in_amount)
+    }
+
+    fn max_input(&self, input_mint: &Pubkey) -> Result<u64> {
+        self.pool().max_input(input_mint)
+    }
+
+    fn swap_instruction(&self, params: &SwapInstructionParams) -> Result<Instruction> {
+        self.pool().swap_instruction(params)
+    }
+
+    fn state(&self) -> PoolState {
+        self.pool().state()
+    }
+}
+
+/// The v3 sdk is on the 0.2 interface, its calls are translated here.
+impl Amm for ObricAmm {
+    fn from_keyed_account(keyed_account: &KeyedAccount, amm_context: &AmmContext) -> Result<Self> {
+        ObricAmm::from_keyed_account(keyed_account, amm_context)
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::V2(amm) => Amm::label(amm.as_ref()),
+            Self::V3(amm) => amm_v3::Amm::label(amm.as_ref()),
+        }
+    }
+
+    fn program_id(&self) -> Pubkey {
+        ObricPool::program_id(self)
+    }
+
+    fn key(&self) -> Pubkey {
+        ObricPool::key(self)
+    }
+
+    fn get_reserve_mints(&self) -> Vec<Pubkey> {
+        let (mint_x, mint_y) = self.mints();
+        vec![mint_x, mint_y]
+    }
+
+    fn get_accounts_to_update(&self) -> Vec<Pubkey> {
+        self.accounts_to_update()
+    }
+
+    fn update(&mut self, account_map: &AccountMap) -> Result<()> {
+        ObricPool::update(self, account_map)
+    }
+
+    fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
+        match self {
+            Self::V2(amm) => Amm::quote(amm.as_ref(), quote_params),
+            Self::V3(amm) => {
+                if quote_params.swap_mode != SwapMode::ExactIn {
+                    bail!("Obric v3 only quotes exact in");
+                }
+                let quote = amm_v3::Amm::quote(
+                    amm.as_ref(),
+                    &amm_v3::QuoteParams {
+                        in_amount: quote_params.amount,
+                        input_mint: quote_params.input_mint,
+                        output_mint: quote_params.output_mint,
+                    },
+                )?;
+                Ok(Quote {
+                    in_amount: quote.in_amount,
+                    out_amount: quote.out_amount,
+                    fee_amount: quote.fee_amount,
+                    fee_mint: quote.fee_mint,
+                    fee_pct: quote.fee_pct,
+                    ..Quote::default()
+                })
+            }
+        }
+    }
+
+    fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
+        match self {
+            Self::V2(amm) => amm.get_swap_and_account_metas(swap_params),
+            Self::V3(amm) => {
+                let metas = amm_v3::Amm::get_swap_and_account_metas(
+                    amm.as_ref(),
+                    &amm_v3::SwapParams {
+                        in_amount: swap_params.in_amount,
+                        source_mint: swap_params.source_mint,
+                        destination_mint: swap_params.destination_mint,
+                        source_token_account: swap_params.source_token_account,
+                        destination_token_account: swap_params.destination_token_account,
+                        token_transfer_authority: swap_params.token_transfer_authority,
+                        open_order_address: None,
+                        quote_mint_to_referrer: None,
+                        jupiter_program_id: swap_params.jupiter_program_id,
+                    },
+                )?;
+                // same placeholder as the v3 sdk, Jupiter has no Obric v3 swap yet
+                Ok(SwapAndAccountMetas {
+                    swap: Swap::Saber,
+                    account_metas: metas.account_metas,
+                })
+            }
+        }
+    }
+
+    fn clone_amm(&self) -> Box<dyn Amm + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn has_dynamic_accounts(&self) -> bool {
+        true
+    }
+
+    /// v3 swaps from Y to X list the most accounts.
+    fn get_accounts_len(&self) -> usize {
+        match self {
+            Self::V2(amm) => amm.get_accounts_len(),
+            Self::V3(_) => SWAP_Y_TO_X_ACCOUNTS_LEN,
+        }
+    }
+}
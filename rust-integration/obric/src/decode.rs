@@ -0,0 +1,613 @@
+//! Obric swaps out of transactions. `SwapDecoder` recognizes the v2 `swap` and the v3
+//! `swap_x_to_y` and `swap_y_to_x` instructions by program id and discriminator, at the top level
+//! or called by another program, and names their accounts. `parse_transaction` turns every swap
+//! of a confirmed transaction into a `TradeRecord`.
+//!
+//! Amounts come from the token transfers the swap made itself, the inner instructions that follow
+//! it deeper in the stack. A swap called by another program in a transaction without stack
+//! heights has no transfers that can be told apart, its output is read off the token balances of
+//! the user's account instead, which is only right when nothing else in the transaction moved it.
+//! The protocol fee is read off the balance of the protocol fee account the same way.
+//!
+//! v3 swaps are named as `obric_solana_v3` declares them. Its `SwapYToX` takes no mints, unlike
+//! the v3 sdk's `get_swap_and_account_metas`, which lists `mint_x` and `mint_y` after the pair for
+//! both directions, so swaps from Y to X built by the sdk do not decode to the right names.
+
+use crate::PoolVersion;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{anyhow, bail, Result};
+use obric_solana::instruction::Swap as V2Swap;
+use obric_solana_v3::instruction::{SwapXToY, SwapYToX};
+use obric_v2_sdk::cluster as v2_cluster;
+use obric_v3_sdk::cluster as v3_cluster;
+use solana_sdk::{
+    instruction::{CompiledInstruction, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, UiInstruction, UiLoadedAddresses,
+    UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+/// spl-token `Transfer` and `TransferChecked`, the amount follows as a little endian u64.
+const TRANSFER: u8 = 3;
+const TRANSFER_CHECKED: u8 = 12;
+
+const V2_SWAP_ACCOUNTS: [&str; 12] = [
+    "trading_pair",
+    "mint_x",
+    "mint_y",
+    "reserve_x",
+    "reserve_y",
+    "user_token_account_x",
+    "user_token_account_y",
+    "reference_oracle",
+    "x_price_feed",
+    "y_price_feed",
+    "user",
+    "token_program",
+];
+
+const V3_SWAP_X_TO_Y_ACCOUNTS: [&str; 28] = [
+    "trading_pair",
+    "mint_x",
+    "mint_y",
+    "mint_x_ctoken",
+    "mint_y_ctoken",
+    "user_token_account_x",
+    "user_token_account_y",
+    "reserve_x",
+    "reserve_y",
+    "reserve_x_ctoken",
+    "reserve_y_ctoken",
+    "protocol_fee_y",
+    "x_price_feed",
+    "y_price_feed",
+    "larix_reserve_liquidity_supply_x",
+    "larix_reserve_liquidity_supply_y",
+    "larix_destination_reserve_ctoken_x",
+    "larix_destination_reserve_ctoken_y",
+    "larix_reserve_x",
+    "larix_reserve_y",
+    "larix_obligation",
+    "larix_lending_market",
+    "larix_market_authority",
+    "larix_x_oracle",
+    "larix_y_oracle",
+    "user",
+    "token_program",
+    "larix_program",
+];
+
+/// As `SwapYToX` declares them, without the mints.
+const V3_SWAP_Y_TO_X_ACCOUNTS: [&str; 29] = [
+    "trading_pair",
+    "mint_x_ctoken",
+    "mint_y_ctoken",
+    "user_token_account_x",
+    "user_token_account_y",
+    "reserve_x",
+    "reserve_y",
+    "reserve_x_ctoken",
+    "reserve_y_ctoken",
+    "protocol_fee_x",
+    "x_price_feed",
+    "y_price_feed",
+    "larix_reserve_liquidity_supply_x",
+    "larix_reserve_liquidity_supply_y",
+    "larix_destination_reserve_ctoken_x",
+    "larix_destination_reserve_ctoken_y",
+    "larix_reserve_x",
+    "larix_reserve_y",
+    "larix_obligation",
+    "larix_lending_market",
+    "larix_market_authority",
+    "larix_x_oracle",
+    "larix_y_oracle",
+    "user",
+    "token_program",
+    "larix_program",
+    "larix_oracle_program",
+    "larix_mint",
+    "larix_reserve_fee_receiver_x",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NamedAccount {
+    pub name: &'static str,
+    pub pubkey: Pubkey,
+}
+
+/// A swap instruction with its arguments and accounts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedSwap {
+    pub version: PoolVersion,
+    pub program_id: Pubkey,
+    pub x_to_y: bool,
+    pub input_amount: u64,
+    pub min_output_amount: u64,
+    /// In instruction order, accounts past the ones the swap takes are left out.
+    pub accounts: Vec<NamedAccount>,
+}
+
+impl DecodedSwap {
+    pub fn account(&self, name: &str) -> Option<Pubkey> {
+        self.accounts
+            .iter()
+            .find(|account| account.name == name)
+            .map(|account| account.pubkey)
+    }
+
+    pub fn trading_pair(&self) -> Option<Pubkey> {
+        self.account("trading_pair")
+    }
+
+    /// Signer and owner of the token accounts.
+    pub fn user(&self) -> Option<Pubkey> {
+        self.account("user")
+    }
+
+    /// `(source, destination)` token accounts of the user.
+    pub fn user_token_accounts(&self) -> Option<(Pubkey, Pubkey)> {
+        let x = self.account("user_token_account_x")?;
+        let y = self.account("user_token_account_y")?;
+        Some(if self.x_to_y { (x, y) } else { (y, x) })
+    }
+
+    /// `(input_mint, output_mint)` when the swap lists the mints, v3 swaps from Y to X do not.
+    pub fn mints(&self) -> Option<(Pubkey, Pubkey)> {
+        let (mint_x, mint_y) = (self.account("mint_x")?, self.account("mint_y")?);
+        Some(if self.x_to_y {
+            (mint_x, mint_y)
+        } else {
+            (mint_y, mint_x)
+        })
+    }
+
+    /// Token account the protocol fee is sent to, v2 has none.
+    pub fn protocol_fee_account(&self) -> Option<Pubkey> {
+        self.account("protocol_fee_x")
+            .or_else(|| self.account("protocol_fee_y"))
+    }
+}
+
+/// One executed swap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TradeRecord {
+    pub signature: Signature,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// Top level instruction that is, or called, the swap.
+    pub instruction_index: usize,
+    /// Position among the inner instructions of `instruction_index`, `None` at the top level.
+    pub inner_index: Option<usize>,
+    pub version: PoolVersion,
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub x_to_y: bool,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub in_amount: u64,
+    /// What the user received, after fees.
+    pub out_amount: u64,
+    pub min_output_amount: u64,
+    /// Sent to the protocol fee account, in the output token. v3 only, and only when the swap's
+    /// own transfers or the account's balances show it.
+    pub protocol_fee: Option<u64>,
+    /// All the swap charged, in the output token, protocol fee included, see `fee_for_output`.
+    /// `None` when the pool's `fee_millionth` is not known.
+    pub fee: Option<u64>,
+}
+
+/// The programs to recognize swaps of, see `with_clusters`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapDecoder {
+    pub v2_program_id: Pubkey,
+    pub v3_program_id: Pubkey,
+}
+
+/// Mainnet programs.
+impl Default for SwapDecoder {
+    fn default() -> Self {
+        Self {
+            v2_program_id: v2_cluster::ClusterConfig::mainnet().program_id,
+            v3_program_id: v3_cluster::ClusterConfig::mainnet().program_id,
+        }
+    }
+}
+
+impl SwapDecoder {
+    pub fn with_clusters(
+        v2_cluster: &v2_cluster::Cluster,
+        v3_cluster: &v3_cluster::Cluster,
+    ) -> Result<Self> {
+        Ok(Self {
+            v2_program_id: v2_cluster.config()?.program_id,
+            v3_program_id: v3_cluster.config()?.program_id,
+        })
+    }
+
+    /// `None` when the instruction is not an Obric swap. Other programs reuse the v2 `swap`
+    /// discriminator, so it only counts from the v2 program.
+    pub fn decode(
+        &self,
+        program_id: &Pubkey,
+        data: &[u8],
+        accounts: &[Pubkey],
+    ) -> Result<Option<DecodedSwap>> {
+        let (v2, v3) = (
+            *program_id == self.v2_program_id,
+            *program_id == self.v3_program_id,
+        );
+        let (version, x_to_y, input_amount, min_output_amount, names) =
+            if v2 && data.starts_with(&V2Swap::DISCRIMINATOR) {
+                let args = V2Swap::try_from_slice(&data[8..])
+                    .map_err(|err| anyhow!("malformed Obric v2 swap: {err}"))?;
+                (
+                    PoolVersion::V2,
+                    args._is_x_to_y,
+                    args._input_amt,
+                    args._min_output_amt,
+                    &V2_SWAP_ACCOUNTS[..],
+                )
+            } else if v3 && data.starts_with(&SwapXToY::DISCRIMINATOR) {
+                let args = SwapXToY::try_from_slice(&data[8..])
+                    .map_err(|err| anyhow!("malformed Obric v3 swap_x_to_y: {err}"))?;
+                (
+                    PoolVersion::V3,
+                    true,
+                    args._input_x,
+                    args._min_output_amt,
+                    &V3_SWAP_X_TO_Y_ACCOUNTS[..],
+                )
+            } else if v3 && data.starts_with(&SwapYToX::DISCRIMINATOR) {
+                let args = SwapYToX::try_from_slice(&data[8..])
+                    .map_err(|err| anyhow!("malformed Obric v3 swap_y_to_x: {err}"))?;
+                (
+                    PoolVersion::V3,
+                    false,
+                    args._input_y,
+                    args._min_output_amt,
+                    &V3_SWAP_Y_TO_X_ACCOUNTS[..],
+                )
+            } else {
+                return Ok(None);
+            };
+        if accounts.len() < names.len() {
+            bail!(
+                "Obric {version:?} swap lists {} accounts, it takes {}",
+                accounts.len(),
+                names.len()
+            );
+        }
+        Ok(Some(DecodedSwap {
+            version,
+            program_id: *program_id,
+            x_to_y,
+            input_amount,
+            min_output_amount,
+            accounts: names
+                .iter()
+                .zip(accounts)
+                .map(|(&name, pubkey)| NamedAccount {
+                    name,
+                    pubkey: *pubkey,
+                })
+                .collect(),
+        }))
+    }
+
+    pub fn decode_instruction(&self, instruction: &Instruction) -> Result<Option<DecodedSwap>> {
+        let accounts: Vec<_> = instruction
+            .accounts
+            .iter()
+            .map(|meta| meta.pubkey)
+            .collect();
+        self.decode(&instruction.program_id, &instruction.data, &accounts)
+    }
+
+    /// `account_keys` as the message resolves them, static keys then the loaded writable and
+    /// readonly ones.
+    pub fn decode_compiled(
+        &self,
+        instruction: &CompiledInstruction,
+        account_keys: &[Pubkey],
+    ) -> Result<Option<DecodedSwap>> {
+        let accounts = instruction
+            .accounts
+            .iter()
+            .map(|index| account_key(account_keys, *index))
+            .collect::<Result<Vec<_>>>()?;
+        self.decode(
+            &account_key(account_keys, instruction.program_id_index)?,
+            &instruction.data,
+            &accounts,
+        )
+    }
+
+    /// Every Obric swap of `transaction` in execution order, none when it failed. The transaction
+    /// has to be binary encoded, base64 or base58, with its status meta. `fee_millionth` gives
+    /// the fee of a trading pair, for the `fee` of its swaps.
+    pub fn parse_transaction(
+        &self,
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+        fee_millionth: impl Fn(&Pubkey) -> Option<u64>,
+    ) -> Result<Vec<TradeRecord>> {
+        let meta = transaction
+            .transaction
+            .meta
+            .as_ref()
+            .ok_or_else(|| anyhow!("the transaction comes without its status meta"))?;
+        if meta.err.is_some() {
+            return Ok(vec![]);
+        }
+        let decoded = transaction
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| anyhow!("the transaction does not decode, fetch it as base64"))?;
+        let mut account_keys = decoded.message.static_account_keys().to_vec();
+        if let Some(loaded) = Option::<UiLoadedAddresses>::from(meta.loaded_addresses.clone()) {
+            for key in loaded.writable.iter().chain(&loaded.readonly) {
+                account_keys.push(Pubkey::from_str(key)?);
+            }
+        }
+        let balances = TokenBalances::new(meta, &account_keys)?;
+        let inner_instructions = inner_instructions(meta)?;
+
+        let mut records = vec![];
+        let mut record = |instruction_index: usize,
+                          inner_index: Option<usize>,
+                          swap: DecodedSwap,
+                          transfers: Option<Vec<Transfer>>| {
+            let incomplete = || anyhow!("Obric {:?} swap lacks an account", swap.version);
+            let pool = swap.trading_pair().ok_or_else(incomplete)?;
+            let user = swap.user().ok_or_else(incomplete)?;
+            let (source, destination) = swap.user_token_accounts().ok_or_else(incomplete)?;
+            let (in_amount, out_amount, protocol_fee) = amounts(
+                &swap,
+                (source, destination),
+                transfers.as_deref(),
+                &balances,
+            );
+            let (input_mint, output_mint) = match swap.mints() {
+                Some(mints) => mints,
+                None => (balances.mint(&source)?, balances.mint(&destination)?),
+            };
+            records.push(TradeRecord {
+                signature: decoded.signatures.first().copied().unwrap_or_default(),
+                slot: transaction.slot,
+                block_time: transaction.block_time,
+                instruction_index,
+                inner_index,
+                version: swap.version,
+                pool,
+                user,
+                x_to_y: swap.x_to_y,
+                input_mint,
+                output_mint,
+                in_amount,
+                out_amount,
+                min_output_amount: swap.min_output_amount,
+                protocol_fee,
+                fee: fee_millionth(&pool)
+                    .and_then(|fee_millionth| fee_for_output(out_amount, fee_millionth)),
+            });
+            anyhow::Ok(())
+        };
+        for (index, instruction) in decoded.message.instructions().iter().enumerate() {
+            let inner = inner_instructions
+                .get(&index)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            if let Some(swap) = self.decode_compiled(instruction, &account_keys)? {
+                let transfers = token_transfers(inner, &account_keys)?;
+                record(index, None, swap, Some(transfers))?;
+            }
+            for (inner_index, (instruction, stack_height)) in inner.iter().enumerate() {
+                let Some(swap) = self.decode_compiled(instruction, &account_keys)? else {
+                    continue;
+                };
+                let transfers = match stack_height {
+                    Some(height) => {
+                        let called: Vec<_> = inner[inner_index + 1..]
+                            .iter()
+                            .take_while(|(_, below)| below.is_some_and(|below| below > *height))
+                            .cloned()
+                            .collect();
+                        Some(token_transfers(&called, &account_keys)?)
+                    }
+                    None => None,
+                };
+                record(index, Some(inner_index), swap, transfers)?;
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Fee a pool charging `fee_millionth` took from a swap that paid `out_amount`. Both programs
+/// round the fee down, so the user is paid `gross - floor(gross * fee_millionth / 10^6)` of a curve
+/// output `gross`, and the smallest `gross` that pays `out_amount` is
+/// `floor((out_amount - 1) * 10^6 / (10^6 - fee_millionth)) + 1`. Under a fee of half the output
+/// at most two curve outputs pay the same, so the fee charged was this or 1 more.
+pub fn fee_for_output(out_amount: u64, fee_millionth: u64) -> Option<u64> {
+    const MILLION: u128 = 1_000_000;
+    let fee_millionth = fee_millionth as u128;
+    if fee_millionth >= MILLION {
+        return None;
+    }
+    let Some(below) = (out_amount as u128).checked_sub(1) else {
+        return Some(0);
+    };
+    let gross = below * MILLION / (MILLION - fee_millionth) + 1;
+    u64::try_from(gross - out_amount as u128).ok()
+}
+
+/// `(in_amount, out_amount, protocol_fee)` from the swap's own transfers when known, else the
+/// input argument and the balances the destination and the protocol fee account gained.
+fn amounts(
+    swap: &DecodedSwap,
+    (source, destination): (Pubkey, Pubkey),
+    transfers: Option<&[Transfer]>,
+    balances: &TokenBalances,
+) -> (u64, u64, Option<u64>) {
+    let Some(transfers) = transfers else {
+        return (
+            swap.input_amount,
+            balances.gained(&destination).unwrap_or_default(),
+            swap.protocol_fee_account()
+                .and_then(|account| balances.gained(&account)),
+        );
+    };
+    let sent = |from: &Pubkey| {
+        transfers
+            .iter()
+            .filter(|transfer| transfer.source == *from)
+            .map(|transfer| transfer.amount)
+            .sum::<u64>()
+    };
+    let received = |to: &Pubkey| {
+        transfers
+            .iter()
+            .filter(|transfer| transfer.destination == *to)
+            .map(|transfer| transfer.amount)
+            .sum::<u64>()
+    };
+    (
+        sent(&source),
+        received(&destination),
+        swap.protocol_fee_account()
+            .map(|account| received(&account)),
+    )
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Transfer {
+    source: Pubkey,
+    destination: Pubkey,
+    amount: u64,
+}
+
+fn token_transfers(
+    instructions: &[(CompiledInstruction, Option<u32>)],
+    account_keys: &[Pubkey],
+) -> Result<Vec<Transfer>> {
+    let mut transfers = vec![];
+    for (instruction, _) in instructions {
+        let program_id = account_key(account_keys, instruction.program_id_index)?;
+        if program_id != TOKEN_PROGRAM_ID && program_id != TOKEN_2022_PROGRAM_ID {
+            continue;
+        }
+        let destination = match instruction.data.first() {
+            Some(&TRANSFER) => 1,
+            Some(&TRANSFER_CHECKED) => 2,
+            _ => continue,
+        };
+        let (Some(amount), Some(source), Some(destination)) = (
+            instruction.data.get(1..9),
+            instruction.accounts.first(),
+            instruction.accounts.get(destination),
+        ) else {
+            bail!("malformed token transfer");
+        };
+        transfers.push(Transfer {
+            source: account_key(account_keys, *source)?,
+            destination: account_key(account_keys, *destination)?,
+            amount: u64::from_le_bytes(amount.try_into()?),
+        });
+    }
+    Ok(transfers)
+}
+
+/// By top level instruction, with their stack heights.
+fn inner_instructions(
+    meta: &UiTransactionStatusMeta,
+) -> Result<HashMap<usize, Vec<(CompiledInstruction, Option<u32>)>>> {
+    let mut by_index = HashMap::new();
+    let Some(inner) = Option::<Vec<_>>::from(meta.inner_instructions.clone()) else {
+        return Ok(by_index);
+    };
+    for inner in inner {
+        let mut instructions = vec![];
+        for instruction in inner.instructions {
+            let UiInstruction::Compiled(instruction) = instruction else {
+                bail!("inner instructions are parsed, fetch the transaction as base64");
+            };
+            instructions.push((
+                CompiledInstruction {
+                    program_id_index: instruction.program_id_index,
+                    accounts: instruction.accounts,
+                    data: bs58::decode(&instruction.data).into_vec()?,
+                },
+                instruction.stack_height,
+            ));
+        }
+        by_index.insert(inner.index as usize, instructions);
+    }
+    Ok(by_index)
+}
+
+fn account_key(account_keys: &[Pubkey], index: u8) -> Result<Pubkey> {
+    account_keys.get(index as usize).copied().ok_or_else(|| {
+        anyhow!(
+            "account index {index} is past the {} keys",
+            account_keys.len()
+        )
+    })
+}
+
+/// Mint and amount of every token account, before and after the transaction.
+struct TokenBalances {
+    pre: HashMap<Pubkey, (Pubkey, u64)>,
+    post: HashMap<Pubkey, (Pubkey, u64)>,
+}
+
+impl TokenBalances {
+    fn new(meta: &UiTransactionStatusMeta, account_keys: &[Pubkey]) -> Result<Self> {
+        let by_account = |balances: Option<Vec<UiTransactionTokenBalance>>| {
+            balances
+                .unwrap_or_default()
+                .iter()
+                .map(|balance| {
+                    Ok((
+                        account_key(account_keys, balance.account_index)?,
+                        (
+                            Pubkey::from_str(&balance.mint)?,
+                            balance.ui_token_amount.amount.parse::<u64>()?,
+                        ),
+                    ))
+                })
+                .collect::<Result<HashMap<_, _>>>()
+        };
+        Ok(Self {
+            pre: by_account(meta.pre_token_balances.clone().into())?,
+            post: by_account(meta.post_token_balances.clone().into())?,
+        })
+    }
+
+    fn mint(&self, account: &Pubkey) -> Result<Pubkey> {
+        self.post
+            .get(account)
+            .or_else(|| self.pre.get(account))
+            .map(|(mint, _)| *mint)
+            .ok_or_else(|| anyhow!("no token balance tells the mint of {account}"))
+    }
+
+    /// `None` when the transaction lists no balance of `account`.
+    fn gained(&self, account: &Pubkey) -> Option<u64> {
+        if !self.pre.contains_key(account) && !self.post.contains_key(account) {
+            return None;
+        }
+        let amount = |balances: &HashMap<Pubkey, (Pubkey, u64)>| {
+            balances.get(account).map_or(0, |(_, amount)| *amount)
+        };
+        Some(amount(&self.post).saturating_sub(amount(&self.pre)))
+    }
+}
@@ -0,0 +1,180 @@
+//! One interface over Obric v2 and v3 pools.
+//!
+//! `ObricPool` is implemented by `ObricV2Amm` and `ObricV3Amm`, so code that quotes, sizes or
+//! swaps against Obric does not need to know which program a pool belongs to. `ObricAmm` picks the
+//! implementation from a keyed account and also implements the Jupiter `Amm` interface the v2 sdk
+//! is on, so both sdks can be registered with a router the same way. `simulate_swap` applies a
+//! swap to the cached pool, Larix deposits and borrows included for v3, so a sequence of swaps can
+//! be quoted offline. `decode` reads Obric swaps back out of confirmed transactions.
+
+pub mod amm;
+pub mod decode;
+pub mod snapshot;
+pub mod v2;
+pub mod v3;
+
+pub use amm::ObricAmm;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{account::Account, instruction::Instruction, pubkey::Pubkey};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PoolVersion {
+    V2,
+    V3,
+}
+
+/// Amounts of each side the pool can pay out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reserves {
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub x: u64,
+    pub y: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolQuote {
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub fee_amount: u64,
+    pub fee_mint: Pubkey,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapInstructionParams {
+    pub input_mint: Pubkey,
+    pub in_amount: u64,
+    pub min_out_amount: u64,
+    /// Owner of both token accounts, signs the swap.
+    pub user: Pubkey,
+    pub source_token_account: Pubkey,
+    pub destination_token_account: Pubkey,
+}
+
+/// Decoded trading pair, the layouts of the two programs differ.
+#[derive(Clone, Debug)]
+pub enum PoolState {
+    V2(obric_solana::state::SSTradingPair),
+    V3(obric_solana_v3::state::SSTradingPair),
+}
+
+/// What the pool looked like at its last `update`.
+#[derive(Clone, Debug)]
+pub struct PoolSnapshot {
+    pub key: Pubkey,
+    pub program_id: Pubkey,
+    pub state: PoolState,
+    pub reserves: Reserves,
+    /// `None` while the pool cannot quote.
+    pub spot_price_x_in_y: Option<u128>,
+}
+
+pub trait ObricPool: Send + Sync {
+    fn version(&self) -> PoolVersion;
+
+    fn key(&self) -> Pubkey;
+
+    fn program_id(&self) -> Pubkey;
+
+    /// `(mint_x, mint_y)`
+    fn mints(&self) -> (Pubkey, Pubkey);
+
+    fn accounts_to_update(&self) -> Vec<Pubkey>;
+
+    fn update(&mut self, accounts: &HashMap<Pubkey, Account>) -> Result<()>;
+
+    fn reserves(&self) -> Result<Reserves>;
+
+    /// Price of X in Y before fees, fixed-point with `PRICE_DECIMALS` decimals.
+    fn spot_price_x_in_y(&self) -> Result<u128>;
+
+    /// Exact-in quote of `in_amount` of `input_mint` for the other mint of the pool.
+    fn quote(&self, input_mint: &Pubkey, in_amount: u64) -> Result<PoolQuote>;
+
+    /// `quote` as if an admin had set `concentration` and `fee_millionth`, on a copy of the pool.
+    fn quote_with_params(
+        &self,
+        input_mint: &Pubkey,
+        in_amount: u64,
+        concentration: u64,
+        fee_millionth: u64,
+    ) -> Result<PoolQuote>;
+
+    /// Decimals the feed prices the program reads, and `quote_at_prices` takes, are normalized to.
+    fn price_decimals(&self) -> u8;
+
+    /// `quote` as if the feeds had published `price_x` and `price_y`, in `price_decimals`, on a
+    /// copy of the pool.
+    fn quote_at_prices(
+        &self,
+        input_mint: &Pubkey,
+        in_amount: u64,
+        price_x: u64,
+        price_y: u64,
+    ) -> Result<PoolQuote>;
+
+    /// Swaps `in_amount` of `input_mint` on the cached state as the program would, so later
+    /// quotes see it, and returns what it filled at. The pool is left alone when it fails.
+    fn simulate_swap(&mut self, input_mint: &Pubkey, in_amount: u64) -> Result<PoolQuote>;
+
+    /// Largest `in_amount` of `input_mint` that `quote` accepts.
+    fn max_input(&self, input_mint: &Pubkey) -> Result<u64>;
+
+    /// Smallest exact-in quote of `input_mint` that pays out at least `out_amount`. Neither
+    /// program quotes exact out, so `quote` is bisected up to `max_input`.
+    fn quote_exact_out(&self, input_mint: &Pubkey, out_amount: u64) -> Result<PoolQuote> {
+        let max_input = self.max_input(input_mint)?;
+        let best = self.quote(input_mint, max_input)?;
+        if best.out_amount < out_amount {
+            bail!(
+                "pool {} pays out at most {} for {input_mint}",
+                self.key(),
+                best.out_amount
+            );
+        }
+        // `quote(high)` always pays out enough
+        let (mut low, mut high) = (0, max_input);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let paid_out = self
+                .quote(input_mint, mid)
+                .map_or(0, |quote| quote.out_amount);
+            if paid_out >= out_amount {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        self.quote(input_mint, high)
+    }
+
+    /// The program's swap instruction, ready to be signed by `params.user`.
+    fn swap_instruction(&self, params: &SwapInstructionParams) -> Result<Instruction>;
+
+    fn state(&self) -> PoolState;
+
+    fn snapshot(&self) -> Result<PoolSnapshot> {
+        Ok(PoolSnapshot {
+            key: self.key(),
+            program_id: self.program_id(),
+            state: self.state(),
+            reserves: self.reserves()?,
+            spot_price_x_in_y: self.spot_price_x_in_y().ok(),
+        })
+    }
+}
+
+/// Whether `input_mint` is X, and the mint it is swapped for.
+fn direction(pool: &dyn ObricPool, input_mint: &Pubkey) -> Result<(bool, Pubkey)> {
+    let (mint_x, mint_y) = pool.mints();
+    if *input_mint == mint_x {
+        Ok((true, mint_y))
+    } else if *input_mint == mint_y {
+        Ok((false, mint_x))
+    } else {
+        bail!("{input_mint} is not a mint of pool {}", pool.key())
+    }
+}
@@ -0,0 +1,325 @@
+//! A pool and every account its `update` reads, with the clock and slot they were read at, so a
+//! quote seen in production can be replayed offline.
+//!
+//! Snapshots are written as JSON, with base58 addresses and base64 account data like the RPC
+//! returns them, or as compact bincode. Both start with `version`, which readers check before
+//! decoding the rest.
+
+use crate::{ObricAmm, ObricPool};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use jupiter_amm_interface::{AmmContext, ClockRef, KeyedAccount};
+use obric_v2_sdk::{cluster as v2_cluster, obric_v2_amm::ObricV2Amm};
+use obric_v3_sdk::{cluster as v3_cluster, obric_v3_amm::ObricV3Amm};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{account::from_account, account::Account, clock::Clock, pubkey::Pubkey, sysvar};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+/// Bumped whenever a field is added, removed or changes meaning.
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u16,
+    #[serde(with = "pubkey_serde")]
+    pub key: Pubkey,
+    /// Slot the accounts were read at.
+    pub slot: u64,
+    /// The clock sysvar at `slot`, what the feeds are checked against.
+    pub clock: Clock,
+    /// The trading pair and its dependencies, ordered by address.
+    pub accounts: Vec<SnapshotAccount>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotAccount {
+    #[serde(with = "pubkey_serde")]
+    pub pubkey: Pubkey,
+    #[serde(with = "pubkey_serde")]
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    #[serde(with = "data_serde")]
+    pub data: Vec<u8>,
+}
+
+/// Everything a snapshot of `amm` holds: what `update` reads, the mints the v2 sdk only reads on
+/// the first update, and the Larix obligation of a v3 pair.
+pub fn dependencies(amm: &ObricAmm) -> Vec<Pubkey> {
+    let (mint_x, mint_y) = amm.mints();
+    let mut keys = amm.accounts_to_update();
+    match amm {
+        ObricAmm::V2(_) => keys.extend([mint_x, mint_y]),
+        ObricAmm::V3(amm) => keys.push(amm.obligation),
+    }
+    let mut seen = HashSet::new();
+    keys.retain(|key| seen.insert(*key));
+    keys
+}
+
+/// `record_with_clusters` on mainnet.
+pub fn record(client: &RpcClient, pool: &Pubkey) -> Result<Snapshot> {
+    record_with_clusters(
+        client,
+        pool,
+        &v2_cluster::Cluster::Mainnet,
+        &v3_cluster::Cluster::Mainnet,
+    )
+}
+
+/// Reads the pool, then its dependencies and the clock in one request so they share a slot.
+/// Fails when a dependency does not exist, since the snapshot would not load.
+pub fn record_with_clusters(
+    client: &RpcClient,
+    pool: &Pubkey,
+    v2_cluster: &v2_cluster::Cluster,
+    v3_cluster: &v3_cluster::Cluster,
+) -> Result<Snapshot> {
+    let keyed_account = KeyedAccount {
+        key: *pool,
+        account: client.get_account(pool)?,
+        params: None,
+    };
+    let amm = ObricAmm::from_keyed_account_with_clusters(
+        &keyed_account,
+        &AmmContext {
+            clock_ref: ClockRef::from(Clock::default()),
+        },
+        v2_cluster,
+        v3_cluster,
+    )?;
+
+    let mut keys = vec![sysvar::clock::id()];
+    keys.extend(dependencies(&amm));
+    let response = client.get_multiple_accounts_with_commitment(&keys, client.commitment())?;
+    let mut accounts = HashMap::new();
+    let mut missing = vec![];
+    for (key, account) in keys.iter().zip(response.value) {
+        match account {
+            Some(account) => {
+                accounts.insert(*key, account);
+            }
+            None => missing.push(key.to_string()),
+        }
+    }
+    if !missing.is_empty() {
+        bail!("{pool} depends on missing accounts {}", missing.join(", "));
+    }
+    let clock = accounts
+        .remove(&sysvar::clock::id())
+        .and_then(|account| from_account::<Clock, _>(&account))
+        .ok_or_else(|| anyhow!("the clock sysvar does not decode"))?;
+    Ok(Snapshot::new(
+        *pool,
+        response.context.slot,
+        clock,
+        &accounts,
+    ))
+}
+
+impl Snapshot {
+    /// `accounts` has to hold the trading pair at `key` besides what `update` reads.
+    pub fn new(key: Pubkey, slot: u64, clock: Clock, accounts: &HashMap<Pubkey, Account>) -> Self {
+        let mut accounts: Vec<_> = accounts
+            .iter()
+            .map(|(pubkey, account)| SnapshotAccount {
+                pubkey: *pubkey,
+                owner: account.owner,
+                lamports: account.lamports,
+                executable: account.executable,
+                rent_epoch: account.rent_epoch,
+                data: account.data.clone(),
+            })
+            .collect();
+        // a stable order keeps two snapshots of the same state byte-identical
+        accounts.sort_by_key(|account| account.pubkey);
+        Self {
+            version: SNAPSHOT_VERSION,
+            key,
+            slot,
+            clock,
+            accounts,
+        }
+    }
+
+    pub fn account_map(&self) -> HashMap<Pubkey, Account> {
+        self.accounts
+            .iter()
+            .map(|account| {
+                (
+                    account.pubkey,
+                    Account {
+                        lamports: account.lamports,
+                        data: account.data.clone(),
+                        owner: account.owner,
+                        executable: account.executable,
+                        rent_epoch: account.rent_epoch,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    pub fn keyed_account(&self) -> Result<KeyedAccount> {
+        let account = self
+            .account_map()
+            .remove(&self.key)
+            .ok_or_else(|| anyhow!("snapshot does not hold the trading pair {}", self.key))?;
+        Ok(KeyedAccount {
+            key: self.key,
+            account,
+            params: None,
+        })
+    }
+
+    pub fn amm_context(&self) -> AmmContext {
+        AmmContext {
+            clock_ref: ClockRef::from(self.clock.clone()),
+        }
+    }
+
+    /// `load_with_clusters` on mainnet.
+    pub fn load(&self) -> Result<ObricAmm> {
+        self.load_with_clusters(&v2_cluster::Cluster::Mainnet, &v3_cluster::Cluster::Mainnet)
+    }
+
+    /// The pool as it was at `slot`, updated and ready to quote.
+    pub fn load_with_clusters(
+        &self,
+        v2_cluster: &v2_cluster::Cluster,
+        v3_cluster: &v3_cluster::Cluster,
+    ) -> Result<ObricAmm> {
+        let mut amm = ObricAmm::from_keyed_account_with_clusters(
+            &self.keyed_account()?,
+            &self.amm_context(),
+            v2_cluster,
+            v3_cluster,
+        )?;
+        ObricPool::update(&mut amm, &self.account_map())?;
+        Ok(amm)
+    }
+
+    /// `load` for a snapshot known to be of a mainnet v2 pair.
+    pub fn load_v2(&self) -> Result<ObricV2Amm> {
+        match self.load()? {
+            ObricAmm::V2(amm) => Ok(*amm),
+            ObricAmm::V3(_) => bail!("{} is a v3 pair", self.key),
+        }
+    }
+
+    /// `load` for a snapshot known to be of a mainnet v3 pair.
+    pub fn load_v3(&self) -> Result<ObricV3Amm> {
+        match self.load()? {
+            ObricAmm::V3(amm) => Ok(*amm),
+            ObricAmm::V2(_) => bail!("{} is a v2 pair", self.key),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u16,
+        }
+        check_version(serde_json::from_str::<Versioned>(json)?.version)?;
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_bincode(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self> {
+        // `version` is the first field, the rest may be laid out differently
+        check_version(bincode::deserialize::<u16>(bytes)?)?;
+        Ok(bincode::deserialize(bytes)?)
+    }
+
+    /// Reads bincode from `.bin` files and JSON from anything else.
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        if is_bincode(path) {
+            Self::from_bincode(&bytes)
+        } else {
+            Self::from_json(std::str::from_utf8(&bytes)?)
+        }
+        .with_context(|| format!("decoding {}", path.display()))
+    }
+
+    /// Writes bincode to `.bin` files and JSON to anything else.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let bytes = if is_bincode(path) {
+            self.to_bincode()?
+        } else {
+            self.to_json()?.into_bytes()
+        };
+        fs::write(path, bytes).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+fn is_bincode(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "bin")
+}
+
+fn check_version(version: u16) -> Result<()> {
+    if version != SNAPSHOT_VERSION {
+        bail!("snapshot version {version} is not supported, expected {SNAPSHOT_VERSION}");
+    }
+    Ok(())
+}
+
+/// Base58 in JSON, the raw 32 bytes in bincode.
+mod pubkey_serde {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(pubkey)
+        } else {
+            pubkey.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        if deserializer.is_human_readable() {
+            let string = String::deserialize(deserializer)?;
+            Pubkey::from_str(&string).map_err(D::Error::custom)
+        } else {
+            Pubkey::deserialize(deserializer)
+        }
+    }
+}
+
+/// Base64 in JSON, the bytes as they are in bincode.
+mod data_serde {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(data))
+        } else {
+            data.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            let string = String::deserialize(deserializer)?;
+            STANDARD.decode(string).map_err(D::Error::custom)
+        } else {
+            Vec::deserialize(deserializer)
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    direction, ObricPool, PoolQuote, PoolState, PoolVersion, Reserves, SwapInstructionParams,
+};
+use anchor_lang::InstructionData;
+use anyhow::Result;
+use jupiter_amm_interface::{Amm, QuoteParams, SwapMode, SwapParams};
+use obric_v2_sdk::obric_v2_amm::ObricV2Amm;
+use solana_sdk::{account::Account, instruction::Instruction, pubkey::Pubkey};
+use std::collections::HashMap;
+
+impl ObricPool for ObricV2Amm {
+    fn version(&self) -> PoolVersion {
+        PoolVersion::V2
+    }
+
+    fn key(&self) -> Pubkey {
+        self.key
+    }
+
+    fn program_id(&self) -> Pubkey {
+        self.cluster.program_id
+    }
+
+    fn mints(&self) -> (Pubkey, Pubkey) {
+        (self.state.mint_x, self.state.mint_y)
+    }
+
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        self.get_accounts_to_update()
+    }
+
+    fn update(&mut self, accounts: &HashMap<Pubkey, Account>) -> Result<()> {
+        Amm::update(self, accounts)
+    }
+
+    fn reserves(&self) -> Result<Reserves> {
+        Ok(Reserves {
+            mint_x: self.state.mint_x,
+            mint_y: self.state.mint_y,
+            x: self.current_x,
+            y: self.current_y,
+        })
+    }
+
+    fn spot_price_x_in_y(&self) -> Result<u128> {
+        ObricV2Amm::spot_price_x_in_y(self)
+    }
+
+    fn quote(&self, input_mint: &Pubkey, in_amount: u64) -> Result<PoolQuote> {
+        let (_, output_mint) = direction(self, input_mint)?;
+        let quote = Amm::quote(
+            self,
+            &QuoteParams {
+                amount: in_amount,
+                input_mint: *input_mint,
+                output_mint,
+                swap_mode: SwapMode::ExactIn,
+            },
+        )?;
+        Ok(PoolQuote {
+            in_amount,
+            out_amount: quote.out_amount,
+            fee_amount: quote.fee_amount,
+            fee_mint: quote.fee_mint,
+        })
+    }
+
+    fn quote_with_params(
+        &self,
+        input_mint: &Pubkey,
+        in_amount: u64,
+        concentration: u64,
+        fee_millionth: u64,
+    ) -> Result<PoolQuote> {
+        ObricPool::quote(
+            &self.with_params(concentration, fee_millionth)?,
+            input_mint,
+            in_amount,
+        )
+    }
+
+    fn price_decimals(&self) -> u8 {
+        self.state.price_decimals
+    }
+
+    fn quote_at_prices(
+        &self,
+        input_mint: &Pubkey,
+        in_amount: u64,
+        price_x: u64,
+        price_y: u64,
+    ) -> Result<PoolQuote> {
+        ObricPool::quote(&self.with_prices(price_x, price_y)?, input_mint, in_amount)
+    }
+
+    fn simulate_swap(&mut self, input_mint: &Pubkey, in_amount: u64) -> Result<PoolQuote> {
+        let (_, output_mint) = direction(self, input_mint)?;
+        let quote = ObricV2Amm::simulate_swap(
+            self,
+            &QuoteParams {
+                amount: in_amount,
+                input_mint: *input_mint,
+                output_mint,
+                swap_mode: SwapMode::ExactIn,
+            },
+        )?;
+        Ok(PoolQuote {
+            in_amount,
+            out_amount: quote.out_amount,
+            fee_amount: quote.fee_amount,
+            fee_mint: quote.fee_mint,
+        })
+    }
+
+    fn max_input(&self, input_mint: &Pubkey) -> Result<u64> {
+        ObricV2Amm::max_input(self, input_mint)
+    }
+
+    fn swap_instruction(&self, params: &SwapInstructionParams) -> Result<Instruction> {
+        let (x_to_y, output_mint) = direction(self, &params.input_mint)?;
+        let jupiter_program_id = Pubkey::default();
+        let mut accounts = self
+            .get_swap_and_account_metas(&SwapParams {
+                swap_mode: SwapMode::ExactIn,
+                in_amount: params.in_amount,
+                out_amount: params.min_out_amount,
+                source_mint: params.input_mint,
+                destination_mint: output_mint,
+                source_token_account: params.source_token_account,
+                destination_token_account: params.destination_token_account,
+                token_transfer_authority: params.user,
+                quote_mint_to_referrer: None,
+                jupiter_program_id: &jupiter_program_id,
+                missing_dynamic_accounts_as_default: false,
+            })?
+            .account_metas;
+        // Jupiter lists the program in front of the `Swap` accounts and marks the signer itself
+        accounts.remove(0);
+        for meta in &mut accounts {
+            meta.is_signer |= meta.pubkey == params.user;
+        }
+        Ok(Instruction {
+            program_id: self.cluster.program_id,
+            accounts,
+            data: obric_solana::instruction::Swap {
+                _is_x_to_y: x_to_y,
+                _input_amt: params.in_amount,
+                _min_output_amt: params.min_out_amount,
+            }
+            .data(),
+        })
+    }
+
+    fn state(&self) -> PoolState {
+        PoolState::V2(self.state.clone())
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    direction, ObricPool, PoolQuote, PoolState, PoolVersion, Reserves, SwapInstructionParams,
+};
+use anchor_lang::InstructionData;
+use anyhow::Result;
+use jupiter_amm_interface_v3::{Amm, QuoteParams, SwapParams};
+use obric_solana_v3::instruction::{SwapXToY, SwapYToX};
+use obric_v3_sdk::constants::FEED_PRICE_DECIMALS;
+use obric_v3_sdk::obric_v3_amm::ObricV3Amm;
+use solana_sdk::{account::Account, instruction::Instruction, pubkey::Pubkey};
+use std::collections::HashMap;
+
+impl ObricPool for ObricV3Amm {
+    fn version(&self) -> PoolVersion {
+        PoolVersion::V3
+    }
+
+    fn key(&self) -> Pubkey {
+        self.key
+    }
+
+    fn program_id(&self) -> Pubkey {
+        self.cluster.program_id
+    }
+
+    fn mints(&self) -> (Pubkey, Pubkey) {
+        (self.state.mint_x, self.state.mint_y)
+    }
+
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        self.get_accounts_to_update()
+    }
+
+    fn update(&mut self, accounts: &HashMap<Pubkey, Account>) -> Result<()> {
+        Amm::update(self, accounts)
+    }
+
+    /// The `available_*` amounts of `SSTradingPair::get_pool_values_for_quoting`.
+    fn reserves(&self) -> Result<Reserves> {
+        let (_, _, _, available_x, available_y) = match self.pool_values {
+            Some(pool_values) => pool_values,
+            None => self.state.get_pool_values_for_quoting()?,
+        };
+        Ok(Reserves {
+            mint_x: self.state.mint_x,
+            mint_y: self.state.mint_y,
+            x: available_x,
+            y: available_y,
+        })
+    }
+
+    fn spot_price_x_in_y(&self) -> Result<u128> {
+        ObricV3Amm::spot_price_x_in_y(self)
+    }
+
+    fn quote(&self, input_mint: &Pubkey, in_amount: u64) -> Result<PoolQuote> {
+        let (_, output_mint) = direction(self, input_mint)?;
+        let quote = Amm::quote(
+            self,
+            &QuoteParams {
+                in_amount,
+                input_mint: *input_mint,
+                output_mint,
+            },
+        )?;
+        Ok(PoolQuote {
+            in_amount,
+            out_amount: quote.out_amount,
+            fee_amount: quote.fee_amount,
+            fee_mint: quote.fee_mint,
+        })
+    }
+
+    fn quote_with_params(
+        &self,
+        input_mint: &Pubkey,
+        in_amount: u64,
+        concentration: u64,
+        fee_millionth: u64,
+    ) -> Result<PoolQuote> {
+        ObricPool::quote(
+            &self.with_params(concentration, fee_millionth)?,
+            input_mint,
+            in_amount,
+        )
+    }
+
+    fn price_decimals(&self) -> u8 {
+        FEED_PRICE_DECIMALS
+    }
+
+    fn quote_at_prices(
+        &self,
+        input_mint: &Pubkey,
+        in_amount: u64,
+        price_x: u64,
+        price_y: u64,
+    ) -> Result<PoolQuote> {
+        ObricPool::quote(&self.with_prices(price_x, price_y)?, input_mint, in_amount)
+    }
+
+    fn simulate_swap(&mut self, input_mint: &Pubkey, in_amount: u64) -> Result<PoolQuote> {
+        let (_, output_mint) = direction(self, input_mint)?;
+        let quote = ObricV3Amm::simulate_swap(
+            self,
+            &QuoteParams {
+                in_amount,
+                input_mint: *input_mint,
+                output_mint,
+            },
+        )?;
+        Ok(PoolQuote {
+            in_amount,
+            out_amount: quote.out_amount,
+            fee_amount: quote.fee_amount,
+            fee_mint: quote.fee_mint,
+        })
+    }
+
+    fn max_input(&self, input_mint: &Pubkey) -> Result<u64> {
+        ObricV3Amm::max_input(self, input_mint)
+    }
+
+    fn swap_instruction(&self, params: &SwapInstructionParams) -> Result<Instruction> {
+        let (x_to_y, output_mint) = direction(self, &params.input_mint)?;
+        let jupiter_program_id = Pubkey::default();
+        let accounts = self
+            .get_swap_and_account_metas(&SwapParams {
+                in_amount: params.in_amount,
+                source_mint: params.input_mint,
+                destination_mint: output_mint,
+                source_token_account: params.source_token_account,
+                destination_token_account: params.destination_token_account,
+                token_transfer_authority: params.user,
+                open_order_address: None,
+                quote_mint_to_referrer: None,
+                jupiter_program_id: &jupiter_program_id,
+            })?
+            .account_metas;
+        let data = if x_to_y {
+            SwapXToY {
+                _input_x: params.in_amount,
+                _min_output_amt: params.min_out_amount,
+            }
+            .data()
+        } else {
+            SwapYToX {
+                _input_y: params.in_amount,
+                _min_output_amt: params.min_out_amount,
+            }
+            .data()
+        };
+        Ok(Instruction {
+            program_id: self.cluster.program_id,
+            accounts,
+            data,
+        })
+    }
+
+    fn state(&self) -> PoolState {
+        PoolState::V3(self.state.clone())
+    }
+}
@@ -0,0 +1,448 @@
+//! Swap instructions built through the facade decode back to their arguments and accounts, and
+//! transactions that run them, directly or through a router, parse into trade records.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use jupiter_amm_interface::{AmmContext, ClockRef, KeyedAccount};
+use obric::decode::{fee_for_output, SwapDecoder, TradeRecord};
+use obric::{ObricAmm, ObricPool, PoolState, PoolVersion, SwapInstructionParams};
+use obric_bench::{v2_pool, v3_pool, Fixture, Oracle, NOW};
+use solana_account_decoder::parse_token::token_amount_to_ui_amount;
+use solana_sdk::{
+    clock::Clock,
+    instruction::{AccountMeta, CompiledInstruction, Instruction, InstructionError},
+    message::Message,
+    pubkey,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{Transaction, TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction,
+    EncodedTransactionWithStatusMeta, InnerInstruction, InnerInstructions,
+    TransactionBinaryEncoding, TransactionStatusMeta, TransactionTokenBalance,
+};
+
+const SLOT: u64 = 250_000_000;
+const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+fn updated_amm(fixture: &Fixture) -> ObricAmm {
+    let amm_context = AmmContext {
+        clock_ref: ClockRef::from(Clock {
+            unix_timestamp: NOW,
+            ..Clock::default()
+        }),
+    };
+    let keyed_account = KeyedAccount {
+        key: fixture.key,
+        account: fixture.account.clone(),
+        params: None,
+    };
+    let mut amm = ObricAmm::from_keyed_account(&keyed_account, &amm_context).unwrap();
+    ObricPool::update(&mut amm, &fixture.accounts).unwrap();
+    amm
+}
+
+fn fee_millionth(amm: &ObricAmm) -> u64 {
+    match amm.state() {
+        PoolState::V2(state) => state.fee_millionth,
+        PoolState::V3(state) => state.fee_millionth,
+    }
+}
+
+/// The v3 sdk lists `mint_x` and `mint_y` after the pair for swaps from Y to X too, `SwapYToX`
+/// declares neither.
+fn as_declared(mut instruction: Instruction, swap_y_to_x: bool) -> Instruction {
+    if swap_y_to_x && instruction.program_id == obric_v3_sdk::constants::PROGRAM_ID {
+        instruction.accounts.drain(1..3);
+    }
+    instruction
+}
+
+fn swap_params(input_mint: Pubkey, in_amount: u64) -> SwapInstructionParams {
+    SwapInstructionParams {
+        input_mint,
+        in_amount,
+        min_out_amount: 1,
+        user: Pubkey::new_unique(),
+        source_token_account: Pubkey::new_unique(),
+        destination_token_account: Pubkey::new_unique(),
+    }
+}
+
+fn index(keys: &[Pubkey], key: &Pubkey) -> u8 {
+    keys.iter().position(|listed| listed == key).unwrap() as u8
+}
+
+fn transfer(
+    keys: &[Pubkey],
+    (source, destination, authority): (Pubkey, Pubkey, Pubkey),
+    amount: u64,
+    stack_height: Option<u32>,
+) -> InnerInstruction {
+    let mut data = vec![3];
+    data.extend_from_slice(&amount.to_le_bytes());
+    InnerInstruction {
+        instruction: CompiledInstruction {
+            program_id_index: index(keys, &TOKEN_PROGRAM_ID),
+            accounts: vec![
+                index(keys, &source),
+                index(keys, &destination),
+                index(keys, &authority),
+            ],
+            data,
+        },
+        stack_height,
+    }
+}
+
+fn balance(keys: &[Pubkey], account: Pubkey, mint: Pubkey, amount: u64) -> TransactionTokenBalance {
+    TransactionTokenBalance {
+        account_index: index(keys, &account),
+        mint: mint.to_string(),
+        ui_token_amount: token_amount_to_ui_amount(amount, 6),
+        owner: String::new(),
+        program_id: TOKEN_PROGRAM_ID.to_string(),
+    }
+}
+
+/// `(pre, post)` balances of the user's accounts for a swap of `in_amount` paying `out_amount`.
+fn user_balances(
+    keys: &[Pubkey],
+    params: &SwapInstructionParams,
+    output_mint: Pubkey,
+    out_amount: u64,
+) -> (Vec<TransactionTokenBalance>, Vec<TransactionTokenBalance>) {
+    let (source, destination) = (
+        params.source_token_account,
+        params.destination_token_account,
+    );
+    (
+        vec![
+            balance(keys, source, params.input_mint, params.in_amount),
+            balance(keys, destination, output_mint, 0),
+        ],
+        vec![
+            balance(keys, source, params.input_mint, 0),
+            balance(keys, destination, output_mint, out_amount),
+        ],
+    )
+}
+
+fn confirmed(
+    message: Message,
+    meta: TransactionStatusMeta,
+) -> EncodedConfirmedTransactionWithStatusMeta {
+    let transaction = VersionedTransaction::from(Transaction::new_unsigned(message));
+    EncodedConfirmedTransactionWithStatusMeta {
+        slot: SLOT,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::Binary(
+                STANDARD.encode(bincode::serialize(&transaction).unwrap()),
+                TransactionBinaryEncoding::Base64,
+            ),
+            meta: Some(meta.into()),
+            version: None,
+        },
+        block_time: Some(NOW),
+    }
+}
+
+#[test]
+fn facade_swap_instructions_decode() {
+    let decoder = SwapDecoder::default();
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let amm = updated_amm(&fixture);
+        let (mint_x, mint_y) = amm.mints();
+        for (input_mint, output_mint) in [(mint_x, mint_y), (mint_y, mint_x)] {
+            let params = swap_params(input_mint, 1_000_000);
+            let y_to_x = input_mint == mint_y;
+            let instruction = as_declared(amm.swap_instruction(&params).unwrap(), y_to_x);
+            let swap = decoder.decode_instruction(&instruction).unwrap().unwrap();
+            assert_eq!(swap.version, amm.version());
+            assert_eq!(swap.program_id, amm.program_id());
+            assert_eq!(swap.x_to_y, input_mint == mint_x);
+            assert_eq!((swap.input_amount, swap.min_output_amount), (1_000_000, 1));
+            assert_eq!(swap.accounts.len(), instruction.accounts.len());
+            assert_eq!(swap.trading_pair(), Some(fixture.key));
+            assert_eq!(swap.user(), Some(params.user));
+            assert_eq!(
+                swap.user_token_accounts(),
+                Some((
+                    params.source_token_account,
+                    params.destination_token_account
+                ))
+            );
+            let lists_mints = swap.version == PoolVersion::V2 || !y_to_x;
+            assert_eq!(
+                swap.mints(),
+                lists_mints.then_some((input_mint, output_mint))
+            );
+            assert_eq!(
+                swap.protocol_fee_account().is_some(),
+                swap.version == PoolVersion::V3
+            );
+        }
+    }
+}
+
+#[test]
+fn other_programs_and_malformed_swaps() {
+    let decoder = SwapDecoder::default();
+    let amm = updated_amm(&v2_pool(Oracle::Pyth));
+    let mut instruction = amm
+        .swap_instruction(&swap_params(amm.mints().0, 1_000_000))
+        .unwrap();
+    let accounts: Vec<_> = instruction
+        .accounts
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+
+    // any Anchor program with a `swap` instruction shares the discriminator
+    let foreign = Pubkey::new_unique();
+    assert_eq!(
+        decoder
+            .decode(&foreign, &instruction.data, &accounts)
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        decoder
+            .decode(&instruction.program_id, &[0; 8], &accounts)
+            .unwrap(),
+        None
+    );
+    assert!(decoder
+        .decode(&instruction.program_id, &instruction.data[..12], &accounts)
+        .is_err());
+    instruction.accounts.pop();
+    assert!(decoder.decode_instruction(&instruction).is_err());
+}
+
+#[test]
+fn top_level_swaps_are_read_off_their_own_transfers() {
+    let decoder = SwapDecoder::default();
+    let fixture = v2_pool(Oracle::Pyth);
+    let amm = updated_amm(&fixture);
+    let (mint_x, mint_y) = amm.mints();
+    let params = swap_params(mint_x, 1_000_000_000);
+    let quote = amm.quote(&mint_x, params.in_amount).unwrap();
+    let instruction = amm.swap_instruction(&params).unwrap();
+    let swap = decoder.decode_instruction(&instruction).unwrap().unwrap();
+    let (reserve_x, reserve_y) = (
+        swap.account("reserve_x").unwrap(),
+        swap.account("reserve_y").unwrap(),
+    );
+
+    let message = Message::new(&[instruction], Some(&params.user));
+    let keys = message.account_keys.clone();
+    let (pre, post) = user_balances(&keys, &params, mint_y, quote.out_amount);
+    let meta = TransactionStatusMeta {
+        inner_instructions: Some(vec![InnerInstructions {
+            index: 0,
+            instructions: vec![
+                transfer(
+                    &keys,
+                    (params.source_token_account, reserve_x, params.user),
+                    params.in_amount,
+                    Some(2),
+                ),
+                transfer(
+                    &keys,
+                    (reserve_y, params.destination_token_account, fixture.key),
+                    quote.out_amount,
+                    Some(2),
+                ),
+            ],
+        }]),
+        pre_token_balances: Some(pre),
+        post_token_balances: Some(post),
+        ..TransactionStatusMeta::default()
+    };
+    let records = decoder
+        .parse_transaction(&confirmed(message, meta), |_| Some(fee_millionth(&amm)))
+        .unwrap();
+    let fee = records[0].fee;
+    assert!(fee.unwrap().abs_diff(quote.fee_amount) <= 1);
+    assert_eq!(
+        records,
+        vec![TradeRecord {
+            signature: Signature::default(),
+            slot: SLOT,
+            block_time: Some(NOW),
+            instruction_index: 0,
+            inner_index: None,
+            version: PoolVersion::V2,
+            pool: fixture.key,
+            user: params.user,
+            x_to_y: true,
+            input_mint: mint_x,
+            output_mint: mint_y,
+            in_amount: params.in_amount,
+            out_amount: quote.out_amount,
+            min_output_amount: 1,
+            protocol_fee: None,
+            fee,
+        }]
+    );
+}
+
+#[test]
+fn routed_swaps_keep_to_the_transfers_below_them() {
+    let decoder = SwapDecoder::default();
+    let fixture = v3_pool();
+    let amm = updated_amm(&fixture);
+    let (mint_x, mint_y) = amm.mints();
+    let params = swap_params(mint_y, 150_000_000);
+    let quote = amm.quote(&mint_y, params.in_amount).unwrap();
+    let protocol_fee = quote.fee_amount / 5;
+    let instruction = as_declared(amm.swap_instruction(&params).unwrap(), true);
+    let swap = decoder.decode_instruction(&instruction).unwrap().unwrap();
+    let (reserve_x, reserve_y, protocol_fee_x) = (
+        swap.account("reserve_x").unwrap(),
+        swap.account("reserve_y").unwrap(),
+        swap.protocol_fee_account().unwrap(),
+    );
+
+    let mut router_accounts = instruction.accounts.clone();
+    router_accounts.push(AccountMeta::new_readonly(instruction.program_id, false));
+    let router = Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: router_accounts,
+        data: vec![],
+    };
+    let message = Message::new(&[router], Some(&params.user));
+    let keys = message.account_keys.clone();
+    let called = CompiledInstruction {
+        program_id_index: index(&keys, &instruction.program_id),
+        accounts: instruction
+            .accounts
+            .iter()
+            .map(|meta| index(&keys, &meta.pubkey))
+            .collect(),
+        data: instruction.data.clone(),
+    };
+    let destination = params.destination_token_account;
+    let inner = |stack_height: fn(u32) -> Option<u32>| {
+        vec![
+            InnerInstruction {
+                instruction: called.clone(),
+                stack_height: stack_height(2),
+            },
+            transfer(
+                &keys,
+                (params.source_token_account, reserve_y, params.user),
+                params.in_amount,
+                stack_height(3),
+            ),
+            transfer(
+                &keys,
+                (reserve_x, destination, fixture.key),
+                quote.out_amount,
+                stack_height(3),
+            ),
+            transfer(
+                &keys,
+                (reserve_x, protocol_fee_x, fixture.key),
+                protocol_fee,
+                stack_height(3),
+            ),
+            // the router pays into the same account once the swap is done
+            transfer(
+                &keys,
+                (reserve_y, destination, params.user),
+                1_000,
+                stack_height(2),
+            ),
+        ]
+    };
+    let (mut pre, mut post) = user_balances(&keys, &params, mint_x, quote.out_amount + 1_000);
+    pre.push(balance(&keys, protocol_fee_x, mint_x, 0));
+    post.push(balance(&keys, protocol_fee_x, mint_x, protocol_fee));
+    let meta = |stack_height| TransactionStatusMeta {
+        inner_instructions: Some(vec![InnerInstructions {
+            index: 0,
+            instructions: inner(stack_height),
+        }]),
+        pre_token_balances: Some(pre.clone()),
+        post_token_balances: Some(post.clone()),
+        ..TransactionStatusMeta::default()
+    };
+
+    let fees = |pool: &Pubkey| (*pool == fixture.key).then(|| fee_millionth(&amm));
+    let records = decoder
+        .parse_transaction(&confirmed(message.clone(), meta(Some)), fees)
+        .unwrap();
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!((record.instruction_index, record.inner_index), (0, Some(0)));
+    assert_eq!(
+        (record.version, record.pool, record.user, record.x_to_y),
+        (PoolVersion::V3, fixture.key, params.user, false)
+    );
+    assert_eq!((record.input_mint, record.output_mint), (mint_y, mint_x));
+    assert_eq!(
+        (record.in_amount, record.out_amount, record.protocol_fee),
+        (params.in_amount, quote.out_amount, Some(protocol_fee))
+    );
+    assert!(record.fee.unwrap().abs_diff(quote.fee_amount) <= 1);
+
+    // without stack heights the balances count the router's transfer too
+    let records = decoder
+        .parse_transaction(&confirmed(message, meta(|_| None)), fees)
+        .unwrap();
+    assert_eq!(
+        (
+            records[0].in_amount,
+            records[0].out_amount,
+            records[0].protocol_fee
+        ),
+        (
+            params.in_amount,
+            quote.out_amount + 1_000,
+            Some(protocol_fee)
+        )
+    );
+}
+
+#[test]
+fn fees_come_back_from_the_output() {
+    let paid = |gross: u64, fee_millionth: u64| {
+        gross - (gross as u128 * fee_millionth as u128 / 1_000_000) as u64
+    };
+    for fee_millionth in [0, 1, 2_500, 30_000, 499_999, 999_999] {
+        for gross in (0..20_000).chain([u64::MAX / 3, u64::MAX]) {
+            let out_amount = paid(gross, fee_millionth);
+            let fee = fee_for_output(out_amount, fee_millionth).unwrap();
+            // the smallest curve output that pays `out_amount`
+            assert_eq!(paid(out_amount + fee, fee_millionth), out_amount);
+            assert!(fee == 0 || paid(out_amount + fee - 1, fee_millionth) < out_amount);
+            assert!(fee <= gross - out_amount);
+            if fee_millionth < 500_000 {
+                assert!(gross - out_amount - fee <= 1);
+            }
+        }
+    }
+    assert_eq!(fee_for_output(1, 1_000_000), None);
+}
+
+#[test]
+fn failed_transactions_trade_nothing() {
+    let amm = updated_amm(&v2_pool(Oracle::Doves));
+    let params = swap_params(amm.mints().0, 1_000_000);
+    let message = Message::new(
+        &[amm.swap_instruction(&params).unwrap()],
+        Some(&params.user),
+    );
+    let meta = TransactionStatusMeta {
+        status: Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(6000),
+        )),
+        ..TransactionStatusMeta::default()
+    };
+    let records = SwapDecoder::default()
+        .parse_transaction(&confirmed(message, meta), |_| None)
+        .unwrap();
+    assert!(records.is_empty());
+}
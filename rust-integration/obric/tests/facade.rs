@@ -0,0 +1,367 @@
+//! `ObricAmm` against the bench fixtures: the factory picks the sdk from the owner, and quotes and
+//! swap instructions through the facade match what the sdks produce on their own.
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator, InstructionData};
+use jupiter_amm_interface::{AmmContext, ClockRef, KeyedAccount};
+use obric::{ObricAmm, ObricPool, PoolState, PoolVersion, SwapInstructionParams};
+use obric_bench::{v2_pool, v3_pool, Fixture, Oracle, NOW};
+use obric_v3_sdk::constants::{SWAP_X_TO_Y_ACCOUNTS_LEN, SWAP_Y_TO_X_ACCOUNTS_LEN};
+use solana_sdk::{clock::Clock, pubkey::Pubkey};
+
+fn amm_context() -> AmmContext {
+    AmmContext {
+        clock_ref: ClockRef::from(Clock {
+            unix_timestamp: NOW,
+            ..Clock::default()
+        }),
+    }
+}
+
+fn keyed_account(fixture: &Fixture) -> KeyedAccount {
+    KeyedAccount {
+        key: fixture.key,
+        account: fixture.account.clone(),
+        params: None,
+    }
+}
+
+fn updated_amm(fixture: &Fixture) -> ObricAmm {
+    let mut amm = ObricAmm::from_keyed_account(&keyed_account(fixture), &amm_context()).unwrap();
+    ObricPool::update(&mut amm, &fixture.accounts).unwrap();
+    amm
+}
+
+fn swap_params(input_mint: Pubkey, in_amount: u64) -> SwapInstructionParams {
+    SwapInstructionParams {
+        input_mint,
+        in_amount,
+        min_out_amount: 1,
+        user: Pubkey::new_unique(),
+        source_token_account: Pubkey::new_unique(),
+        destination_token_account: Pubkey::new_unique(),
+    }
+}
+
+#[test]
+fn both_programs_share_the_pair_discriminator() {
+    assert_eq!(
+        obric_solana::state::SSTradingPair::DISCRIMINATOR,
+        obric_solana_v3::state::SSTradingPair::DISCRIMINATOR
+    );
+}
+
+#[test]
+fn factory_picks_the_sdk_from_the_owner() {
+    let v2 = updated_amm(&v2_pool(Oracle::Pyth));
+    assert_eq!(v2.version(), PoolVersion::V2);
+    assert_eq!(ObricPool::program_id(&v2), obric_v2_sdk::obric_v2_amm::id());
+
+    let v3 = updated_amm(&v3_pool());
+    assert_eq!(v3.version(), PoolVersion::V3);
+    assert_eq!(
+        ObricPool::program_id(&v3),
+        obric_v3_sdk::constants::PROGRAM_ID
+    );
+}
+
+#[test]
+fn factory_rejects_other_accounts() {
+    let fixture = v2_pool(Oracle::Pyth);
+
+    let mut foreign = keyed_account(&fixture);
+    foreign.account.owner = Pubkey::new_unique();
+    assert!(ObricAmm::from_keyed_account(&foreign, &amm_context()).is_err());
+
+    let mut not_a_pair = keyed_account(&fixture);
+    not_a_pair.account.data[0] ^= 0xff;
+    assert!(ObricAmm::from_keyed_account(&not_a_pair, &amm_context()).is_err());
+}
+
+#[test]
+fn quotes_match_the_sdks() {
+    for fixture in [v2_pool(Oracle::Pyth), v2_pool(Oracle::Doves), v3_pool()] {
+        let amm = updated_amm(&fixture);
+        let (mint_x, mint_y) = amm.mints();
+        let reserves = amm.reserves().unwrap();
+        assert_eq!((reserves.mint_x, reserves.mint_y), (mint_x, mint_y));
+
+        // 1 SOL and 150 USDC
+        for (input_mint, in_amount) in [(mint_x, 1_000_000_000), (mint_y, 150_000_000)] {
+            let facade = ObricPool::quote(&amm, &input_mint, in_amount).unwrap();
+            let sdk = match &amm {
+                ObricAmm::V2(pool) => ObricPool::quote(pool.as_ref(), &input_mint, in_amount),
+                ObricAmm::V3(pool) => ObricPool::quote(pool.as_ref(), &input_mint, in_amount),
+            }
+            .unwrap();
+            assert_eq!(facade, sdk);
+            assert_eq!(facade.in_amount, in_amount);
+            assert!(facade.out_amount > 0);
+        }
+        assert!(ObricPool::quote(&amm, &Pubkey::new_unique(), 1).is_err());
+    }
+}
+
+#[test]
+fn v2_swap_instruction() {
+    let amm = updated_amm(&v2_pool(Oracle::Pyth));
+    let (mint_x, _) = amm.mints();
+    let params = swap_params(mint_x, 1_000_000_000);
+    let ix = amm.swap_instruction(&params).unwrap();
+
+    assert_eq!(ix.program_id, obric_v2_sdk::obric_v2_amm::id());
+    assert_eq!(ix.accounts.len(), 12);
+    assert!(ix.accounts.iter().all(|meta| meta.pubkey != ix.program_id));
+    let signers: Vec<_> = ix.accounts.iter().filter(|meta| meta.is_signer).collect();
+    assert_eq!(signers.len(), 1);
+    assert_eq!(signers[0].pubkey, params.user);
+    assert_eq!(
+        ix.data,
+        obric_solana::instruction::Swap {
+            _is_x_to_y: true,
+            _input_amt: params.in_amount,
+            _min_output_amt: params.min_out_amount,
+        }
+        .data()
+    );
+}
+
+#[test]
+fn v3_swap_instruction() {
+    let amm = updated_amm(&v3_pool());
+    let (mint_x, mint_y) = amm.mints();
+
+    let params = swap_params(mint_x, 1_000_000_000);
+    let ix = amm.swap_instruction(&params).unwrap();
+    assert_eq!(ix.program_id, obric_v3_sdk::constants::PROGRAM_ID);
+    assert_eq!(ix.accounts.len(), SWAP_X_TO_Y_ACCOUNTS_LEN);
+    assert_eq!(
+        ix.data,
+        obric_solana_v3::instruction::SwapXToY {
+            _input_x: params.in_amount,
+            _min_output_amt: params.min_out_amount,
+        }
+        .data()
+    );
+
+    let params = swap_params(mint_y, 150_000_000);
+    let ix = amm.swap_instruction(&params).unwrap();
+    assert_eq!(ix.accounts.len(), SWAP_Y_TO_X_ACCOUNTS_LEN);
+    assert_eq!(
+        jupiter_amm_interface::Amm::get_accounts_len(&amm),
+        SWAP_Y_TO_X_ACCOUNTS_LEN
+    );
+    assert_eq!(
+        ix.data,
+        obric_solana_v3::instruction::SwapYToX {
+            _input_y: params.in_amount,
+            _min_output_amt: params.min_out_amount,
+        }
+        .data()
+    );
+    assert!(ix
+        .accounts
+        .iter()
+        .any(|meta| meta.pubkey == params.user && meta.is_signer));
+}
+
+#[test]
+fn exact_out_quotes_take_the_smallest_input() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let amm = updated_amm(&fixture);
+        let (mint_x, mint_y) = amm.mints();
+        // 150 USDC and 1 SOL out
+        for (input_mint, out_amount) in [(mint_x, 150_000_000), (mint_y, 1_000_000_000)] {
+            let quote = amm.quote_exact_out(&input_mint, out_amount).unwrap();
+            assert!(quote.out_amount >= out_amount);
+            let less = ObricPool::quote(&amm, &input_mint, quote.in_amount - 1).unwrap();
+            assert!(less.out_amount < out_amount);
+        }
+        assert!(amm.quote_exact_out(&mint_x, u64::MAX).is_err());
+    }
+}
+
+#[test]
+fn quotes_with_params_leave_the_pool_alone() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let amm = updated_amm(&fixture);
+        let (concentration, fee_millionth) = match amm.state() {
+            PoolState::V2(state) => (state.concentration, state.fee_millionth),
+            PoolState::V3(state) => (state.concentration, state.fee_millionth),
+        };
+        let (mint_x, _) = amm.mints();
+        // 10 SOL
+        let in_amount = 10_000_000_000;
+        let live = ObricPool::quote(&amm, &mint_x, in_amount).unwrap();
+        let quote = |concentration, fee_millionth| {
+            amm.quote_with_params(&mint_x, in_amount, concentration, fee_millionth)
+                .unwrap()
+        };
+
+        assert_eq!(quote(concentration, fee_millionth), live);
+        assert!(quote(concentration * 4, fee_millionth).out_amount > live.out_amount);
+        assert!(quote(concentration / 4, fee_millionth).out_amount < live.out_amount);
+        let dearer = quote(concentration, fee_millionth * 2);
+        assert!(dearer.out_amount < live.out_amount);
+        assert!(dearer.fee_amount > live.fee_amount);
+        assert_eq!(ObricPool::quote(&amm, &mint_x, in_amount).unwrap(), live);
+        assert!(amm
+            .quote_with_params(&mint_x, in_amount, 0, fee_millionth)
+            .is_err());
+    }
+}
+
+#[test]
+fn quotes_at_prices_leave_the_pool_alone() {
+    for fixture in [v2_pool(Oracle::Pyth), v2_pool(Oracle::Doves), v3_pool()] {
+        let amm = updated_amm(&fixture);
+        let (mint_x, mint_y) = amm.mints();
+        let one = 10u64.pow(amm.price_decimals() as u32);
+        // the fixtures' feeds, 150 USDC per SOL
+        let (price_x, price_y) = (150 * one, one);
+        // 10 SOL and 1500 USDC
+        for (input_mint, in_amount) in [(mint_x, 10_000_000_000), (mint_y, 1_500_000_000)] {
+            let live = ObricPool::quote(&amm, &input_mint, in_amount).unwrap();
+            let quote = |price_x| {
+                amm.quote_at_prices(&input_mint, in_amount, price_x, price_y)
+                    .unwrap()
+            };
+
+            assert_eq!(quote(price_x), live);
+            // a basis point up on X pays more Y for X and less X for Y
+            let up = quote(price_x + price_x / 10_000);
+            if input_mint == mint_x {
+                assert!(up.out_amount > live.out_amount);
+            } else {
+                assert!(up.out_amount < live.out_amount);
+            }
+            assert_eq!(
+                ObricPool::quote(&amm, &input_mint, in_amount).unwrap(),
+                live
+            );
+        }
+    }
+}
+
+#[test]
+fn simulated_swaps_move_the_pool() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let amm = updated_amm(&fixture);
+        let (mint_x, mint_y) = amm.mints();
+        // 10 SOL
+        let in_amount = 10_000_000_000;
+        let quote = ObricPool::quote(&amm, &mint_x, in_amount).unwrap();
+
+        let mut pool = amm.clone();
+        assert_eq!(pool.simulate_swap(&mint_x, in_amount).unwrap(), quote);
+        // the second swap fills lower on the curve, selling back fills higher
+        let again = ObricPool::quote(&pool, &mint_x, in_amount).unwrap();
+        assert!(again.out_amount < quote.out_amount);
+        let back = ObricPool::quote(&pool, &mint_y, quote.out_amount).unwrap();
+        let back_before = ObricPool::quote(&amm, &mint_y, quote.out_amount).unwrap();
+        assert!(back.out_amount > back_before.out_amount);
+        if pool.version() == PoolVersion::V2 {
+            let (before, after) = (amm.reserves().unwrap(), pool.reserves().unwrap());
+            assert_eq!(after.x, before.x + in_amount);
+            assert_eq!(after.y, before.y - quote.out_amount);
+        }
+
+        assert!(pool
+            .simulate_swap(&Pubkey::new_unique(), in_amount)
+            .is_err());
+        assert_eq!(ObricPool::quote(&pool, &mint_x, in_amount).unwrap(), again);
+        assert_eq!(ObricPool::quote(&amm, &mint_x, in_amount).unwrap(), quote);
+    }
+}
+
+#[test]
+fn v3_swaps_go_through_larix() {
+    const SOL: u64 = 1_000_000_000;
+    const USDC: u64 = 1_000_000;
+    let amm = updated_amm(&v3_pool());
+    let (mint_x, mint_y) = amm.mints();
+    let larix_x = |pool: &ObricAmm| match pool {
+        ObricAmm::V3(pool) => {
+            let reserve = pool.larix_reserve_x.as_ref().unwrap();
+            (
+                reserve.liquidity.available_amount,
+                pool.state.deposit_x,
+                pool.state.borrow_x,
+            )
+        }
+        ObricAmm::V2(_) => unreachable!(),
+    };
+    // 1,000 SOL deposited, 200 borrowed
+    let (available, deposit_x, borrow_x) = larix_x(&amm);
+    assert_eq!((deposit_x, borrow_x), (1_000 * SOL, 200 * SOL));
+
+    // X in repays the borrow first
+    let mut pool = amm.clone();
+    pool.simulate_swap(&mint_x, 100 * SOL).unwrap();
+    assert_eq!(
+        larix_x(&pool),
+        (available + 100 * SOL, 1_000 * SOL, 100 * SOL)
+    );
+    // and is deposited once it is repaid, a little is lost to the collateral rounding
+    pool.simulate_swap(&mint_x, 300 * SOL).unwrap();
+    let (_, deposit_x, borrow_x) = larix_x(&pool);
+    assert_eq!(borrow_x, 0);
+    assert!(
+        (1_200 * SOL - 2..=1_200 * SOL).contains(&deposit_x),
+        "{deposit_x}"
+    );
+
+    // X out past the deposit is borrowed
+    let mut pool = amm.clone();
+    let quote = pool.simulate_swap(&mint_y, 200_000 * USDC).unwrap();
+    let (available_after, deposit_x, borrow_x) = larix_x(&pool);
+    assert_eq!(deposit_x, 0);
+    assert!(borrow_x > 200 * SOL + quote.out_amount - 1_000 * SOL);
+    assert_eq!(
+        available - available_after,
+        1_000 * SOL + borrow_x - 200 * SOL
+    );
+
+    // unless the reserve has nothing left to lend
+    let mut pool = amm.clone();
+    if let ObricAmm::V3(pool) = &mut pool {
+        pool.larix_reserve_x
+            .as_mut()
+            .unwrap()
+            .liquidity
+            .available_amount = 1_000 * SOL;
+    }
+    assert!(pool.simulate_swap(&mint_y, 200_000 * USDC).is_err());
+    assert_eq!(larix_x(&pool), (1_000 * SOL, 1_000 * SOL, 200 * SOL));
+}
+
+#[test]
+fn v3_pools_past_the_u128_big_k_still_update() {
+    let mut fixture = v3_pool();
+    let mut pair = obric_solana_v3::state::SSTradingPair::try_deserialize(
+        &mut fixture.account.data.as_slice(),
+    )
+    .unwrap();
+    // a billion USDC at a concentration of 100,000 puts K past u128
+    pair.concentration = 100_000;
+    pair.deposit_y = 1_000_000_000_000_000;
+    fixture.account.data.clear();
+    pair.try_serialize(&mut fixture.account.data).unwrap();
+    fixture
+        .accounts
+        .insert(fixture.key, fixture.account.clone());
+
+    let amm = updated_amm(&fixture);
+    let PoolState::V3(mut state) = amm.state() else {
+        unreachable!()
+    };
+    assert_eq!(state.big_k, u128::MAX);
+    assert!(state.update_target_y(state.target_y).is_err());
+
+    let (mint_x, _) = amm.mints();
+    let quote = ObricPool::quote(&amm, &mint_x, 1_000_000_000).unwrap();
+    // 1 SOL at 150 USDC, the pool is deep enough to barely move
+    assert!(
+        (149_900_000..150_000_000).contains(&quote.out_amount),
+        "{quote:?}"
+    );
+}
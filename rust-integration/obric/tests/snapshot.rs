@@ -0,0 +1,101 @@
+//! Snapshots of the bench fixtures survive a JSON and a bincode round trip and load into a pool
+//! that quotes like one updated from the accounts directly.
+
+use jupiter_amm_interface::KeyedAccount;
+use obric::snapshot::{self, Snapshot, SNAPSHOT_VERSION};
+use obric::{ObricAmm, ObricPool};
+use obric_bench::{v2_pool, v3_pool, Fixture, Oracle, NOW};
+use solana_sdk::clock::Clock;
+
+const SLOT: u64 = 250_000_000;
+
+fn snapshot(fixture: &Fixture) -> Snapshot {
+    let clock = Clock {
+        slot: SLOT,
+        unix_timestamp: NOW,
+        ..Clock::default()
+    };
+    Snapshot::new(fixture.key, SLOT, clock, &fixture.accounts)
+}
+
+#[test]
+fn snapshots_round_trip_and_quote() {
+    for fixture in [v2_pool(Oracle::Pyth), v2_pool(Oracle::Doves), v3_pool()] {
+        let snapshot = snapshot(&fixture);
+        let from_json = Snapshot::from_json(&snapshot.to_json().unwrap()).unwrap();
+        let bincode = snapshot.to_bincode().unwrap();
+        let from_bincode = Snapshot::from_bincode(&bincode).unwrap();
+        assert_eq!(from_json, snapshot);
+        assert_eq!(from_bincode, snapshot);
+        assert_eq!(from_bincode.account_map(), fixture.accounts);
+        assert!(bincode.len() < snapshot.to_json().unwrap().len());
+
+        let mut live = ObricAmm::from_keyed_account(
+            &KeyedAccount {
+                key: fixture.key,
+                account: fixture.account.clone(),
+                params: None,
+            },
+            &snapshot.amm_context(),
+        )
+        .unwrap();
+        ObricPool::update(&mut live, &fixture.accounts).unwrap();
+        let loaded = from_bincode.load().unwrap();
+
+        let (mint_x, mint_y) = live.mints();
+        for (input_mint, in_amount) in [(mint_x, 1_000_000_000), (mint_y, 150_000_000)] {
+            assert_eq!(
+                ObricPool::quote(&loaded, &input_mint, in_amount).unwrap(),
+                ObricPool::quote(&live, &input_mint, in_amount).unwrap()
+            );
+        }
+    }
+}
+
+#[test]
+fn typed_loaders_check_the_version_of_the_pair() {
+    let v2 = snapshot(&v2_pool(Oracle::Pyth));
+    let v3 = snapshot(&v3_pool());
+
+    assert_eq!(v2.load_v2().unwrap().key, v2.key);
+    assert_eq!(v3.load_v3().unwrap().key, v3.key);
+    assert!(v2.load_v3().is_err());
+    assert!(v3.load_v2().is_err());
+}
+
+#[test]
+fn snapshots_hold_every_dependency() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let amm = snapshot(&fixture).load().unwrap();
+        let dependencies = snapshot::dependencies(&amm);
+        assert!(dependencies.contains(&fixture.key));
+        for key in amm.accounts_to_update() {
+            assert!(dependencies.contains(&key), "{key}");
+        }
+        if let ObricAmm::V3(amm) = &amm {
+            assert!(dependencies.contains(&amm.obligation));
+        }
+    }
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let mut snapshot = snapshot(&v2_pool(Oracle::Pyth));
+    snapshot.version = SNAPSHOT_VERSION + 1;
+    assert!(Snapshot::from_json(&snapshot.to_json().unwrap()).is_err());
+    assert!(Snapshot::from_bincode(&snapshot.to_bincode().unwrap()).is_err());
+}
+
+#[test]
+fn snapshot_without_the_pair_does_not_load() {
+    let fixture = v2_pool(Oracle::Pyth);
+    let mut accounts = fixture.accounts.clone();
+    accounts.remove(&fixture.key);
+    let clock = Clock {
+        unix_timestamp: NOW,
+        ..Clock::default()
+    };
+    assert!(Snapshot::new(fixture.key, SLOT, clock, &accounts)
+        .load()
+        .is_err());
+}
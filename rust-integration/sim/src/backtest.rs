@@ -0,0 +1,264 @@
+//! Replays a trade tape through a pool while its oracle prices follow a price series, and reports
+//! what the LPs would have made with the chosen `concentration` and `fee_millionth`.
+//!
+//! Both inputs are read from CSV files with a header row, or from JSON arrays of the same records
+//! when the file ends in `.json`:
+//!
+//! - prices: `timestamp,price_x,price_y`, prices in USD or any common quote
+//! - trades: `timestamp,side,amount`, `side` is `x_to_y` or `y_to_x` and `amount` the input in
+//!   its smallest unit
+//!
+//! A price applies from its timestamp on, before the trades at the same timestamp.
+
+use crate::pool::{Side, SimPool};
+use anyhow::{anyhow, bail, Context, Result};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fs, path::Path};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceTick {
+    pub timestamp: i64,
+    pub price_x: Decimal,
+    pub price_y: Decimal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trade {
+    pub timestamp: i64,
+    pub side: Side,
+    pub amount: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Params {
+    pub concentration: u64,
+    pub fee_millionth: u64,
+}
+
+impl Params {
+    /// What the pool runs with today.
+    pub fn of(pool: &SimPool) -> Self {
+        Self {
+            concentration: pool.concentration(),
+            fee_millionth: pool.fee_millionth(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Rejection {
+    pub trade: Trade,
+    pub reason: String,
+}
+
+/// The pool right after a price tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Sample {
+    pub timestamp: i64,
+    pub x: i128,
+    pub y: i128,
+    pub value_y: i128,
+    pub inventory_drift_x: i128,
+}
+
+/// Amounts are in the smallest unit of their token, values in the smallest unit of Y at the last
+/// price of the series.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BacktestReport {
+    pub params: Params,
+    pub trades: usize,
+    pub filled: usize,
+    /// X and Y the pool took in.
+    pub volume_x: u128,
+    pub volume_y: u128,
+    /// Fees left in the pool for the LPs.
+    pub lp_fees_x: u128,
+    pub lp_fees_y: u128,
+    pub lp_fees_value_y: i128,
+    pub protocol_fees_x: u128,
+    pub protocol_fees_y: u128,
+    /// What the pool held at the first price, valued at that price.
+    pub initial_value_y: i128,
+    pub final_value_y: i128,
+    /// What the initial holdings would be worth had the pool not traded.
+    pub hodl_value_y: i128,
+    /// `final_value_y - initial_value_y`
+    pub pnl_y: i128,
+    /// `final_value_y - hodl_value_y`, fees included.
+    pub pnl_vs_hodl_y: i128,
+    pub final_inventory_drift_x: i128,
+    /// Largest drift away from the target, either way.
+    pub max_inventory_drift_x: i128,
+    pub rejected: Vec<Rejection>,
+    pub samples: Vec<Sample>,
+}
+
+pub fn read_prices(path: &Path) -> Result<Vec<PriceTick>> {
+    read_records(path)
+}
+
+pub fn read_trades(path: &Path) -> Result<Vec<Trade>> {
+    read_records(path)
+}
+
+fn read_records<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let context = || format!("reading {}", path.display());
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        let json = fs::read_to_string(path).with_context(context)?;
+        return serde_json::from_str(&json).with_context(context);
+    }
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(context)?
+        .deserialize()
+        .collect::<Result<_, _>>()
+        .with_context(context)
+}
+
+/// `price` in the smallest unit of `decimals` decimals, truncated like the programs scale feeds.
+pub fn normalize_price(price: Decimal, decimals: u8) -> Result<u64> {
+    let scale = Decimal::from(10u64.pow(decimals as u32));
+    price
+        .checked_mul(scale)
+        .and_then(|price| price.trunc().to_u64())
+        .filter(|price| *price > 0)
+        .ok_or_else(|| anyhow!("price {price} does not fit {decimals} decimals"))
+}
+
+enum Event<'a> {
+    Price(&'a PriceTick),
+    Trade(&'a Trade),
+}
+
+pub fn run(
+    pool: &SimPool,
+    params: Params,
+    prices: &[PriceTick],
+    trades: &[Trade],
+) -> Result<BacktestReport> {
+    if prices.is_empty() {
+        bail!("the price series is empty");
+    }
+    let mut pool = pool.clone();
+    pool.set_concentration(params.concentration)?;
+    pool.set_fee_millionth(params.fee_millionth);
+
+    // stable sorts keep the input order of events at the same timestamp, prices first
+    let mut events: Vec<_> = prices
+        .iter()
+        .map(Event::Price)
+        .chain(trades.iter().map(Event::Trade))
+        .collect();
+    events.sort_by_key(|event| match event {
+        Event::Price(tick) => (tick.timestamp, 0),
+        Event::Trade(trade) => (trade.timestamp, 1),
+    });
+
+    let initial = pool.holdings();
+    let mut report = BacktestReport {
+        params,
+        trades: trades.len(),
+        filled: 0,
+        volume_x: 0,
+        volume_y: 0,
+        lp_fees_x: 0,
+        lp_fees_y: 0,
+        lp_fees_value_y: 0,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        initial_value_y: 0,
+        final_value_y: 0,
+        hodl_value_y: 0,
+        pnl_y: 0,
+        pnl_vs_hodl_y: 0,
+        final_inventory_drift_x: 0,
+        max_inventory_drift_x: 0,
+        rejected: vec![],
+        samples: vec![],
+    };
+    let mut last_price_at = None;
+
+    for event in events {
+        match event {
+            Event::Price(tick) => {
+                let decimals = pool.price_decimals();
+                pool.update_prices(
+                    normalize_price(tick.price_x, decimals)?,
+                    normalize_price(tick.price_y, decimals)?,
+                )
+                .with_context(|| format!("applying the prices at {}", tick.timestamp))?;
+                let (x, y) = pool.holdings();
+                let value_y = pool.value_in_y(x, y)?;
+                if last_price_at.is_none() {
+                    report.initial_value_y = value_y;
+                }
+                last_price_at = Some(tick.timestamp);
+                report.samples.push(Sample {
+                    timestamp: tick.timestamp,
+                    x,
+                    y,
+                    value_y,
+                    inventory_drift_x: pool.inventory_drift_x(),
+                });
+            }
+            Event::Trade(trade) => {
+                let reject = |reason: String| Rejection {
+                    trade: *trade,
+                    reason,
+                };
+                let Some(price_at) = last_price_at else {
+                    report.rejected.push(reject("no price yet".to_string()));
+                    continue;
+                };
+                if let Some(max_age) = pool.max_feed_age() {
+                    if trade.timestamp - price_at > max_age {
+                        report.rejected.push(reject(format!(
+                            "price is {}s old, the pool accepts {max_age}s",
+                            trade.timestamp - price_at
+                        )));
+                        continue;
+                    }
+                }
+                match pool.swap(trade.side, trade.amount) {
+                    Ok(fill) => {
+                        report.filled += 1;
+                        let (volume, lp_fees, protocol_fees) = match fill.side {
+                            Side::XToY => (
+                                &mut report.volume_x,
+                                &mut report.lp_fees_y,
+                                &mut report.protocol_fees_y,
+                            ),
+                            Side::YToX => (
+                                &mut report.volume_y,
+                                &mut report.lp_fees_x,
+                                &mut report.protocol_fees_x,
+                            ),
+                        };
+                        *volume += fill.in_amount as u128;
+                        *lp_fees += fill.lp_fee as u128;
+                        *protocol_fees += fill.protocol_fee as u128;
+                    }
+                    Err(err) => report.rejected.push(reject(format!("{err:#}"))),
+                }
+            }
+        }
+        report.max_inventory_drift_x = report
+            .max_inventory_drift_x
+            .max(pool.inventory_drift_x().abs());
+    }
+
+    let (x, y) = pool.holdings();
+    report.final_value_y = pool.value_in_y(x, y)?;
+    report.hodl_value_y = pool.value_in_y(initial.0, initial.1)?;
+    report.lp_fees_value_y = pool.value_in_y(report.lp_fees_x as i128, report.lp_fees_y as i128)?;
+    report.pnl_y = report.final_value_y - report.initial_value_y;
+    report.pnl_vs_hodl_y = report.final_value_y - report.hodl_value_y;
+    report.final_inventory_drift_x = pool.inventory_drift_x();
+    Ok(report)
+}
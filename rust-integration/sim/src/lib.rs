@@ -0,0 +1,10 @@
+//! Offline analysis of Obric pools: `pool` replays swaps through the curve math of either
+//! program, `backtest` runs a price series and a trade tape through it, and `stress` drives a
+//! pool's own `update` through oracle shocks written into its feeds by `oracle`. `optimize`
+//! searches concentration and fee over synthetic flow shaped like the pool's history.
+
+pub mod backtest;
+pub mod optimize;
+pub mod oracle;
+pub mod pool;
+pub mod stress;
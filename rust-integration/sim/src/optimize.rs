@@ -0,0 +1,288 @@
+//! Searches `concentration` and `fee_millionth` by replaying synthetic flow through the
+//! backtester. Price paths are drawn from a volatility and trades from a size distribution, both
+//! usually estimated from history with `volatility` and `FlowSizes::from_trades`. Every point of
+//! the grid runs on the same paths, so points differ by their parameters only.
+
+use crate::backtest::{self, Params, PriceTick, Trade};
+use crate::pool::{Side, SimPool};
+use anyhow::{anyhow, bail, Result};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Realized volatility of the price of X in Y, per square root of a second.
+pub fn volatility(prices: &[PriceTick]) -> Result<f64> {
+    let ratio = |tick: &PriceTick| {
+        tick.price_x
+            .checked_div(tick.price_y)
+            .and_then(|ratio| ratio.to_f64())
+            .filter(|ratio| *ratio > 0.0)
+            .ok_or_else(|| anyhow!("no price of X in Y at {}", tick.timestamp))
+    };
+    let (mut variance, mut elapsed) = (0.0, 0);
+    for window in prices.windows(2) {
+        let seconds = window[1].timestamp - window[0].timestamp;
+        if seconds <= 0 {
+            bail!("prices are not in time order at {}", window[1].timestamp);
+        }
+        let log_return = (ratio(&window[1])? / ratio(&window[0])?).ln();
+        variance += log_return * log_return;
+        elapsed += seconds;
+    }
+    if elapsed == 0 {
+        bail!("the volatility takes at least two prices");
+    }
+    Ok((variance / elapsed as f64).sqrt())
+}
+
+/// Trade sizes seen on each side, drawn with replacement so the mix of sides follows history.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlowSizes {
+    pub x_to_y: Vec<u64>,
+    pub y_to_x: Vec<u64>,
+}
+
+impl FlowSizes {
+    pub fn from_trades(trades: &[Trade]) -> Self {
+        let mut sizes = Self::default();
+        for trade in trades {
+            match trade.side {
+                Side::XToY => sizes.x_to_y.push(trade.amount),
+                Side::YToX => sizes.y_to_x.push(trade.amount),
+            }
+        }
+        sizes
+    }
+
+    fn draw(&self, rng: &mut Rng) -> (Side, u64) {
+        let index = rng.below(self.x_to_y.len() + self.y_to_x.len());
+        match self.x_to_y.get(index) {
+            Some(amount) => (Side::XToY, *amount),
+            None => (Side::YToX, self.y_to_x[index - self.x_to_y.len()]),
+        }
+    }
+}
+
+/// How the synthetic paths are drawn: one price and one trade every `interval` seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Market {
+    /// Prices every path starts from.
+    pub price_x: Decimal,
+    pub price_y: Decimal,
+    /// Of the price of X in Y, per square root of a second. Y holds its price.
+    pub volatility: f64,
+    pub interval: i64,
+    pub steps: usize,
+    pub paths: usize,
+    pub seed: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Grid {
+    pub concentrations: Vec<u64>,
+    pub fees_millionth: Vec<u64>,
+}
+
+impl Grid {
+    /// A quarter, half, twice and four times `params` besides `params` itself.
+    pub fn around(params: Params) -> Self {
+        let spread = |value: u64| {
+            let mut values: Vec<_> = [value / 4, value / 2, value, value * 2, value * 4]
+                .into_iter()
+                .filter(|value| *value > 0)
+                .collect();
+            values.dedup();
+            values
+        };
+        Self {
+            concentrations: spread(params.concentration),
+            fees_millionth: spread(params.fee_millionth.max(1)),
+        }
+    }
+}
+
+/// One grid point, averaged over the paths. Values are in the smallest unit of Y at the last
+/// price of each path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Point {
+    pub params: Params,
+    /// Input of X and Y the pool takes at the starting prices before its average fill is
+    /// `depth_bps` below spot. `None` when the fee alone is more.
+    pub depth_x: Option<u64>,
+    pub depth_y: Option<u64>,
+    pub mean_pnl_vs_hodl_y: i128,
+    pub worst_pnl_vs_hodl_y: i128,
+    pub mean_lp_fees_value_y: i128,
+    /// Trades filled and drawn, over every path.
+    pub filled: usize,
+    pub trades: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Optimization {
+    pub current: Params,
+    pub depth_bps: u64,
+    pub paths: usize,
+    pub points: Vec<Point>,
+    /// The point with the best mean PnL against holding among those at least as deep on both
+    /// sides as `current`.
+    pub recommended: Option<Params>,
+}
+
+/// Runs every point of `grid`, and the pool's current parameters, on `market.paths` paths.
+pub fn optimize(
+    pool: &SimPool,
+    market: &Market,
+    flow: &FlowSizes,
+    grid: &Grid,
+    depth_bps: u64,
+) -> Result<Optimization> {
+    if flow.x_to_y.is_empty() && flow.y_to_x.is_empty() {
+        bail!("the flow has no trade sizes to draw from");
+    }
+    let paths = draw_paths(market, flow)?;
+    let current = Params::of(pool);
+    let mut candidates = vec![current];
+    for &concentration in &grid.concentrations {
+        for &fee_millionth in &grid.fees_millionth {
+            let params = Params {
+                concentration,
+                fee_millionth,
+            };
+            if !candidates.contains(&params) {
+                candidates.push(params);
+            }
+        }
+    }
+
+    let mut points = vec![];
+    for params in candidates {
+        points.push(evaluate(pool, params, market, &paths, depth_bps)?);
+    }
+    let current_point = points[0].clone();
+    let deep_enough = |point: &&Point| {
+        point.depth_x >= current_point.depth_x && point.depth_y >= current_point.depth_y
+    };
+    let recommended = points
+        .iter()
+        .filter(deep_enough)
+        .max_by_key(|point| point.mean_pnl_vs_hodl_y)
+        .map(|point| point.params);
+    points.sort_by_key(|point| (point.params.concentration, point.params.fee_millionth));
+    Ok(Optimization {
+        current,
+        depth_bps,
+        paths: market.paths,
+        points,
+        recommended,
+    })
+}
+
+fn evaluate(
+    pool: &SimPool,
+    params: Params,
+    market: &Market,
+    paths: &[(Vec<PriceTick>, Vec<Trade>)],
+    depth_bps: u64,
+) -> Result<Point> {
+    let mut at_start = pool.clone();
+    at_start.set_concentration(params.concentration)?;
+    at_start.set_fee_millionth(params.fee_millionth);
+    let decimals = at_start.price_decimals();
+    at_start.update_prices(
+        backtest::normalize_price(market.price_x, decimals)?,
+        backtest::normalize_price(market.price_y, decimals)?,
+    )?;
+
+    let mut point = Point {
+        params,
+        depth_x: at_start.input_for_price_impact(Side::XToY, depth_bps)?,
+        depth_y: at_start.input_for_price_impact(Side::YToX, depth_bps)?,
+        mean_pnl_vs_hodl_y: 0,
+        worst_pnl_vs_hodl_y: i128::MAX,
+        mean_lp_fees_value_y: 0,
+        filled: 0,
+        trades: 0,
+    };
+    for (prices, trades) in paths {
+        let report = backtest::run(pool, params, prices, trades)?;
+        point.mean_pnl_vs_hodl_y += report.pnl_vs_hodl_y;
+        point.worst_pnl_vs_hodl_y = point.worst_pnl_vs_hodl_y.min(report.pnl_vs_hodl_y);
+        point.mean_lp_fees_value_y += report.lp_fees_value_y;
+        point.filled += report.filled;
+        point.trades += report.trades;
+    }
+    if !paths.is_empty() {
+        point.mean_pnl_vs_hodl_y /= paths.len() as i128;
+        point.mean_lp_fees_value_y /= paths.len() as i128;
+    } else {
+        point.worst_pnl_vs_hodl_y = 0;
+    }
+    Ok(point)
+}
+
+/// Geometric Brownian paths of X in Y with a trade drawn after every price.
+fn draw_paths(market: &Market, flow: &FlowSizes) -> Result<Vec<(Vec<PriceTick>, Vec<Trade>)>> {
+    let mut rng = Rng(market.seed);
+    let step_volatility = market.volatility * (market.interval as f64).sqrt();
+    let drift = -step_volatility * step_volatility / 2.0;
+    let start = market
+        .price_x
+        .to_f64()
+        .ok_or_else(|| anyhow!("price {} is not a float", market.price_x))?;
+
+    let mut paths = vec![];
+    for _ in 0..market.paths {
+        let (mut prices, mut trades) = (vec![], vec![]);
+        let mut price_x = start;
+        for step in 0..market.steps {
+            if step > 0 {
+                price_x *= (drift + step_volatility * rng.normal()).exp();
+            }
+            let timestamp = market.interval * step as i64;
+            prices.push(PriceTick {
+                timestamp,
+                price_x: Decimal::from_f64(price_x)
+                    .ok_or_else(|| anyhow!("price {price_x} is not a decimal"))?
+                    .round_dp(9),
+                price_y: market.price_y,
+            });
+            let (side, amount) = flow.draw(&mut rng);
+            trades.push(Trade {
+                timestamp,
+                side,
+                amount,
+            });
+        }
+        paths.push((prices, trades));
+    }
+    Ok(paths)
+}
+
+/// SplitMix64, so a seed replays the same paths everywhere.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Standard normal, by Box-Muller.
+    fn normal(&mut self) -> f64 {
+        let radius = (-2.0 * (1.0 - self.unit()).ln()).sqrt();
+        radius * (2.0 * std::f64::consts::PI * self.unit()).cos()
+    }
+}
@@ -0,0 +1,106 @@
+//! Rewrites Pyth and Doves price accounts in place, so a pool's own `update` can be driven with
+//! prices and publish times that never happened. The exponent and every other field of the feed
+//! are kept.
+
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+use anyhow::{anyhow, bail, Result};
+use obric::snapshot::Snapshot;
+use obric::{ObricAmm, ObricPool, PoolState};
+use pyth_sdk_solana::state::{PriceStatus, SolanaPriceAccount};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::mem::size_of;
+
+/// `(x_price_feed_id, y_price_feed_id)` of the pair.
+pub fn feed_ids(amm: &ObricAmm) -> (Pubkey, Pubkey) {
+    match amm.state() {
+        PoolState::V2(pair) => (pair.x_price_feed_id, pair.y_price_feed_id),
+        PoolState::V3(pair) => (pair.x_price_feed_id, pair.y_price_feed_id),
+    }
+}
+
+/// `(price_x, price_y)` the feeds of the pool in `snapshot` publish.
+pub fn snapshot_prices(snapshot: &Snapshot) -> Result<(Decimal, Decimal)> {
+    let accounts = snapshot.account_map();
+    let (feed_x, feed_y) = feed_ids(&snapshot.load()?);
+    let price = |feed: &Pubkey| -> Result<Decimal> {
+        let account = accounts
+            .get(feed)
+            .ok_or_else(|| anyhow!("snapshot does not hold the feed {feed}"))?;
+        Ok(read_price(account)?.0)
+    };
+    Ok((price(&feed_x)?, price(&feed_y)?))
+}
+
+/// Price the feed publishes and its publish time. Anything not owned by the Doves program is read
+/// as a Pyth price account.
+pub fn read_price(account: &Account) -> Result<(Decimal, i64)> {
+    if account.owner == doves_cpi::ID {
+        let feed = doves_cpi::PriceFeed::try_deserialize(&mut account.data.as_slice())?;
+        let price =
+            Decimal::try_from_i128_with_scale(feed.price as i128, scale(feed.expo as i32)?)?;
+        return Ok((price, feed.timestamp));
+    }
+    let feed = pyth_feed(account)?;
+    let price = Decimal::try_from_i128_with_scale(feed.agg.price as i128, scale(feed.expo)?)?;
+    Ok((price, feed.timestamp))
+}
+
+/// Publishes `price` at `publish_time`, truncated to the exponent of the feed.
+pub fn write_price(account: &mut Account, price: Decimal, publish_time: i64) -> Result<()> {
+    if account.owner == doves_cpi::ID {
+        let mut feed = doves_cpi::PriceFeed::try_deserialize(&mut account.data.as_slice())?;
+        feed.price = to_units(price, feed.expo as i32)?
+            .to_u64()
+            .ok_or_else(|| anyhow!("price {price} does not fit a Doves feed"))?;
+        feed.timestamp = publish_time;
+        let mut data = vec![];
+        feed.try_serialize(&mut data)?;
+        return overwrite(account, &data);
+    }
+    let mut feed = pyth_feed(account)?;
+    let units = to_units(price, feed.expo)?
+        .to_i64()
+        .ok_or_else(|| anyhow!("price {price} does not fit a Pyth feed"))?;
+    feed.agg.price = units;
+    feed.agg.status = PriceStatus::Trading;
+    feed.timestamp = publish_time;
+    feed.prev_price = units;
+    feed.prev_timestamp = publish_time;
+    overwrite(account, bytemuck::bytes_of(&feed))
+}
+
+fn pyth_feed(account: &Account) -> Result<SolanaPriceAccount> {
+    // account data carries no alignment, so the feed is copied out rather than cast in place
+    let data = account
+        .data
+        .get(..size_of::<SolanaPriceAccount>())
+        .ok_or_else(|| anyhow!("account is too short for a Pyth price"))?;
+    Ok(bytemuck::pod_read_unaligned(data))
+}
+
+/// Writes `data` over the start of the account, leaving its size as it is.
+pub(crate) fn overwrite(account: &mut Account, data: &[u8]) -> Result<()> {
+    match account.data.get_mut(..data.len()) {
+        Some(prefix) => {
+            prefix.copy_from_slice(data);
+            Ok(())
+        }
+        None => bail!("{} bytes do not fit the account", data.len()),
+    }
+}
+
+fn scale(expo: i32) -> Result<u32> {
+    u32::try_from(-expo)
+        .map_err(|_| anyhow!("feeds with a positive exponent {expo} are not supported"))
+}
+
+fn to_units(price: Decimal, expo: i32) -> Result<Decimal> {
+    let units = Decimal::from(10u64.pow(scale(expo)?));
+    price
+        .checked_mul(units)
+        .map(|units| units.trunc())
+        .filter(|units| units.is_sign_positive() && !units.is_zero())
+        .ok_or_else(|| anyhow!("price {price} does not fit exponent {expo}"))
+}
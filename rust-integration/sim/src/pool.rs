@@ -0,0 +1,278 @@
+//! A trading pair and the balances a swap moves, without the accounts around them, so flow can be
+//! replayed through the curve math at any price.
+
+use anyhow::{anyhow, bail, Result};
+use obric::ObricAmm;
+use obric_solana::state::{SSTradingPair as V2Pair, SwapDirection as V2Direction};
+use obric_solana_v3::state::{SSTradingPair as V3Pair, SwapDirection as V3Direction};
+use serde::{Deserialize, Serialize};
+
+/// Decimals the v3 program normalizes feed prices to.
+pub const V3_PRICE_DECIMALS: u8 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    XToY,
+    YToX,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Fill {
+    pub side: Side,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    /// Part of the fee that stays in the pool, in the output token.
+    pub lp_fee: u64,
+    /// Part of the fee sent to the protocol fee account, always 0 for v2.
+    pub protocol_fee: u64,
+}
+
+#[derive(Clone, Debug)]
+pub enum SimPool {
+    V2 {
+        state: V2Pair,
+        current_x: u64,
+        current_y: u64,
+        x_decimals: u8,
+        y_decimals: u8,
+    },
+    V3 {
+        state: V3Pair,
+    },
+}
+
+impl SimPool {
+    /// The pair and reserves of an updated pool.
+    pub fn from_amm(amm: &ObricAmm) -> Self {
+        match amm {
+            ObricAmm::V2(amm) => Self::V2 {
+                state: amm.state.clone(),
+                current_x: amm.current_x,
+                current_y: amm.current_y,
+                x_decimals: amm.x_decimals,
+                y_decimals: amm.y_decimals,
+            },
+            ObricAmm::V3(amm) => Self::V3 {
+                state: amm.state.clone(),
+            },
+        }
+    }
+
+    pub fn concentration(&self) -> u64 {
+        match self {
+            Self::V2 { state, .. } => state.concentration,
+            Self::V3 { state } => state.concentration,
+        }
+    }
+
+    pub fn fee_millionth(&self) -> u64 {
+        match self {
+            Self::V2 { state, .. } => state.fee_millionth,
+            Self::V3 { state } => state.fee_millionth,
+        }
+    }
+
+    /// v2 rescales `big_k` and keeps the price the pair was set up at, v3 re-derives it at the
+    /// current target.
+    pub fn set_concentration(&mut self, concentration: u64) -> Result<()> {
+        match self {
+            Self::V2 { state, .. } => state.set_concentration(concentration)?,
+            Self::V3 { state } => state.set_concentration(concentration)?,
+        }
+        Ok(())
+    }
+
+    pub fn set_fee_millionth(&mut self, fee_millionth: u64) {
+        match self {
+            Self::V2 { state, .. } => state.fee_millionth = fee_millionth,
+            Self::V3 { state } => state.fee_millionth = fee_millionth,
+        }
+    }
+
+    /// `(x_decimals, y_decimals)` of the mints.
+    pub fn decimals(&self) -> (u8, u8) {
+        match self {
+            Self::V2 {
+                x_decimals,
+                y_decimals,
+                ..
+            } => (*x_decimals, *y_decimals),
+            Self::V3 { state } => (state.decimals_x, state.decimals_y),
+        }
+    }
+
+    /// Decimals `update_prices` expects its prices in.
+    pub fn price_decimals(&self) -> u8 {
+        match self {
+            Self::V2 { state, .. } => state.price_decimals,
+            Self::V3 { .. } => V3_PRICE_DECIMALS,
+        }
+    }
+
+    /// Oldest price the program swaps at, in seconds. v3 does not check.
+    pub fn max_feed_age(&self) -> Option<i64> {
+        match self {
+            Self::V2 { state, .. } => Some(state.feed_max_age_x.min(state.feed_max_age_y) as i64),
+            Self::V3 { .. } => None,
+        }
+    }
+
+    /// Moves the pair to new oracle prices, normalized to `price_decimals`. v3 re-targets on
+    /// them as its `update` does, v2 re-targets Y within every quote.
+    pub fn update_prices(&mut self, price_x: u64, price_y: u64) -> Result<()> {
+        match self {
+            Self::V2 {
+                state,
+                x_decimals,
+                y_decimals,
+                ..
+            } => state.update_price(price_x, price_y, *x_decimals, *y_decimals)?,
+            Self::V3 { state } => {
+                state.update_price(price_x, price_y)?;
+                let target_y = state.compute_target_y()?;
+                state.retarget(target_y)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Swaps `in_amount` and moves the balances as the program would: v2 reserves keep the fee,
+    /// v3 repays the X borrow before depositing X and borrows X once its deposit runs out, and
+    /// sends the protocol fee out. Larix interest is left out. Fails on what the program would
+    /// reject, including swaps it pays nothing for.
+    pub fn swap(&mut self, side: Side, in_amount: u64) -> Result<Fill> {
+        let fill = match self {
+            Self::V2 {
+                state,
+                current_x,
+                current_y,
+                ..
+            } => {
+                let (out_amount, fee) = match side {
+                    Side::XToY => state.quote_x_to_y(in_amount, *current_x, *current_y)?,
+                    Side::YToX => state.quote_y_to_x(in_amount, *current_x, *current_y)?,
+                };
+                if out_amount == 0 {
+                    bail!("the pool pays out nothing, locked or out of reserves");
+                }
+                let (current_in, current_out) = match side {
+                    Side::XToY => (current_x, current_y),
+                    Side::YToX => (current_y, current_x),
+                };
+                *current_in = current_in
+                    .checked_add(in_amount)
+                    .ok_or_else(|| anyhow!("reserve overflows"))?;
+                *current_out -= out_amount;
+                Fill {
+                    side,
+                    in_amount,
+                    out_amount,
+                    lp_fee: fee,
+                    protocol_fee: 0,
+                }
+            }
+            Self::V3 { state } => {
+                let (out_amount, protocol_fee, lp_fee) = match side {
+                    Side::XToY => state.quote_x_to_y(in_amount)?,
+                    Side::YToX => state.quote_y_to_x(in_amount)?,
+                };
+                if out_amount == 0 {
+                    bail!("the pool pays out nothing");
+                }
+                let paid_out = out_amount + protocol_fee;
+                match side {
+                    Side::XToY => {
+                        let repaid = in_amount.min(state.borrow_x);
+                        state.borrow_x -= repaid;
+                        state.deposit_x += in_amount - repaid;
+                        state.deposit_y -= paid_out;
+                    }
+                    Side::YToX => {
+                        state.deposit_y += in_amount;
+                        let withdrawn = paid_out.min(state.deposit_x);
+                        state.deposit_x -= withdrawn;
+                        state.borrow_x += paid_out - withdrawn;
+                    }
+                }
+                Fill {
+                    side,
+                    in_amount,
+                    out_amount,
+                    lp_fee,
+                    protocol_fee,
+                }
+            }
+        };
+        Ok(fill)
+    }
+
+    /// Largest input of `side` whose average fill is at most `impact_bps` below spot, fees
+    /// included. `None` when the fee alone is more.
+    pub fn input_for_price_impact(&self, side: Side, impact_bps: u64) -> Result<Option<u64>> {
+        Ok(match self {
+            Self::V2 {
+                state,
+                current_x,
+                current_y,
+                ..
+            } => state.input_for_price_impact(
+                &state.get_pool_values_for_quoting(*current_x, *current_y)?,
+                impact_bps,
+                match side {
+                    Side::XToY => V2Direction::XToY,
+                    Side::YToX => V2Direction::YToX,
+                },
+            )?,
+            Self::V3 { state } => state.input_for_price_impact(
+                &state.get_pool_values_for_quoting()?,
+                impact_bps,
+                match side {
+                    Side::XToY => V3Direction::XToY,
+                    Side::YToX => V3Direction::YToX,
+                },
+            )?,
+        })
+    }
+
+    /// Net amounts of X and Y the pool owns, v3 borrows count against them.
+    pub fn holdings(&self) -> (i128, i128) {
+        match self {
+            Self::V2 {
+                current_x,
+                current_y,
+                ..
+            } => (*current_x as i128, *current_y as i128),
+            Self::V3 { state } => (
+                state.deposit_x as i128 - state.borrow_x as i128,
+                state.deposit_y as i128 - state.borrow_y as i128,
+            ),
+        }
+    }
+
+    /// X held beyond the target, what the curve leans on to sell. v2 targets `target_x`, v3 holds
+    /// no X at its target.
+    pub fn inventory_drift_x(&self) -> i128 {
+        match self {
+            Self::V2 {
+                state, current_x, ..
+            } => *current_x as i128 - state.target_x as i128,
+            Self::V3 { .. } => self.holdings().0,
+        }
+    }
+
+    /// `x` and `y` in Y at the last prices, rounded down.
+    pub fn value_in_y(&self, x: i128, y: i128) -> Result<i128> {
+        let (mult_x, mult_y) = match self {
+            Self::V2 { state, .. } => (state.mult_x, state.mult_y),
+            Self::V3 { state } => (state.mult_x, state.mult_y),
+        };
+        if mult_y == 0 {
+            bail!("the pool has no prices yet");
+        }
+        x.checked_mul(mult_x as i128)
+            .map(|value| value.div_euclid(mult_y as i128))
+            .and_then(|value| value.checked_add(y))
+            .ok_or_else(|| anyhow!("value overflows"))
+    }
+}
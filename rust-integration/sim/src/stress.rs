@@ -0,0 +1,458 @@
+//! Synthetic oracle shocks run against a pool loaded from a snapshot.
+//!
+//! Every step publishes the scenario's feed prices into the pool's price accounts, moves the
+//! clock and calls the pool's own `update`. Then an arbitrageur who can trade X and Y at the
+//! market price takes the most it can from the pool. Fills are written back into the reserve
+//! accounts (v2) or the trading pair (v3), so the next `update` reads them as it would on chain.
+//!
+//! The market price is what a secondary feed shows. The pool only ever sees its primary feeds,
+//! which the scenarios freeze, lag or pull away from the market.
+
+use crate::oracle;
+use crate::pool::{Fill, Side, SimPool};
+use anchor_lang::AccountSerialize;
+use anyhow::{anyhow, bail, Result};
+use obric::snapshot::Snapshot;
+use obric::{ObricAmm, ObricPool, PoolState};
+use obric_solana_v3::state::SSTradingPair as V3Pair;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_token::state::Account as TokenAccount;
+use std::{collections::HashMap, ops::Range, sync::atomic::Ordering};
+
+/// One point of a scenario, prices in USD or any common quote.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Step {
+    pub timestamp: i64,
+    /// Where X and Y trade elsewhere, what the arbitrageur hedges at.
+    pub market_x: Decimal,
+    pub market_y: Decimal,
+    /// What the pool's own feeds publish, and when they last did.
+    pub feed_x: Decimal,
+    pub feed_y: Decimal,
+    pub published_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Scenario {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+/// Where every scenario starts: the prices the feeds of the snapshot publish, with the market
+/// agreeing. Moves are in signed basis points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Baseline {
+    pub start: i64,
+    /// Seconds between steps.
+    pub interval: i64,
+    pub steps: usize,
+    pub price_x: Decimal,
+    pub price_y: Decimal,
+    /// Steps the feeds trail the market by, they still publish at every step. A pool priced off
+    /// its feeds re-centres on every `update`, so a move the feeds publish at once costs nothing.
+    pub feed_lag: usize,
+}
+
+impl Baseline {
+    /// Starts at the clock of `snapshot`, with the feeds one step behind.
+    pub fn from_snapshot(snapshot: &Snapshot, steps: usize, interval: i64) -> Result<Self> {
+        let (price_x, price_y) = oracle::snapshot_prices(snapshot)?;
+        Ok(Self {
+            start: snapshot.clock.unix_timestamp,
+            interval,
+            steps,
+            price_x,
+            price_y,
+            feed_lag: 1,
+        })
+    }
+
+    /// X moves by `jump_bps` at step `at` and stays there, the feeds follow `feed_lag` later.
+    pub fn step_jump(&self, jump_bps: i64, at: usize) -> Scenario {
+        let jumped = shift(self.price_x, jump_bps);
+        self.tracking("step_jump", |step| {
+            let price_x = if step < at { self.price_x } else { jumped };
+            (price_x, self.price_y)
+        })
+    }
+
+    /// Y slides by `depeg_bps` over the scenario, one even move per step.
+    pub fn slow_depeg(&self, depeg_bps: i64) -> Scenario {
+        self.tracking("slow_depeg", |step| {
+            (
+                self.price_x,
+                shift(self.price_y, self.progress(step, depeg_bps)),
+            )
+        })
+    }
+
+    /// X slides by `move_bps` over the scenario while the feeds publish nothing during `frozen`,
+    /// then catch up at once.
+    pub fn oracle_freeze(&self, move_bps: i64, frozen: Range<usize>) -> Scenario {
+        let mut scenario = self.tracking("oracle_freeze", |step| {
+            (
+                shift(self.price_x, self.progress(step, move_bps)),
+                self.price_y,
+            )
+        });
+        let mut last_published = None;
+        for (index, step) in scenario.steps.iter_mut().enumerate() {
+            match last_published {
+                Some((feed_x, feed_y, published_at)) if frozen.contains(&index) => {
+                    step.feed_x = feed_x;
+                    step.feed_y = feed_y;
+                    step.published_at = published_at;
+                }
+                _ => last_published = Some((step.feed_x, step.feed_y, step.published_at)),
+            }
+        }
+        scenario
+    }
+
+    /// The market holds still while the pool's X feed drifts by `divergence_bps` away from it,
+    /// as when the primary feed breaks from the secondary.
+    pub fn feed_divergence(&self, divergence_bps: i64) -> Scenario {
+        let mut scenario = self.tracking("feed_divergence", |_| (self.price_x, self.price_y));
+        for (index, step) in scenario.steps.iter_mut().enumerate() {
+            let published = index.saturating_sub(self.feed_lag);
+            step.feed_x = shift(self.price_x, self.progress(published, divergence_bps));
+        }
+        scenario
+    }
+
+    /// Every scenario at `shock_bps`: X crashes by it halfway through, Y depegs by it, X falls by
+    /// it through a freeze of the middle third, and the X feed drifts up by it.
+    pub fn standard(&self, shock_bps: i64) -> Vec<Scenario> {
+        let third = self.steps / 3;
+        vec![
+            self.step_jump(-shock_bps, self.steps / 2),
+            self.slow_depeg(-shock_bps),
+            self.oracle_freeze(-shock_bps, third..2 * third),
+            self.feed_divergence(shock_bps),
+        ]
+    }
+
+    /// The feeds follow the market `feed_lag` steps behind and publish at every step.
+    fn tracking(&self, name: &str, prices: impl Fn(usize) -> (Decimal, Decimal)) -> Scenario {
+        let steps = (0..self.steps)
+            .map(|step| {
+                let (market_x, market_y) = prices(step);
+                let (feed_x, feed_y) = prices(step.saturating_sub(self.feed_lag));
+                let timestamp = self.start + self.interval * step as i64;
+                Step {
+                    timestamp,
+                    market_x,
+                    market_y,
+                    feed_x,
+                    feed_y,
+                    published_at: timestamp,
+                }
+            })
+            .collect();
+        Scenario {
+            name: name.to_string(),
+            steps,
+        }
+    }
+
+    /// Part of `bps` reached by `step`, all of it at the last step.
+    fn progress(&self, step: usize, bps: i64) -> i64 {
+        if self.steps <= 1 {
+            return bps;
+        }
+        bps * step as i64 / (self.steps - 1) as i64
+    }
+}
+
+fn shift(price: Decimal, bps: i64) -> Decimal {
+    price * Decimal::from(10_000 + bps) / Decimal::from(10_000)
+}
+
+/// The `target_y_based_lock` held the arbitrageur back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct LockHit {
+    pub side: Side,
+    /// Input that would make the most without the lock.
+    pub wanted: u64,
+    /// Largest input the lock lets through.
+    pub allowed: u64,
+}
+
+/// Values are in the smallest unit of Y at the market prices of the step.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StepReport {
+    pub timestamp: i64,
+    pub feed_age: i64,
+    /// Why `update` refused the feeds. The pool does not trade until it takes them again.
+    pub rejected: Option<String>,
+    pub fill: Option<Fill>,
+    /// What the arbitrageur made on `fill`.
+    pub arbitrage_profit_y: i128,
+    pub lock: Option<LockHit>,
+    pub value_y: i128,
+    /// The initial holdings, had the pool not traded.
+    pub hodl_value_y: i128,
+}
+
+/// Values are in the smallest unit of Y at the market prices of their step.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StressReport {
+    pub scenario: String,
+    pub steps: usize,
+    pub fills: usize,
+    /// Most the pool trailed its initial holdings by, and when.
+    pub worst_loss_y: i128,
+    pub worst_loss_bps: i128,
+    pub worst_loss_at: Option<i64>,
+    pub final_loss_y: i128,
+    pub arbitrage_profit_y: i128,
+    /// Steps the `target_y_based_lock` held the arbitrageur back.
+    pub lock_engaged_at: Vec<i64>,
+    /// Steps `update` refused the feeds, for v2 once they are older than `feed_max_age_*`.
+    pub rejected_at: Vec<i64>,
+    pub trace: Vec<StepReport>,
+}
+
+pub fn run(snapshot: &Snapshot, scenario: &Scenario) -> Result<StressReport> {
+    let mut accounts = snapshot.account_map();
+    let mut amm = snapshot.load()?;
+    let (feed_x, feed_y) = oracle::feed_ids(&amm);
+    let mut pool = SimPool::from_amm(&amm);
+    let (x_decimals, y_decimals) = pool.decimals();
+    let initial = pool.holdings();
+    let mut report = StressReport {
+        scenario: scenario.name.clone(),
+        steps: scenario.steps.len(),
+        fills: 0,
+        worst_loss_y: 0,
+        worst_loss_bps: 0,
+        worst_loss_at: None,
+        final_loss_y: 0,
+        arbitrage_profit_y: 0,
+        lock_engaged_at: vec![],
+        rejected_at: vec![],
+        trace: vec![],
+    };
+
+    for step in &scenario.steps {
+        if let ObricAmm::V2(amm) = &amm {
+            amm.clock_ref
+                .unix_timestamp
+                .store(step.timestamp, Ordering::Relaxed);
+        }
+        oracle::write_price(
+            account_mut(&mut accounts, &feed_x)?,
+            step.feed_x,
+            step.published_at,
+        )?;
+        oracle::write_price(
+            account_mut(&mut accounts, &feed_y)?,
+            step.feed_y,
+            step.published_at,
+        )?;
+        let rate = market_rate(step, x_decimals, y_decimals)?;
+        let mut step_report = StepReport {
+            timestamp: step.timestamp,
+            feed_age: step.timestamp - step.published_at,
+            rejected: None,
+            fill: None,
+            arbitrage_profit_y: 0,
+            lock: None,
+            value_y: 0,
+            hodl_value_y: 0,
+        };
+
+        match ObricPool::update(&mut amm, &accounts) {
+            Err(err) => {
+                step_report.rejected = Some(format!("{err:#}"));
+                report.rejected_at.push(step.timestamp);
+            }
+            Ok(()) => {
+                pool = SimPool::from_amm(&amm);
+                step_report.lock = lock_hit(&amm, rate)?;
+                if step_report.lock.is_some() {
+                    report.lock_engaged_at.push(step.timestamp);
+                }
+                if let Some((side, in_amount)) = best_trade(&amm, rate)? {
+                    let fill = pool.swap(side, in_amount)?;
+                    write_balances(&mut accounts, &amm, &pool)?;
+                    step_report.arbitrage_profit_y = to_i128(profit(&fill, rate))?;
+                    step_report.fill = Some(fill);
+                    report.fills += 1;
+                    report.arbitrage_profit_y += step_report.arbitrage_profit_y;
+                }
+            }
+        }
+
+        step_report.value_y = value_y(pool.holdings(), rate)?;
+        step_report.hodl_value_y = value_y(initial, rate)?;
+        let loss = step_report.hodl_value_y - step_report.value_y;
+        if report.worst_loss_at.is_none() || loss > report.worst_loss_y {
+            report.worst_loss_y = loss;
+            report.worst_loss_at = Some(step.timestamp);
+            if step_report.hodl_value_y > 0 {
+                report.worst_loss_bps = loss * 10_000 / step_report.hodl_value_y;
+            }
+        }
+        report.final_loss_y = loss;
+        report.trace.push(step_report);
+    }
+    Ok(report)
+}
+
+fn account_mut<'a>(
+    accounts: &'a mut HashMap<Pubkey, Account>,
+    key: &Pubkey,
+) -> Result<&'a mut Account> {
+    accounts
+        .get_mut(key)
+        .ok_or_else(|| anyhow!("snapshot does not hold {key}"))
+}
+
+/// Smallest units of Y one smallest unit of X is worth on the market.
+fn market_rate(step: &Step, x_decimals: u8, y_decimals: u8) -> Result<Decimal> {
+    let decimals = |decimals: u8| Decimal::from(10u64.pow(decimals as u32));
+    step.market_x
+        .checked_mul(decimals(y_decimals))
+        .and_then(|value| value.checked_div(step.market_y))
+        .and_then(|value| value.checked_div(decimals(x_decimals)))
+        .ok_or_else(|| anyhow!("no market rate at {}", step.timestamp))
+}
+
+fn value_y((x, y): (i128, i128), rate: Decimal) -> Result<i128> {
+    to_i128(Decimal::from_i128_with_scale(x, 0) * rate + Decimal::from_i128_with_scale(y, 0))
+}
+
+fn to_i128(value: Decimal) -> Result<i128> {
+    value
+        .floor()
+        .to_i128()
+        .ok_or_else(|| anyhow!("{value} does not fit an i128"))
+}
+
+fn profit(fill: &Fill, rate: Decimal) -> Decimal {
+    let (in_amount, out_amount) = (
+        Decimal::from(fill.in_amount),
+        Decimal::from(fill.out_amount),
+    );
+    match fill.side {
+        Side::XToY => out_amount - in_amount * rate,
+        Side::YToX => out_amount * rate - in_amount,
+    }
+}
+
+fn input_mint(amm: &ObricAmm, side: Side) -> Pubkey {
+    let (mint_x, mint_y) = amm.mints();
+    match side {
+        Side::XToY => mint_x,
+        Side::YToX => mint_y,
+    }
+}
+
+/// Side and input the pool quotes the most profit for at the market rate, if any. The profit
+/// only grows until the pool's marginal price meets the market, so it is searched by thirds.
+fn best_trade(amm: &ObricAmm, rate: Decimal) -> Result<Option<(Side, u64)>> {
+    let mut best: Option<(Side, u64, Decimal)> = None;
+    for side in [Side::XToY, Side::YToX] {
+        let input_mint = input_mint(amm, side);
+        let Ok(max_input) = amm.max_input(&input_mint) else {
+            continue;
+        };
+        let profit_of = |in_amount: u64| {
+            let quote = amm.quote(&input_mint, in_amount).ok()?;
+            let fill = Fill {
+                side,
+                in_amount,
+                out_amount: quote.out_amount,
+                lp_fee: 0,
+                protocol_fee: 0,
+            };
+            Some(profit(&fill, rate))
+        };
+        let (mut low, mut high) = (0, max_input);
+        while high - low > 2 {
+            let third = (high - low) / 3;
+            let (left, right) = (low + third, high - third);
+            if profit_of(left) < profit_of(right) {
+                low = left + 1;
+            } else {
+                high = right - 1;
+            }
+        }
+        for in_amount in low..=high {
+            match (profit_of(in_amount), best) {
+                (Some(profit), Some((.., most))) if profit > most => {
+                    best = Some((side, in_amount, profit))
+                }
+                (Some(profit), None) if profit > Decimal::ZERO => {
+                    best = Some((side, in_amount, profit))
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(best.map(|(side, in_amount, _)| (side, in_amount)))
+}
+
+/// Whether the lock stops the arbitrageur short of what it would take from the same v2 pool
+/// without it.
+fn lock_hit(amm: &ObricAmm, rate: Decimal) -> Result<Option<LockHit>> {
+    let ObricAmm::V2(locked) = amm else {
+        return Ok(None);
+    };
+    if !locked.state.target_y_based_lock {
+        return Ok(None);
+    }
+    let mut unlocked = locked.clone();
+    unlocked.state.target_y_based_lock = false;
+    let Some((side, wanted)) = best_trade(&ObricAmm::V2(unlocked), rate)? else {
+        return Ok(None);
+    };
+    let allowed = amm.max_input(&input_mint(amm, side))?;
+    Ok((wanted > allowed).then_some(LockHit {
+        side,
+        wanted,
+        allowed,
+    }))
+}
+
+/// Writes the balances of `pool` into the accounts `update` reads them from.
+fn write_balances(
+    accounts: &mut HashMap<Pubkey, Account>,
+    amm: &ObricAmm,
+    pool: &SimPool,
+) -> Result<()> {
+    match (amm.state(), pool) {
+        (
+            PoolState::V2(pair),
+            SimPool::V2 {
+                current_x,
+                current_y,
+                ..
+            },
+        ) => {
+            for (reserve, amount) in [(pair.reserve_x, *current_x), (pair.reserve_y, *current_y)] {
+                let account = account_mut(accounts, &reserve)?;
+                let mut token_account = TokenAccount::unpack(&account.data)?;
+                token_account.amount = amount;
+                TokenAccount::pack(token_account, &mut account.data)?;
+            }
+        }
+        (PoolState::V3(_), SimPool::V3 { state }) => {
+            let account = account_mut(accounts, &amm.key())?;
+            let mut pair = V3Pair::decode(&account.data)?;
+            pair.deposit_x = state.deposit_x;
+            pair.borrow_x = state.borrow_x;
+            pair.deposit_y = state.deposit_y;
+            pair.borrow_y = state.borrow_y;
+            let mut data = vec![];
+            pair.try_serialize(&mut data)?;
+            oracle::overwrite(account, &data)?;
+        }
+        _ => bail!("the pool and the simulated pair are of different versions"),
+    }
+    Ok(())
+}
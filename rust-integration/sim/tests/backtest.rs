@@ -0,0 +1,143 @@
+//! Backtests of the bench fixtures at their own prices, 150 USDC per SOL.
+
+use obric::snapshot::Snapshot;
+use obric_bench::{v2_pool, v3_pool, Fixture, Oracle, NOW};
+use obric_sim::backtest::{self, Params, PriceTick, Trade};
+use obric_sim::pool::{Side, SimPool};
+use rust_decimal::Decimal;
+use solana_sdk::clock::Clock;
+use std::fs;
+
+const SOL: u64 = 1_000_000_000;
+const USDC: u64 = 1_000_000;
+
+fn sim_pool(fixture: &Fixture) -> SimPool {
+    let clock = Clock {
+        unix_timestamp: NOW,
+        ..Clock::default()
+    };
+    let amm = Snapshot::new(fixture.key, 0, clock, &fixture.accounts)
+        .load()
+        .unwrap();
+    SimPool::from_amm(&amm)
+}
+
+fn flat_prices() -> Vec<PriceTick> {
+    (0..4)
+        .map(|tick| PriceTick {
+            timestamp: NOW + 10 * tick,
+            price_x: Decimal::from(150),
+            price_y: Decimal::ONE,
+        })
+        .collect()
+}
+
+fn trade(timestamp: i64, side: Side, amount: u64) -> Trade {
+    Trade {
+        timestamp,
+        side,
+        amount,
+    }
+}
+
+#[test]
+fn round_trips_at_a_flat_price_earn_the_lps_fees() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let pool = sim_pool(&fixture);
+        let trades = [
+            trade(NOW + 1, Side::XToY, 10 * SOL),
+            trade(NOW + 2, Side::YToX, 1_500 * USDC),
+        ];
+        let report = backtest::run(&pool, Params::of(&pool), &flat_prices(), &trades).unwrap();
+
+        assert_eq!(report.filled, 2, "{:?}", report.rejected);
+        assert_eq!(report.volume_x, 10 * SOL as u128);
+        assert_eq!(report.volume_y, 1_500 * USDC as u128);
+        assert!(report.lp_fees_x > 0 && report.lp_fees_y > 0);
+        assert!(report.lp_fees_value_y > 0);
+        assert_eq!(report.hodl_value_y, report.initial_value_y);
+        assert!(report.pnl_vs_hodl_y > 0, "{}", report.pnl_vs_hodl_y);
+        assert_eq!(report.samples.len(), 4);
+    }
+}
+
+#[test]
+fn selling_x_drifts_the_inventory() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let pool = sim_pool(&fixture);
+        let before = pool.inventory_drift_x();
+        let trades = [trade(NOW + 1, Side::XToY, 10 * SOL)];
+        let report = backtest::run(&pool, Params::of(&pool), &flat_prices(), &trades).unwrap();
+
+        assert_eq!(report.final_inventory_drift_x, before + 10 * SOL as i128);
+        assert!(report.max_inventory_drift_x >= report.final_inventory_drift_x.abs());
+    }
+}
+
+#[test]
+fn trades_without_a_fresh_price_are_rejected() {
+    let pool = sim_pool(&v2_pool(Oracle::Pyth));
+    let prices = &flat_prices()[..1];
+    let trades = [
+        trade(NOW - 1, Side::XToY, SOL),
+        trade(NOW + 30, Side::XToY, SOL),
+        trade(NOW + 31, Side::XToY, SOL),
+    ];
+    let report = backtest::run(&pool, Params::of(&pool), prices, &trades).unwrap();
+
+    assert_eq!(report.filled, 1);
+    let rejected: Vec<_> = report.rejected.iter().map(|r| r.trade).collect();
+    assert_eq!(rejected, [trades[0], trades[2]]);
+}
+
+#[test]
+fn higher_concentration_quotes_deeper() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let pool = sim_pool(&fixture);
+        let out = |concentration: u64| {
+            let mut pool = pool.clone();
+            pool.set_concentration(concentration).unwrap();
+            pool.update_prices(
+                150 * 10u64.pow(pool.price_decimals() as u32),
+                10u64.pow(pool.price_decimals() as u32),
+            )
+            .unwrap();
+            pool.swap(Side::XToY, 100 * SOL).unwrap().out_amount
+        };
+        assert!(out(100) > out(50));
+        assert!(out(50) > out(10));
+    }
+}
+
+#[test]
+fn tapes_are_read_from_csv_and_json() {
+    let dir = std::env::temp_dir().join(format!("obric-sim-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let csv = dir.join("trades.csv");
+    fs::write(
+        &csv,
+        "timestamp,side,amount\n1700000001, x_to_y, 5\n1700000002,y_to_x,7\n",
+    )
+    .unwrap();
+    let json = dir.join("prices.json");
+    fs::write(
+        &json,
+        r#"[{"timestamp": 1700000000, "price_x": "150.25", "price_y": 1}]"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        backtest::read_trades(&csv).unwrap(),
+        [trade(NOW + 1, Side::XToY, 5), trade(NOW + 2, Side::YToX, 7)]
+    );
+    let prices = backtest::read_prices(&json).unwrap();
+    assert_eq!(
+        backtest::normalize_price(prices[0].price_x, 6).unwrap(),
+        150_250_000
+    );
+    assert_eq!(
+        backtest::normalize_price(prices[0].price_y, 3).unwrap(),
+        1_000
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,145 @@
+//! Optimizing the bench fixtures, 150 USDC per SOL.
+
+use obric::snapshot::Snapshot;
+use obric_bench::{v2_pool, v3_pool, Fixture, Oracle, NOW};
+use obric_sim::backtest::{Params, PriceTick, Trade};
+use obric_sim::optimize::{self, FlowSizes, Grid, Market};
+use obric_sim::pool::{Side, SimPool};
+use rust_decimal::Decimal;
+use solana_sdk::clock::Clock;
+
+const SOL: u64 = 1_000_000_000;
+const USDC: u64 = 1_000_000;
+
+fn sim_pool(fixture: &Fixture) -> SimPool {
+    let clock = Clock {
+        unix_timestamp: NOW,
+        ..Clock::default()
+    };
+    let amm = Snapshot::new(fixture.key, 0, clock, &fixture.accounts)
+        .load()
+        .unwrap();
+    SimPool::from_amm(&amm)
+}
+
+fn tick(timestamp: i64, price_x: u64) -> PriceTick {
+    PriceTick {
+        timestamp,
+        price_x: Decimal::from(price_x),
+        price_y: Decimal::ONE,
+    }
+}
+
+fn market(volatility: f64) -> Market {
+    Market {
+        price_x: Decimal::from(150),
+        price_y: Decimal::ONE,
+        volatility,
+        interval: 10,
+        steps: 20,
+        paths: 8,
+        seed: 7,
+    }
+}
+
+fn flow() -> FlowSizes {
+    FlowSizes::from_trades(&[
+        Trade {
+            timestamp: NOW,
+            side: Side::XToY,
+            amount: SOL,
+        },
+        Trade {
+            timestamp: NOW + 1,
+            side: Side::YToX,
+            amount: 150 * USDC,
+        },
+    ])
+}
+
+#[test]
+fn volatility_is_per_square_root_of_a_second() {
+    // two returns of ln 2 over 4 seconds
+    let prices = [tick(0, 100), tick(1, 200), tick(4, 100)];
+    let volatility = optimize::volatility(&prices).unwrap();
+    let expected = (2.0 * 2f64.ln().powi(2) / 4.0).sqrt();
+    assert!((volatility - expected).abs() < 1e-12, "{volatility}");
+
+    let flat: Vec<_> = (0..5).map(|at| tick(at, 150)).collect();
+    assert_eq!(optimize::volatility(&flat).unwrap(), 0.0);
+    assert!(optimize::volatility(&prices[..1]).is_err());
+    assert!(optimize::volatility(&[tick(1, 100), tick(1, 100)]).is_err());
+}
+
+#[test]
+fn grids_spread_around_the_params() {
+    let grid = Grid::around(Params {
+        concentration: 50,
+        fee_millionth: 2,
+    });
+    assert_eq!(grid.concentrations, [12, 25, 50, 100, 200]);
+    assert_eq!(grid.fees_millionth, [1, 2, 4, 8]);
+}
+
+#[test]
+fn concentration_buys_depth() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let pool = sim_pool(&fixture);
+        let grid = Grid {
+            concentrations: vec![25, 50, 100],
+            fees_millionth: vec![300],
+        };
+        let result = optimize::optimize(&pool, &market(0.0), &flow(), &grid, 50).unwrap();
+
+        assert_eq!(result.current, Params::of(&pool));
+        assert_eq!(result.points.len(), 3);
+        for pair in result.points.windows(2) {
+            assert!(pair[0].depth_x < pair[1].depth_x, "{:?}", result.points);
+            assert!(pair[0].depth_y < pair[1].depth_y, "{:?}", result.points);
+        }
+        // the shallower point is never recommended
+        let recommended = result.recommended.unwrap();
+        assert!(recommended.concentration >= pool.concentration());
+    }
+}
+
+#[test]
+fn without_volatility_fees_are_all_profit() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let pool = sim_pool(&fixture);
+        let grid = Grid {
+            concentrations: vec![pool.concentration()],
+            fees_millionth: vec![100, 300, 1_000],
+        };
+        let result = optimize::optimize(&pool, &market(0.0), &flow(), &grid, 50).unwrap();
+
+        assert_eq!(result.points.len(), 3);
+        for point in &result.points {
+            assert_eq!(point.filled, point.trades);
+            assert_eq!(point.trades, 8 * 20);
+            assert!(point.mean_pnl_vs_hodl_y > 0, "{point:?}");
+        }
+        for pair in result.points.windows(2) {
+            assert!(pair[0].mean_lp_fees_value_y < pair[1].mean_lp_fees_value_y);
+        }
+        // depth counts the fee, so the dearer point is shallower and the cheaper one earns less
+        assert!(result.points[2].depth_x < result.points[1].depth_x);
+        assert_eq!(result.recommended, Some(result.current));
+    }
+}
+
+#[test]
+fn a_seed_replays_the_same_paths() {
+    let pool = sim_pool(&v2_pool(Oracle::Pyth));
+    let grid = Grid::around(Params::of(&pool));
+    let run = |seed| {
+        let market = Market {
+            seed,
+            ..market(0.0005)
+        };
+        optimize::optimize(&pool, &market, &flow(), &grid, 50).unwrap()
+    };
+    assert_eq!(run(1), run(1));
+    assert_ne!(run(1), run(2));
+    assert!(optimize::optimize(&pool, &market(0.0), &FlowSizes::default(), &grid, 50).is_err());
+}
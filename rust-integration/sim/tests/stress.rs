@@ -0,0 +1,154 @@
+//! Oracle shocks against the bench fixtures, 150 USDC per SOL before the shock.
+
+use anchor_lang::AccountSerialize;
+use obric::snapshot::Snapshot;
+use obric_bench::{v2_pool, v3_pool, Fixture, Oracle, NOW};
+use obric_sim::oracle;
+use obric_sim::pool::Side;
+use obric_sim::stress::{self, Baseline, StressReport};
+use rust_decimal::Decimal;
+use solana_sdk::clock::Clock;
+use std::str::FromStr;
+
+const STEPS: usize = 12;
+const INTERVAL: i64 = 10;
+
+fn snapshot(fixture: &Fixture) -> Snapshot {
+    let clock = Clock {
+        unix_timestamp: NOW,
+        ..Clock::default()
+    };
+    Snapshot::new(fixture.key, 0, clock, &fixture.accounts)
+}
+
+fn baseline(snapshot: &Snapshot) -> Baseline {
+    Baseline::from_snapshot(snapshot, STEPS, INTERVAL).unwrap()
+}
+
+fn locked_v2_pool() -> Fixture {
+    let mut fixture = v2_pool(Oracle::Pyth);
+    let pair = fixture.accounts.get_mut(&fixture.key).unwrap();
+    let mut state = obric_solana::state::SSTradingPair::decode(&pair.data).unwrap();
+    state.target_y_based_lock = true;
+    pair.data.clear();
+    state.try_serialize(&mut pair.data).unwrap();
+    fixture.account = pair.clone();
+    fixture
+}
+
+fn fills_at(report: &StressReport) -> Vec<i64> {
+    report
+        .trace
+        .iter()
+        .filter(|step| step.fill.is_some())
+        .map(|step| step.timestamp)
+        .collect()
+}
+
+#[test]
+fn baselines_start_from_the_feeds() {
+    for fixture in [v2_pool(Oracle::Pyth), v2_pool(Oracle::Doves), v3_pool()] {
+        let baseline = baseline(&snapshot(&fixture));
+        assert_eq!(baseline.start, NOW);
+        assert_eq!(baseline.price_x, Decimal::from(150));
+        assert_eq!(baseline.price_y, Decimal::ONE);
+    }
+}
+
+#[test]
+fn feeds_are_rewritten_in_place() {
+    for fixture in [v2_pool(Oracle::Pyth), v2_pool(Oracle::Doves)] {
+        let snapshot = snapshot(&fixture);
+        let amm = snapshot.load_v2().unwrap();
+        let mut feed = fixture.accounts[&amm.state.x_price_feed_id].clone();
+        let size = feed.data.len();
+        let price = Decimal::from_str("142.5").unwrap();
+        oracle::write_price(&mut feed, price, NOW + 7).unwrap();
+        assert_eq!(oracle::read_price(&feed).unwrap(), (price, NOW + 7));
+        assert_eq!(feed.data.len(), size);
+    }
+}
+
+#[test]
+fn a_step_jump_is_arbitraged_while_the_feeds_trail() {
+    for fixture in [v2_pool(Oracle::Pyth), v2_pool(Oracle::Doves), v3_pool()] {
+        let snapshot = snapshot(&fixture);
+        let baseline = baseline(&snapshot);
+        let report = stress::run(&snapshot, &baseline.step_jump(-500, 6)).unwrap();
+        let jump_at = NOW + 6 * INTERVAL;
+
+        // the feeds publish the jump a step late
+        assert!(fills_at(&report).contains(&jump_at));
+        assert!(report.arbitrage_profit_y > 0);
+        assert!(report.worst_loss_y > 0 && report.worst_loss_bps > 0);
+        assert!(report.worst_loss_at >= Some(jump_at));
+        assert!(report.rejected_at.is_empty());
+        assert!(report.lock_engaged_at.is_empty());
+    }
+}
+
+#[test]
+fn v2_rejects_feeds_frozen_past_their_max_age() {
+    let snapshot = snapshot(&v2_pool(Oracle::Pyth));
+    let baseline = baseline(&snapshot);
+    let report = stress::run(&snapshot, &baseline.oracle_freeze(-500, 3..9)).unwrap();
+
+    // feed_max_age is 30s, the freeze lasts 60s
+    for step in &report.trace {
+        assert_eq!(step.rejected.is_some(), step.feed_age > 30, "{step:?}");
+        if step.rejected.is_some() {
+            assert!(step.fill.is_none());
+        }
+    }
+    assert_eq!(
+        report.rejected_at,
+        [NOW + 6 * INTERVAL, NOW + 7 * INTERVAL, NOW + 8 * INTERVAL]
+    );
+    // the catch-up is arbitraged at once
+    assert!(fills_at(&report).contains(&(NOW + 9 * INTERVAL)));
+}
+
+#[test]
+fn v3_keeps_quoting_frozen_feeds() {
+    let snapshot = snapshot(&v3_pool());
+    let baseline = baseline(&snapshot);
+    let report = stress::run(&snapshot, &baseline.oracle_freeze(-500, 3..9)).unwrap();
+
+    assert!(report.rejected_at.is_empty());
+    assert!(fills_at(&report)
+        .iter()
+        .any(|at| (NOW + 3 * INTERVAL..NOW + 9 * INTERVAL).contains(at)));
+}
+
+#[test]
+fn the_lock_holds_off_the_arbitrageur() {
+    let snapshot = snapshot(&locked_v2_pool());
+    let baseline = baseline(&snapshot);
+    let report = stress::run(&snapshot, &baseline.step_jump(-500, 6)).unwrap();
+
+    // the pool sits at its target, selling X into it moves it away, and once the feeds have
+    // caught up there is nothing left to take
+    assert_eq!(report.fills, 0);
+    assert_eq!(report.lock_engaged_at, [NOW + 6 * INTERVAL]);
+    let hit = report.trace[6].lock.unwrap();
+    assert_eq!(hit.side, Side::XToY);
+    assert!(hit.wanted > 0);
+    assert_eq!(hit.allowed, 0);
+    assert_eq!(report.worst_loss_y, 0);
+}
+
+#[test]
+fn diverging_and_depegging_feeds_are_arbitraged() {
+    for fixture in [v2_pool(Oracle::Pyth), v3_pool()] {
+        let snapshot = snapshot(&fixture);
+        for scenario in baseline(&snapshot).standard(500) {
+            let report = stress::run(&snapshot, &scenario).unwrap();
+            assert_eq!(report.steps, STEPS);
+            assert_eq!(report.trace.len(), STEPS);
+            if scenario.name == "feed_divergence" || scenario.name == "slow_depeg" {
+                assert!(report.fills > 0, "{}", scenario.name);
+                assert!(report.final_loss_y > 0, "{}", scenario.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Addresses that differ between clusters. `ObricV2Amm::from_keyed_account` assumes mainnet,
+//! `from_keyed_account_with_cluster` takes any of them.
+
+use crate::obric_v2_amm;
+use anyhow::Result;
+use obric_solana::consts::deployments;
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+pub const PYTH_MAINNET_PROGRAM_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+pub const PYTH_DEVNET_PROGRAM_ID: Pubkey = pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
+pub const DOVES_PROGRAM_ID: Pubkey = pubkey!("DoVEsk76QybCEHQGzkvYPWLQu9gzNoZZZt3TPiL597e");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClusterConfig {
+    /// Owner of the trading pairs and target of the swap instruction.
+    pub program_id: Pubkey,
+    /// Price feeds must be owned by one of the two oracle programs, the owner decides how the
+    /// feed is read.
+    pub pyth_program_id: Pubkey,
+    pub doves_program_id: Pubkey,
+}
+
+impl ClusterConfig {
+    pub fn mainnet() -> Self {
+        Self {
+            program_id: obric_v2_amm::id(),
+            pyth_program_id: PYTH_MAINNET_PROGRAM_ID,
+            doves_program_id: DOVES_PROGRAM_ID,
+        }
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    Mainnet,
+    /// Doves feeds are read at the mainnet program address.
+    Devnet,
+    /// A test validator running the local build, with the oracle programs cloned from mainnet.
+    Localnet,
+    Custom(ClusterConfig),
+}
+
+impl Cluster {
+    pub fn config(&self) -> Result<ClusterConfig> {
+        Ok(match self {
+            Cluster::Mainnet => ClusterConfig::mainnet(),
+            Cluster::Devnet => ClusterConfig {
+                program_id: deployments::devnet::id(),
+                pyth_program_id: PYTH_DEVNET_PROGRAM_ID,
+                doves_program_id: DOVES_PROGRAM_ID,
+            },
+            Cluster::Localnet => ClusterConfig {
+                program_id: obric_solana::id(),
+                ..ClusterConfig::mainnet()
+            },
+            Cluster::Custom(config) => *config,
+        })
+    }
+}
+
+#[test]
+fn cluster_ids_agree_with_account_crate() {
+    let expected = [
+        (Cluster::Mainnet, deployments::mainnet::id()),
+        (Cluster::Devnet, deployments::devnet::id()),
+        (Cluster::Localnet, obric_solana::id()),
+    ];
+    for (cluster, program_id) in expected {
+        let config = cluster.config().unwrap();
+        assert_eq!(config.program_id, program_id, "{cluster:?}");
+        assert_ne!(
+            config.pyth_program_id, config.doves_program_id,
+            "{cluster:?}"
+        );
+    }
+    assert_eq!(DOVES_PROGRAM_ID, doves_cpi::id());
+}
@@ -0,0 +1,114 @@
+//! Replays swaps recorded on mainnet against the SDK quote.
+//!
+//! Recording and replaying live in `obric-golden-vectors`, this module quotes a vector with
+//! `ObricV2Amm` and decodes the v2 swap instruction. `record_golden_vectors` needs `SOLANA_RPC`
+//! and appends new vectors to `golden_vectors/`.
+
+use crate::obric_v2_amm::{ObricV2Amm, ID};
+use crate::test_harness::AmmTestHarness;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{anyhow, Result};
+use jupiter_amm_interface::{
+    AccountMap, Amm, AmmContext, ClockRef, KeyedAccount, QuoteParams, SwapMode,
+};
+use obric_golden_vectors::{GoldenVector, SwapArgs, SwapInstruction};
+use obric_solana::instruction::Swap;
+use solana_sdk::clock::Clock;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+const SWAP: SwapInstruction = SwapInstruction {
+    program_id: ID,
+    decode: decode_swap,
+    // user_token_account_x and user_token_account_y in the Swap accounts
+    user_token_accounts: [5, 6],
+};
+
+fn decode_swap(data: &[u8]) -> Result<Option<SwapArgs>> {
+    if !data.starts_with(&Swap::DISCRIMINATOR) {
+        return Ok(None);
+    }
+    let args = Swap::try_from_slice(&data[8..])?;
+    Ok(Some(SwapArgs {
+        x_to_y: args._is_x_to_y,
+        input_amount: args._input_amt,
+        min_output_amount: args._min_output_amt,
+    }))
+}
+
+fn vectors_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("golden_vectors")
+}
+
+fn quote(vector: &GoldenVector) -> Result<u64> {
+    let key = vector.pair()?;
+    let account_map: AccountMap = vector.accounts()?;
+    let account = account_map
+        .get(&key)
+        .cloned()
+        .ok_or_else(|| anyhow!("vector is missing the trading pair"))?;
+    let clock = Clock {
+        slot: vector.slot,
+        unix_timestamp: vector.unix_timestamp,
+        ..Clock::default()
+    };
+    let amm_context = AmmContext {
+        clock_ref: ClockRef::from(clock),
+    };
+
+    let mut amm = ObricV2Amm::from_keyed_account(
+        &KeyedAccount {
+            key,
+            account,
+            params: None,
+        },
+        &amm_context,
+    )?;
+    amm.update(&account_map)?;
+
+    let (input_mint, output_mint) = if vector.swap.x_to_y {
+        (amm.state.mint_x, amm.state.mint_y)
+    } else {
+        (amm.state.mint_y, amm.state.mint_x)
+    };
+    let quote = amm.quote(&QuoteParams {
+        amount: vector.swap.input_amount,
+        input_mint,
+        output_mint,
+        swap_mode: SwapMode::ExactIn,
+    })?;
+    Ok(quote.out_amount)
+}
+
+#[test]
+#[ignore = "golden_vectors/ has no recorded swaps yet, see golden_vectors/README.md"]
+fn replay_golden_vectors() {
+    obric_golden_vectors::replay(&vectors_dir(), quote);
+}
+
+/// `GOLDEN_VECTORS` vectors per pool and direction, written to `golden_vectors/`.
+#[test]
+#[ignore]
+fn record_golden_vectors() {
+    let wanted: usize = env::var("GOLDEN_VECTORS")
+        .map(|count| count.parse().unwrap())
+        .unwrap_or(1);
+    let test_harness = AmmTestHarness::new();
+    let amm_context = AmmContext {
+        clock_ref: ClockRef::default(),
+    };
+    // a fresh amm still lists the mints, which the replay needs for decimals
+    let pools: Vec<_> = test_harness
+        .get_all_keyed_account()
+        .unwrap()
+        .iter()
+        .map(|keyed_account| {
+            let amm = ObricV2Amm::from_keyed_account(keyed_account, &amm_context).unwrap();
+            (keyed_account.key, amm.get_accounts_to_update())
+        })
+        .collect();
+
+    obric_golden_vectors::record(&test_harness.client, SWAP, &pools, wanted, &vectors_dir());
+}
@@ -1,4 +1,8 @@
+pub mod cluster;
+pub use obric_depth as depth;
 pub mod obric_v2_amm;
 
+#[cfg(test)]
+pub mod golden_vectors;
 #[cfg(test)]
 pub mod test_harness;
@@ -1,10 +1,15 @@
-use anchor_lang::{declare_id, AccountDeserialize};
+use crate::cluster::{Cluster, ClusterConfig, DOVES_PROGRAM_ID};
+use crate::depth::{to_decimal, DepthLevel, DepthProfile, ImpactThreshold};
+use anchor_lang::declare_id;
 use anyhow::{anyhow, bail, Result};
 use jupiter_amm_interface::{
     try_get_account_data, AccountMap, Amm, AmmContext, ClockRef, KeyedAccount, Quote, QuoteParams,
-    Swap, SwapAndAccountMetas, SwapParams,
+    Swap, SwapAndAccountMetas, SwapMode, SwapParams,
 };
-use obric_solana::state::{parse_price, SSTradingPair};
+use obric_solana::math::fixed_point_price;
+use obric_solana::pda;
+use obric_solana::state::{parse_price, PoolValues, SSTradingPair, SwapDirection};
+use rust_decimal::Decimal;
 use solana_sdk::{instruction::AccountMeta, program_pack::Pack, pubkey::Pubkey};
 use spl_token::state::{Account as TokenAccount, Mint};
 
@@ -21,24 +26,14 @@ pub struct ObricV2Amm {
     pub clock_ref: ClockRef,
     pub x_price_publish_time: i64,
     pub y_price_publish_time: i64,
+    /// Curve values `update` computes for the quotes, `None` when the pool cannot quote.
+    pub pool_values: Option<PoolValues>,
+    pub cluster: ClusterConfig,
 }
 
 impl Amm for ObricV2Amm {
     fn from_keyed_account(keyed_account: &KeyedAccount, amm_context: &AmmContext) -> Result<Self> {
-        let data = &mut &keyed_account.account.data.clone()[0..];
-        let ss_trading_pair = SSTradingPair::try_deserialize(data)?;
-
-        Ok(Self {
-            key: keyed_account.key,
-            state: ss_trading_pair,
-            current_x: 0u64,
-            current_y: 0u64,
-            x_decimals: 0u8,
-            y_decimals: 0u8,
-            clock_ref: amm_context.clock_ref.clone(),
-            x_price_publish_time: 0,
-            y_price_publish_time: 0,
-        })
+        Self::from_keyed_account_with_cluster(keyed_account, amm_context, &Cluster::Mainnet)
     }
 
     fn label(&self) -> String {
@@ -46,7 +41,7 @@ impl Amm for ObricV2Amm {
     }
 
     fn program_id(&self) -> Pubkey {
-        self::id()
+        self.cluster.program_id
     }
 
     fn key(&self) -> Pubkey {
@@ -79,18 +74,19 @@ impl Amm for ObricV2Amm {
 
     fn update(&mut self, account_map: &AccountMap) -> Result<()> {
         let trading_pair_account =
-            SSTradingPair::try_deserialize(&mut try_get_account_data(account_map, &self.key())?)?;
+            SSTradingPair::decode(try_get_account_data(account_map, &self.key())?)?;
         let reserve_x_token_account =
             TokenAccount::unpack(try_get_account_data(account_map, &self.state.reserve_x)?)?;
         let reserve_y_token_account =
             TokenAccount::unpack(try_get_account_data(account_map, &self.state.reserve_y)?)?;
 
-        let price_x_data = &mut try_get_account_data(account_map, &self.state.x_price_feed_id)?;
-        let price_y_data = &mut try_get_account_data(account_map, &self.state.y_price_feed_id)?;
+        let price_x_data = self.price_feed_data(account_map, &self.state.x_price_feed_id)?;
+        let price_y_data = self.price_feed_data(account_map, &self.state.y_price_feed_id)?;
 
         self.state = trading_pair_account;
         self.current_x = reserve_x_token_account.amount;
         self.current_y = reserve_y_token_account.amount;
+        self.pool_values = None;
 
         if self.current_x == 0 || self.current_y == 0 {
             return Ok(());
@@ -132,43 +128,26 @@ impl Amm for ObricV2Amm {
             self.x_decimals,
             self.y_decimals,
         )?;
+        self.pool_values = self
+            .state
+            .get_pool_values_for_quoting(self.current_x, self.current_y)
+            .ok();
 
         Ok(())
     }
 
     fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
-        let time = self
-            .clock_ref
-            .unix_timestamp
-            .load(std::sync::atomic::Ordering::Relaxed);
+        self.check_price_freshness()?;
 
-        let x_age = time
-            .checked_sub(self.x_price_publish_time)
-            .ok_or(anyhow!("overflow"))?;
-        let y_age = time
-            .checked_sub(self.y_price_publish_time)
-            .ok_or(anyhow!("overflow"))?;
-        if x_age > self.state.feed_max_age_x as i64 || y_age > self.state.feed_max_age_y as i64 {
-            return Err(anyhow!("stale price feed"));
-        }
-
-        let (output_after_fee, fee_amount) =
-            if quote_params.input_mint.eq(&self.state.mint_x) {
-                self.state
-                    .quote_x_to_y(quote_params.amount, self.current_x, self.current_y)?
-            } else if quote_params.input_mint.eq(&self.state.mint_y) {
-                self.state
-                    .quote_y_to_x(quote_params.amount, self.current_x, self.current_y)?
-            } else {
-                bail!("Quote doesn't return");
-            };
+        let direction = if quote_params.input_mint.eq(&self.state.mint_x) {
+            SwapDirection::XToY
+        } else if quote_params.input_mint.eq(&self.state.mint_y) {
+            SwapDirection::YToX
+        } else {
+            bail!("Quote doesn't return");
+        };
 
-        Ok(Quote {
-            out_amount: output_after_fee,
-            fee_amount,
-            fee_mint: quote_params.output_mint,
-            ..Quote::default()
-        })
+        self.quote_with_pool_values(&self.pool_values()?, quote_params.amount, direction)
     }
 
     fn clone_amm(&self) -> Box<dyn Amm + Send + Sync> {
@@ -194,7 +173,7 @@ impl Amm for ObricV2Amm {
         Ok(SwapAndAccountMetas {
             swap: Swap::Obric { x_to_y },
             account_metas: vec![
-                AccountMeta::new_readonly(self::ID, false),
+                AccountMeta::new_readonly(self.cluster.program_id, false),
                 AccountMeta::new(self.key(), false),
                 AccountMeta::new_readonly(self.state.mint_x, false),
                 AccountMeta::new_readonly(self.state.mint_y, false),
@@ -215,3 +194,383 @@ impl Amm for ObricV2Amm {
         12
     }
 }
+
+impl ObricV2Amm {
+    /// `from_keyed_account` for a pool on `cluster`.
+    pub fn from_keyed_account_with_cluster(
+        keyed_account: &KeyedAccount,
+        amm_context: &AmmContext,
+        cluster: &Cluster,
+    ) -> Result<Self> {
+        let cluster = cluster.config()?;
+        let ss_trading_pair = SSTradingPair::decode(&keyed_account.account.data)?;
+        pda::verify_trading_pair(
+            &cluster.program_id,
+            &keyed_account.key,
+            &keyed_account.account.owner,
+            &ss_trading_pair,
+        )?;
+
+        Ok(Self {
+            key: keyed_account.key,
+            state: ss_trading_pair,
+            current_x: 0u64,
+            current_y: 0u64,
+            x_decimals: 0u8,
+            y_decimals: 0u8,
+            clock_ref: amm_context.clock_ref.clone(),
+            x_price_publish_time: 0,
+            y_price_publish_time: 0,
+            pool_values: None,
+            cluster,
+        })
+    }
+
+    /// Price feed data and the owner `parse_price` tells Pyth and Doves feeds apart by. It only
+    /// knows the mainnet Doves program, so feeds of the cluster's Doves program are handed over as
+    /// owned by that one. Feeds owned by neither oracle program are rejected.
+    fn price_feed_data<'a>(
+        &self,
+        account_map: &'a AccountMap,
+        address: &Pubkey,
+    ) -> Result<(&'a [u8], &'a Pubkey)> {
+        let (data, owner) = try_get_account_data_and_owner(account_map, address)?;
+        if *owner == self.cluster.doves_program_id {
+            Ok((data, &DOVES_PROGRAM_ID))
+        } else if *owner == self.cluster.pyth_program_id {
+            Ok((data, owner))
+        } else {
+            bail!("price feed {address} is owned by {owner}, not Pyth or Doves")
+        }
+    }
+
+    /// A copy of the pool as if an admin had set `concentration` and `fee_millionth`, to preview
+    /// the change. `big_k` is rescaled as `SSTradingPair::set_concentration` describes.
+    pub fn with_params(&self, concentration: u64, fee_millionth: u64) -> Result<Self> {
+        let mut pool = self.clone();
+        pool.state.set_concentration(concentration)?;
+        pool.state.fee_millionth = fee_millionth;
+        pool.pool_values = pool
+            .state
+            .get_pool_values_for_quoting(pool.current_x, pool.current_y)
+            .ok();
+        Ok(pool)
+    }
+
+    /// `quote` on `with_params`, the live state is left untouched.
+    pub fn quote_with_params(
+        &self,
+        concentration: u64,
+        fee_millionth: u64,
+        quote_params: &QuoteParams,
+    ) -> Result<Quote> {
+        self.with_params(concentration, fee_millionth)?
+            .quote(quote_params)
+    }
+
+    /// A copy of the updated pool as if the feeds had just published `price_x` and `price_y`,
+    /// normalized to `state.price_decimals` like the prices `update` reads. Only the prices move,
+    /// the reserves and the rest of the pair stay as the last `update` left them.
+    pub fn with_prices(&self, price_x: u64, price_y: u64) -> Result<Self> {
+        if self.x_decimals == 0 && self.y_decimals == 0 {
+            bail!("pool {} has not been updated yet", self.key);
+        }
+        let mut pool = self.clone();
+        pool.state
+            .update_price(price_x, price_y, pool.x_decimals, pool.y_decimals)?;
+        let time = pool
+            .clock_ref
+            .unix_timestamp
+            .load(std::sync::atomic::Ordering::Relaxed);
+        pool.x_price_publish_time = time;
+        pool.y_price_publish_time = time;
+        pool.pool_values = pool
+            .state
+            .get_pool_values_for_quoting(pool.current_x, pool.current_y)
+            .ok();
+        Ok(pool)
+    }
+
+    /// `quote` on `with_prices`, the live state is left untouched.
+    pub fn quote_at_prices(
+        &self,
+        price_x: u64,
+        price_y: u64,
+        quote_params: &QuoteParams,
+    ) -> Result<Quote> {
+        self.with_prices(price_x, price_y)?.quote(quote_params)
+    }
+
+    /// Swaps on a copy of the pool as the program would and returns it with the quote the swap
+    /// filled at. The input joins its reserve, the output leaves the other one and the fee stays
+    /// in the pool. Swaps the program rejects, including those it pays nothing for, fail.
+    pub fn with_swap(&self, quote_params: &QuoteParams) -> Result<(Self, Quote)> {
+        if quote_params.swap_mode != SwapMode::ExactIn {
+            bail!("Obric v2 only swaps exact in");
+        }
+        let quote = self.quote(quote_params)?;
+        if quote.out_amount == 0 {
+            bail!(
+                "pool {} pays out nothing for {}, locked or out of reserves",
+                self.key,
+                quote_params.amount
+            );
+        }
+        let mut pool = self.clone();
+        let (current_in, current_out) = if quote_params.input_mint == pool.state.mint_x {
+            (&mut pool.current_x, &mut pool.current_y)
+        } else {
+            (&mut pool.current_y, &mut pool.current_x)
+        };
+        *current_in = current_in
+            .checked_add(quote.in_amount)
+            .ok_or_else(|| anyhow!("reserve overflows"))?;
+        *current_out = current_out
+            .checked_sub(quote.out_amount)
+            .ok_or_else(|| anyhow!("reserve runs out"))?;
+        pool.pool_values = pool
+            .state
+            .get_pool_values_for_quoting(pool.current_x, pool.current_y)
+            .ok();
+        Ok((pool, quote))
+    }
+
+    /// `with_swap` in place, so quotes that follow see the swap. The pool is left as it was when
+    /// the swap fails.
+    pub fn simulate_swap(&mut self, quote_params: &QuoteParams) -> Result<Quote> {
+        let (pool, quote) = self.with_swap(quote_params)?;
+        *self = pool;
+        Ok(quote)
+    }
+
+    /// Quotes every size in `inputs` from the curve values cached by `update`. Sizes the pool
+    /// rejects come back with a zero output, like `quote`.
+    pub fn quote_many(&self, inputs: &[u64], direction: SwapDirection) -> Result<Vec<Quote>> {
+        self.check_price_freshness()?;
+        let pool_values = self.pool_values()?;
+        inputs
+            .iter()
+            .map(|&input| self.quote_with_pool_values(&pool_values, input, direction))
+            .collect()
+    }
+
+    fn quote_with_pool_values(
+        &self,
+        pool_values: &PoolValues,
+        input: u64,
+        direction: SwapDirection,
+    ) -> Result<Quote> {
+        let (output_after_fee, fee_amount) = self.state.quote_with_pool_values(
+            pool_values,
+            input,
+            direction,
+            self.current_x,
+            self.current_y,
+        )?;
+        let fee_mint = match direction {
+            SwapDirection::XToY => self.state.mint_y,
+            SwapDirection::YToX => self.state.mint_x,
+        };
+
+        Ok(Quote {
+            in_amount: input,
+            out_amount: output_after_fee,
+            fee_amount,
+            fee_mint,
+            ..Quote::default()
+        })
+    }
+
+    /// Curve values cached by `update`, recomputed when the pool was never updated with both reserves
+    /// funded so the error surfaces here.
+    fn pool_values(&self) -> Result<PoolValues> {
+        match self.pool_values {
+            Some(pool_values) => Ok(pool_values),
+            None => Ok(self
+                .state
+                .get_pool_values_for_quoting(self.current_x, self.current_y)?),
+        }
+    }
+
+    fn check_price_freshness(&self) -> Result<()> {
+        let time = self
+            .clock_ref
+            .unix_timestamp
+            .load(std::sync::atomic::Ordering::Relaxed);
+
+        let x_age = time
+            .checked_sub(self.x_price_publish_time)
+            .ok_or(anyhow!("overflow"))?;
+        let y_age = time
+            .checked_sub(self.y_price_publish_time)
+            .ok_or(anyhow!("overflow"))?;
+        if x_age > self.state.feed_max_age_x as i64 || y_age > self.state.feed_max_age_y as i64 {
+            return Err(anyhow!("stale price feed"));
+        }
+        Ok(())
+    }
+
+    /// Price of X in Y before fees, fixed-point with `PRICE_DECIMALS` decimals.
+    pub fn spot_price_x_in_y(&self) -> Result<u128> {
+        Ok(self
+            .state
+            .spot_price_x_in_y(self.current_x, self.x_decimals, self.y_decimals)?)
+    }
+
+    pub fn spot_price_x_in_y_ui(&self) -> Result<Decimal> {
+        Ok(to_decimal(self.spot_price_x_in_y()?))
+    }
+
+    /// Price of X in Y the pool moves to once `input` is swapped in `direction`.
+    pub fn marginal_price_after(&self, input: u64, direction: SwapDirection) -> Result<u128> {
+        Ok(self.state.marginal_price_after(
+            input,
+            direction,
+            self.current_x,
+            self.x_decimals,
+            self.y_decimals,
+        )?)
+    }
+
+    pub fn marginal_price_after_ui(&self, input: u64, direction: SwapDirection) -> Result<Decimal> {
+        Ok(to_decimal(self.marginal_price_after(input, direction)?))
+    }
+
+    /// Direction and input that move the pool to `target_price`, fixed-point with `PRICE_DECIMALS`
+    /// decimals. `None` when the caps stop the trade short of it.
+    pub fn input_for_target_price(
+        &self,
+        target_price: u128,
+    ) -> Result<Option<(SwapDirection, u64)>> {
+        Ok(self.state.input_for_target_price(
+            target_price,
+            self.current_x,
+            self.current_y,
+            self.x_decimals,
+            self.y_decimals,
+        )?)
+    }
+
+    /// Largest input `quote` accepts for `input_mint`, within the opposite reserve and the
+    /// `target_y_based_lock` rule.
+    pub fn max_input(&self, input_mint: &Pubkey) -> Result<u64> {
+        let direction = if input_mint.eq(&self.state.mint_x) {
+            SwapDirection::XToY
+        } else if input_mint.eq(&self.state.mint_y) {
+            SwapDirection::YToX
+        } else {
+            bail!("{input_mint} is not a mint of pool {}", self.key);
+        };
+        Ok(self.state.max_input_with_pool_values(
+            &self.pool_values()?,
+            direction,
+            self.current_x,
+            self.current_y,
+        )?)
+    }
+
+    /// Quotes as much of `quote_params.amount` as the pool accepts. `in_amount` of the quote is the
+    /// filled part, the rest has to be routed elsewhere.
+    pub fn quote_partial_fill(&self, quote_params: &QuoteParams) -> Result<Quote> {
+        let in_amount = quote_params
+            .amount
+            .min(self.max_input(&quote_params.input_mint)?);
+        let quote = self.quote(&QuoteParams {
+            amount: in_amount,
+            input_mint: quote_params.input_mint,
+            output_mint: quote_params.output_mint,
+            swap_mode: quote_params.swap_mode,
+        })?;
+        Ok(Quote { in_amount, ..quote })
+    }
+
+    /// Fills of every size in `inputs` and the largest input within each of `impact_thresholds_bps`, all
+    /// computed from one snapshot of the curve.
+    pub fn depth_profile(
+        &self,
+        input_mint: &Pubkey,
+        inputs: &[u64],
+        impact_thresholds_bps: &[u64],
+    ) -> Result<DepthProfile> {
+        let (direction, output_mint) = if input_mint.eq(&self.state.mint_x) {
+            (SwapDirection::XToY, self.state.mint_y)
+        } else if input_mint.eq(&self.state.mint_y) {
+            (SwapDirection::YToX, self.state.mint_x)
+        } else {
+            bail!("{input_mint} is not a mint of pool {}", self.key);
+        };
+        let pool_values = self.pool_values()?;
+        let spot_price = to_decimal(self.state.spot_price_with_pool_values(
+            &pool_values,
+            self.x_decimals,
+            self.y_decimals,
+        )?);
+        // rejected quotes come back as zero output
+        let quote = |input| {
+            self.state
+                .quote_with_pool_values(
+                    &pool_values,
+                    input,
+                    direction,
+                    self.current_x,
+                    self.current_y,
+                )
+                .ok()
+                .filter(|(output, _)| *output > 0)
+        };
+
+        let levels = inputs
+            .iter()
+            .map(|&input| match quote(input) {
+                Some((output, fee)) => DepthLevel::new(
+                    direction == SwapDirection::XToY,
+                    spot_price,
+                    input,
+                    output,
+                    fee,
+                    |numerator, denominator| {
+                        fixed_point_price(
+                            numerator.into(),
+                            denominator.into(),
+                            self.x_decimals,
+                            self.y_decimals,
+                        )
+                    },
+                ),
+                None => DepthLevel::unfilled(input),
+            })
+            .collect();
+        let thresholds = impact_thresholds_bps
+            .iter()
+            .map(|&impact_bps| {
+                let max_input = self
+                    .state
+                    .input_for_price_impact(&pool_values, impact_bps, direction)?
+                    .filter(|&input| quote(input).is_some());
+                Ok(ImpactThreshold {
+                    impact_bps,
+                    max_input,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(DepthProfile {
+            pool: self.key.to_string(),
+            input_mint: input_mint.to_string(),
+            output_mint: output_mint.to_string(),
+            spot_price,
+            levels,
+            thresholds,
+        })
+    }
+}
+
+fn try_get_account_data_and_owner<'a>(
+    account_map: &'a AccountMap,
+    address: &Pubkey,
+) -> Result<(&'a [u8], &'a Pubkey)> {
+    let account = account_map
+        .get(address)
+        .ok_or_else(|| anyhow!("Could not find address: {address}"))?;
+    Ok((&account.data, &account.owner))
+}
@@ -1,7 +1,13 @@
+use crate::depth::DEFAULT_IMPACT_THRESHOLDS_BPS;
 use crate::obric_v2_amm::{id, ObricV2Amm};
+use anchor_lang::Discriminator;
 use anyhow::Result;
 use jupiter_amm_interface::{Amm, AmmContext, ClockRef, KeyedAccount, QuoteParams, SwapMode};
+use obric_solana::state::{SSTradingPair, SwapDirection};
+use solana_account_decoder::UiAccountEncoding;
 use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
 use solana_sdk::clock::Clock;
 use std::collections::HashMap;
 use std::env;
@@ -21,18 +27,31 @@ impl AmmTestHarness {
     }
 
     pub fn get_all_keyed_account(&self) -> Result<Vec<KeyedAccount>> {
-        let accounts = self.client.get_program_accounts(&id()).unwrap();
-        let keyed_accounts = &mut vec![];
-        for (key, account) in accounts {
-            if account.data.len() == 666usize {
-                keyed_accounts.push(KeyedAccount {
-                    key,
-                    account,
-                    params: None,
-                })
-            }
-        }
-        Ok(keyed_accounts.clone())
+        // Match on the discriminator rather than the size, so pairs with a newer layout show up
+        // and fail to decode loudly instead of being skipped.
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                0,
+                SSTradingPair::DISCRIMINATOR.to_vec(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .client
+            .get_program_accounts_with_config(&id(), config)
+            .unwrap();
+        Ok(accounts
+            .into_iter()
+            .map(|(key, account)| KeyedAccount {
+                key,
+                account,
+                params: None,
+            })
+            .collect())
     }
 
     pub fn update_amm(&self, amm: &mut dyn Amm) {
@@ -80,6 +99,45 @@ fn test_quote() {
             continue;
         }
         println!("Pool: {}, {}", amm.state.mint_x, amm.state.mint_y);
+        println!("  Spot price: {}", amm.spot_price_x_in_y_ui().unwrap());
+        let unit = pow(10u64, usize::from(amm.x_decimals));
+        let depth = amm
+            .depth_profile(
+                &amm.state.mint_x,
+                &[unit, 10 * unit, 100 * unit, 1_000 * unit],
+                &DEFAULT_IMPACT_THRESHOLDS_BPS,
+            )
+            .unwrap();
+        print!("{}", depth.to_csv());
+        let partial = amm
+            .quote_partial_fill(&QuoteParams {
+                input_mint: amm.state.mint_x,
+                amount: u64::MAX,
+                output_mint: amm.state.mint_y,
+                swap_mode: SwapMode::ExactIn,
+            })
+            .unwrap();
+        assert_eq!(partial.in_amount, amm.max_input(&amm.state.mint_x).unwrap());
+        println!(
+            "  Max input: {}, Out amount: {}",
+            partial.in_amount, partial.out_amount
+        );
+        let sizes = [unit, 10 * unit, 100 * unit];
+        let batch = amm.quote_many(&sizes, SwapDirection::XToY).unwrap();
+        for (&amount, quote) in sizes.iter().zip(&batch) {
+            let single = amm
+                .quote(&QuoteParams {
+                    input_mint: amm.state.mint_x,
+                    amount,
+                    output_mint: amm.state.mint_y,
+                    swap_mode: SwapMode::ExactIn,
+                })
+                .unwrap();
+            assert_eq!(
+                (quote.out_amount, quote.fee_amount),
+                (single.out_amount, single.fee_amount)
+            );
+        }
         let amount = pow(10, usize::from(amm.x_decimals));
         let quote = amm
             .quote(&QuoteParams {
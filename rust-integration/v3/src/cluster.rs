@@ -0,0 +1,117 @@
+//! Addresses that differ between clusters. `ObricV3Amm::from_keyed_account` assumes mainnet,
+//! `from_keyed_account_with_cluster` takes any of them.
+
+use crate::constants::PROGRAM_ID;
+use anyhow::{anyhow, bail, Result};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+pub const PYTH_MAINNET_PROGRAM_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClusterConfig {
+    /// Owner of the trading pairs, seed program of their obligations and target of the swap
+    /// instructions.
+    pub program_id: Pubkey,
+    /// Owner every price feed must have.
+    pub pyth_program_id: Pubkey,
+    pub larix_program_id: Pubkey,
+    pub larix_market_authority: Pubkey,
+    /// Only Y to X swaps list it.
+    pub larix_oracle: Pubkey,
+    /// Larix reserve of each mint a pair can hold, as `(mint, reserve)`.
+    pub larix_reserves: [(Pubkey, Pubkey); 3],
+}
+
+impl ClusterConfig {
+    pub fn mainnet() -> Self {
+        Self {
+            program_id: PROGRAM_ID,
+            pyth_program_id: PYTH_MAINNET_PROGRAM_ID,
+            larix_program_id: larix_lending::id(),
+            larix_market_authority: pubkey!("BxnUi6jyYbtEEgkBq4bPLKzDpSfWVAzgyf3TF2jfC1my"),
+            larix_oracle: pubkey!("GMjBguH3ceg9wAHEMdY5iZnvzY6CgBACBDvkWmjR7upS"),
+            larix_reserves: [
+                (
+                    pubkey!("So11111111111111111111111111111111111111112"),
+                    pubkey!("2RcrbkGNcfy9mbarLCCRYdW3hxph7pSbP38x35MR2Bjt"),
+                ),
+                (
+                    pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+                    pubkey!("Emq1qT9MyyB5eHfftF5thYme84hoEwh4TCjm31K2Xxif"),
+                ),
+                (
+                    pubkey!("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
+                    pubkey!("DC832AzxQMGDaVLGiRQfRCkyXi6PUPjQyQfMbVRRjtKA"),
+                ),
+            ],
+        }
+    }
+
+    /// The Larix reserve a pair deposits `mint` into.
+    pub fn larix_reserve(&self, mint: &Pubkey) -> Result<Pubkey> {
+        self.larix_reserves
+            .iter()
+            .find(|(reserve_mint, _)| reserve_mint == mint)
+            .map(|(_, reserve)| *reserve)
+            .ok_or_else(|| anyhow!("No Larix reserve for mint {mint}"))
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    Mainnet,
+    /// Not deployed, `config` fails.
+    Devnet,
+    /// A test validator running the local build, with Pyth and Larix cloned from mainnet.
+    Localnet,
+    Custom(ClusterConfig),
+}
+
+impl Cluster {
+    pub fn config(&self) -> Result<ClusterConfig> {
+        Ok(match self {
+            Cluster::Mainnet => ClusterConfig::mainnet(),
+            Cluster::Devnet => bail!("Obric v3 has no devnet deployment, use Cluster::Custom"),
+            Cluster::Localnet => ClusterConfig {
+                program_id: obric_solana_v3::id(),
+                ..ClusterConfig::mainnet()
+            },
+            Cluster::Custom(config) => *config,
+        })
+    }
+}
+
+#[test]
+fn cluster_ids_agree_with_account_crate() {
+    use obric_solana_v3::consts::{self, deployments, larix};
+
+    let expected = [
+        (Cluster::Mainnet, deployments::mainnet::id()),
+        (Cluster::Localnet, obric_solana_v3::id()),
+    ];
+    for (cluster, program_id) in expected {
+        let config = cluster.config().unwrap();
+        assert_eq!(config.program_id, program_id, "{cluster:?}");
+        assert_eq!(
+            config.larix_market_authority,
+            larix::market::authority::id(),
+            "{cluster:?}"
+        );
+        assert_eq!(config.larix_oracle, larix::oracle::id(), "{cluster:?}");
+        for (mint, reserve) in config.larix_reserves {
+            assert_eq!(
+                reserve,
+                consts::mint_to_larix_reserve(&mint).unwrap(),
+                "{cluster:?} {mint}"
+            );
+        }
+        assert!(config.larix_reserve(&consts::mints::stsol::id()).is_err());
+    }
+    assert!(Cluster::Devnet.config().is_err());
+}
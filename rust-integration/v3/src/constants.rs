@@ -2,3 +2,11 @@ use solana_sdk::pubkey;
 use solana_sdk::pubkey::Pubkey;
 
 pub const PROGRAM_ID: Pubkey = pubkey!("obr3KRonApm4evaY3ww3C63KUmdARoeHkdgc8uBHLz4");
+
+/// Accounts `get_swap_and_account_metas` lists for `SwapXToY`.
+pub const SWAP_X_TO_Y_ACCOUNTS_LEN: usize = 28;
+/// `SwapYToX` also takes the Larix oracle, the LARIX mint and the X reserve's fee receiver.
+pub const SWAP_Y_TO_X_ACCOUNTS_LEN: usize = SWAP_X_TO_Y_ACCOUNTS_LEN + 3;
+
+/// Decimals `PriceFeed::price_normalized` scales Pyth prices to.
+pub const FEED_PRICE_DECIMALS: u8 = 3;
@@ -0,0 +1,105 @@
+//! Replays swaps recorded on mainnet against the SDK quote.
+//!
+//! Recording and replaying live in `obric-golden-vectors`, this module quotes a vector with
+//! `ObricV3Amm` and decodes the v3 swap instructions. `record_golden_vectors` needs `SOLANA_RPC`
+//! and appends new vectors to `golden_vectors/`.
+
+use crate::constants::PROGRAM_ID;
+use crate::obric_v3_amm::ObricV3Amm;
+use crate::test_harness::AmmTestHarness;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{anyhow, Result};
+use jupiter_amm_interface::{Amm, KeyedAccount, QuoteParams};
+use obric_golden_vectors::{GoldenVector, SwapArgs, SwapInstruction};
+use obric_solana_v3::instruction::{SwapXToY, SwapYToX};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+const SWAP: SwapInstruction = SwapInstruction {
+    program_id: PROGRAM_ID,
+    decode: decode_swap,
+    // user_token_account_x and user_token_account_y in `get_swap_and_account_metas`
+    user_token_accounts: [5, 6],
+};
+
+fn decode_swap(data: &[u8]) -> Result<Option<SwapArgs>> {
+    Ok(if data.starts_with(&SwapXToY::DISCRIMINATOR) {
+        let args = SwapXToY::try_from_slice(&data[8..])?;
+        Some(SwapArgs {
+            x_to_y: true,
+            input_amount: args._input_x,
+            min_output_amount: args._min_output_amt,
+        })
+    } else if data.starts_with(&SwapYToX::DISCRIMINATOR) {
+        let args = SwapYToX::try_from_slice(&data[8..])?;
+        Some(SwapArgs {
+            x_to_y: false,
+            input_amount: args._input_y,
+            min_output_amount: args._min_output_amt,
+        })
+    } else {
+        None
+    })
+}
+
+fn vectors_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("golden_vectors")
+}
+
+fn quote(vector: &GoldenVector) -> Result<u64> {
+    let key = vector.pair()?;
+    let account_map: HashMap<Pubkey, Account> = vector.accounts()?;
+    let account = account_map
+        .get(&key)
+        .cloned()
+        .ok_or_else(|| anyhow!("vector is missing the trading pair"))?;
+    let mut amm = ObricV3Amm::from_keyed_account(&KeyedAccount {
+        key,
+        account,
+        params: None,
+    })?;
+    amm.update(&account_map)?;
+
+    let (input_mint, output_mint) = if vector.swap.x_to_y {
+        (amm.state.mint_x, amm.state.mint_y)
+    } else {
+        (amm.state.mint_y, amm.state.mint_x)
+    };
+    let quote = amm.quote(&QuoteParams {
+        in_amount: vector.swap.input_amount,
+        input_mint,
+        output_mint,
+    })?;
+    Ok(quote.out_amount)
+}
+
+#[test]
+#[ignore = "golden_vectors/ has no recorded swaps yet, see golden_vectors/README.md"]
+fn replay_golden_vectors() {
+    obric_golden_vectors::replay(&vectors_dir(), quote);
+}
+
+/// `GOLDEN_VECTORS` vectors per pool and direction, written to `golden_vectors/`.
+#[test]
+#[ignore]
+fn record_golden_vectors() {
+    let wanted: usize = env::var("GOLDEN_VECTORS")
+        .map(|count| count.parse().unwrap())
+        .unwrap_or(1);
+    let test_harness = AmmTestHarness::new();
+    let pools: Vec<_> = test_harness
+        .get_all_keyed_account()
+        .unwrap()
+        .iter()
+        .map(|keyed_account| {
+            let amm = ObricV3Amm::from_keyed_account(keyed_account).unwrap();
+            (keyed_account.key, amm.get_accounts_to_update())
+        })
+        .collect();
+
+    obric_golden_vectors::record(&test_harness.client, SWAP, &pools, wanted, &vectors_dir());
+}
@@ -0,0 +1,85 @@
+//! What a swap does to the Larix reserves a v3 pool keeps its liquidity in. The pool deposits
+//! both tokens and borrows X against them, so a swap repays or borrows X and deposits or
+//! withdraws the rest. Interest only accrues when a reserve is refreshed and is left out, and so
+//! is the borrow fee.
+
+use anyhow::{anyhow, bail, Result};
+use larix_lending::math::{Decimal, TryAdd, TryDiv, TryMul, TrySub};
+use larix_lending::state::reserve::Reserve;
+
+/// Liquidity one collateral token of `reserve` redeems for, 1 before any is minted.
+pub fn exchange_rate(reserve: &Reserve) -> Result<Decimal> {
+    if reserve.collateral.mint_total_supply == 0 {
+        return Ok(Decimal::one());
+    }
+    let total_supply = Decimal::from(reserve.liquidity.available_amount)
+        .try_add(reserve.liquidity.borrowed_amount_wads)?
+        .try_sub(reserve.liquidity.owner_unclaimed)?;
+    Ok(total_supply.try_div(reserve.collateral.mint_total_supply)?)
+}
+
+/// Deposits `amount` and returns what the collateral minted for it redeems for, rounded down to
+/// whole collateral tokens.
+pub fn deposit(reserve: &mut Reserve, amount: u64) -> Result<u64> {
+    let rate = exchange_rate(reserve)?;
+    let minted = Decimal::from(amount).try_div(rate)?.try_floor_u64()?;
+    reserve.liquidity.available_amount = reserve
+        .liquidity
+        .available_amount
+        .checked_add(amount)
+        .ok_or_else(|| anyhow!("Larix reserve liquidity overflows"))?;
+    reserve.collateral.mint_total_supply = reserve
+        .collateral
+        .mint_total_supply
+        .checked_add(minted)
+        .ok_or_else(|| anyhow!("Larix collateral supply overflows"))?;
+    Ok(rate.try_mul(minted)?.try_floor_u64()?)
+}
+
+/// Withdraws `amount` and returns what it takes off the deposit, rounded up to whole collateral
+/// tokens.
+pub fn withdraw(reserve: &mut Reserve, amount: u64) -> Result<u64> {
+    check_available(reserve, amount)?;
+    let rate = exchange_rate(reserve)?;
+    let burned = Decimal::from(amount).try_div(rate)?.try_ceil_u64()?;
+    reserve.liquidity.available_amount -= amount;
+    reserve.collateral.mint_total_supply = reserve
+        .collateral
+        .mint_total_supply
+        .checked_sub(burned)
+        .ok_or_else(|| anyhow!("Larix collateral supply runs out"))?;
+    Ok(rate.try_mul(burned)?.try_ceil_u64()?.max(amount))
+}
+
+pub fn borrow(reserve: &mut Reserve, amount: u64) -> Result<()> {
+    check_available(reserve, amount)?;
+    reserve.liquidity.available_amount -= amount;
+    reserve.liquidity.borrowed_amount_wads = reserve
+        .liquidity
+        .borrowed_amount_wads
+        .try_add(Decimal::from(amount))?;
+    Ok(())
+}
+
+pub fn repay(reserve: &mut Reserve, amount: u64) -> Result<()> {
+    reserve.liquidity.available_amount = reserve
+        .liquidity
+        .available_amount
+        .checked_add(amount)
+        .ok_or_else(|| anyhow!("Larix reserve liquidity overflows"))?;
+    // a reserve refreshed before the pair was read may owe a little less than the pair does
+    reserve.liquidity.borrowed_amount_wads = reserve
+        .liquidity
+        .borrowed_amount_wads
+        .try_sub(Decimal::from(amount))
+        .unwrap_or_else(|_| Decimal::zero());
+    Ok(())
+}
+
+fn check_available(reserve: &Reserve, amount: u64) -> Result<()> {
+    let available = reserve.liquidity.available_amount;
+    if amount > available {
+        bail!("Larix reserve has {available} available, {amount} is needed");
+    }
+    Ok(())
+}
@@ -1,5 +1,11 @@
+pub mod cluster;
 pub mod constants;
+pub use obric_depth as depth;
+pub mod larix;
 pub mod obric_v3_amm;
+pub mod quote_breakdown;
 
+#[cfg(test)]
+pub mod golden_vectors;
 #[cfg(test)]
 pub mod test_harness;
@@ -1,24 +1,36 @@
-use crate::constants::PROGRAM_ID;
+use crate::cluster::{Cluster, ClusterConfig};
+use crate::depth::{to_decimal, DepthLevel, DepthProfile, ImpactThreshold};
+use crate::larix;
+use crate::quote_breakdown::QuoteBreakdown;
 use anchor_lang::prelude::Pubkey;
 use anchor_lang::AccountDeserialize;
-use anyhow::Result;
+use anchor_spl::token::TokenAccount;
+use anyhow::{anyhow, bail, Result};
 use jupiter_amm_interface::{
     Amm, KeyedAccount, Quote, QuoteParams, Swap, SwapAndAccountMetas, SwapParams,
 };
 use larix_lending::state::reserve::Reserve;
 use obric_solana_v3::consts;
+use obric_solana_v3::math::fixed_point_price;
+use obric_solana_v3::pda;
 use obric_solana_v3::state::PriceFeed;
-use obric_solana_v3::state::SSTradingPair;
+use obric_solana_v3::state::{PoolValues, SSTradingPair, SwapDirection};
+use rust_decimal::Decimal;
 use solana_sdk::account::Account;
 use solana_sdk::instruction::AccountMeta;
+use solana_sdk::program_pack::Pack;
 use std::collections::HashMap;
 
+#[derive(Clone)]
 pub struct ObricV3Amm {
     pub key: Pubkey,
     pub state: SSTradingPair,
     pub obligation: Pubkey,
     pub larix_reserve_x: Option<Reserve>,
     pub larix_reserve_y: Option<Reserve>,
+    /// Curve values `update` computes for the quotes, `None` when the pool cannot quote.
+    pub pool_values: Option<PoolValues>,
+    pub cluster: ClusterConfig,
 }
 
 impl Amm for ObricV3Amm {
@@ -31,23 +43,7 @@ impl Amm for ObricV3Amm {
     }
 
     fn from_keyed_account(keyed_account: &KeyedAccount) -> Result<Self> {
-        let data = &mut &keyed_account.account.data.clone()[0..];
-        let ss_trading_pair = SSTradingPair::try_deserialize(data).unwrap();
-        let (obligation, _) = Pubkey::find_program_address(
-            &[
-                consts::LARIX_OBLIGATION_SEED.as_bytes(),
-                ss_trading_pair.mint_x.to_bytes().as_ref(),
-                ss_trading_pair.mint_y.to_bytes().as_ref(),
-            ],
-            &PROGRAM_ID,
-        );
-        Ok(Self {
-            key: keyed_account.key,
-            state: ss_trading_pair,
-            obligation,
-            larix_reserve_x: None,
-            larix_reserve_y: None,
-        })
+        Self::from_keyed_account_with_cluster(keyed_account, &Cluster::Mainnet)
     }
 
     fn get_reserve_mints(&self) -> Vec<Pubkey> {
@@ -59,113 +55,67 @@ impl Amm for ObricV3Amm {
     }
 
     fn get_accounts_to_update(&self) -> Vec<Pubkey> {
-        if self.larix_reserve_y.is_none() {
-            return [
-                self.key,
-                self.state.x_price_feed_id,
-                self.state.y_price_feed_id,
-                consts::mint_to_larix_reserve(&self.state.mint_x).unwrap(),
-                consts::mint_to_larix_reserve(&self.state.mint_y).unwrap(),
-            ]
-            .to_vec();
-        } else {
-            return [
-                self.key,
-                self.state.x_price_feed_id,
-                self.state.y_price_feed_id,
-            ]
-            .to_vec();
-        }
+        let mut accounts = vec![
+            self.key,
+            self.state.x_price_feed_id,
+            self.state.y_price_feed_id,
+        ];
+        // mints without a Larix reserve are rejected by `update`
+        accounts.extend(
+            [self.state.mint_x, self.state.mint_y]
+                .iter()
+                .filter_map(|mint| self.cluster.larix_reserve(mint).ok()),
+        );
+        accounts
     }
 
     fn update(&mut self, accounts_map: &HashMap<Pubkey, Account>) -> Result<()> {
-        let trading_pair_data = &mut &accounts_map.get(&self.key).unwrap().data[0..];
-        let trading_pair = SSTradingPair::try_deserialize(trading_pair_data).unwrap();
+        let trading_pair = SSTradingPair::decode(get_account_data(accounts_map, &self.key)?)?;
+
+        let price_x_data =
+            &mut self.price_feed_data(accounts_map, &trading_pair.x_price_feed_id)?;
+        let price_y_data =
+            &mut self.price_feed_data(accounts_map, &trading_pair.y_price_feed_id)?;
+        let price_x = PriceFeed::try_deserialize(price_x_data)?
+            .price_normalized()?
+            .price as u64;
+        let price_y = PriceFeed::try_deserialize(price_y_data)?
+            .price_normalized()?
+            .price as u64;
+
+        let larix_reserve_x = Reserve::unpack(get_account_data(
+            accounts_map,
+            &self.cluster.larix_reserve(&trading_pair.mint_x)?,
+        )?)?;
+        let larix_reserve_y = Reserve::unpack(get_account_data(
+            accounts_map,
+            &self.cluster.larix_reserve(&trading_pair.mint_y)?,
+        )?)?;
+
         self.state = trading_pair;
+        self.larix_reserve_x = Some(larix_reserve_x);
+        self.larix_reserve_y = Some(larix_reserve_y);
 
-        let price_x_data = &mut &accounts_map.get(&self.state.x_price_feed_id).unwrap().data[0..];
-        let price_y_data = &mut &accounts_map.get(&self.state.y_price_feed_id).unwrap().data[0..];
-        let price_x_fee = &PriceFeed::try_deserialize(price_x_data).unwrap();
-        let price_y_fee = &PriceFeed::try_deserialize(price_y_data).unwrap();
-        let price_x = price_x_fee.price_normalized()?.price as u64;
-        let price_y = price_y_fee.price_normalized()?.price as u64;
         self.state.update_price(price_x, price_y)?;
-        let target_y = self.state.compute_target_y();
-        let _ = self.state.update_target_y(target_y)?;
+        self.pool_values = None;
+        let target_y = self.state.compute_target_y()?;
+        // large pools outgrow the u128 `big_k` field, the quotes derive K in 256 bits
+        self.state.retarget(target_y)?;
+        self.pool_values = self.state.get_pool_values_for_quoting().ok();
         Ok(())
     }
 
     fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
-        let (output_after_fee, protocol_fee, _) = if quote_params.input_mint.eq(&self.state.mint_x)
-        {
-            self.state
-                .quote_x_to_y(quote_params.in_amount.clone())
-                .unwrap()
-        } else if quote_params.input_mint.eq(&self.state.mint_y) {
-            self.state
-                .quote_y_to_x(quote_params.in_amount.clone())
-                .unwrap()
-        } else {
-            (0u64, 0u64, 0u64)
-        };
-        Ok(Quote {
-            out_amount: output_after_fee,
-            fee_amount: protocol_fee,
-            fee_mint: quote_params.output_mint,
-            ..Quote::default()
-        })
+        let breakdown = self.quote_breakdown(quote_params)?;
+        Ok(breakdown.to_quote(quote_params.output_mint))
     }
 
     fn clone_amm(&self) -> Box<dyn Amm + Send + Sync> {
-        let state = &self.state;
-        Box::new(Self {
-            key: self.key,
-            state: SSTradingPair {
-                is_initialized: state.is_initialized,
-                x_price_feed_id: state.x_price_feed_id,
-                y_price_feed_id: state.y_price_feed_id,
-                reserve_x: state.reserve_x,
-                reserve_y: state.reserve_y,
-                reserve_x_ctoken: state.reserve_x_ctoken,
-                reserve_y_ctoken: state.reserve_y_ctoken,
-
-                protocol_fee_x: state.protocol_fee_x,
-                protocol_fee_y: state.protocol_fee_y,
-                bump: state.bump,
-                mint_x: state.mint_x,
-                mint_y: state.mint_y,
-
-                deposit_x: state.deposit_x,
-                borrow_x: state.borrow_x,
-
-                deposit_y: state.deposit_y,
-                borrow_y: state.borrow_y,
-
-                target_y: state.target_y,
-
-                concentration: state.concentration,
-                big_k: state.big_k,
-                cumulative_volume: state.cumulative_volume,
-                mult_x: state.mult_x,
-                mult_y: state.mult_y,
-                fee_millionth: state.fee_millionth,
-                rebate_percentage: state.rebate_percentage,
-                protocol_fee_share_thousandth: state.protocol_fee_share_thousandth,
-                decimals_x: state.decimals_x,
-                decimals_y: state.decimals_y,
-                volume_records: state.volume_records,
-                padding: state.padding,
-                volume_time_records: state.volume_time_records,
-                padding2: state.padding2,
-            },
-            obligation: self.obligation,
-            larix_reserve_x: self.larix_reserve_x.clone(),
-            larix_reserve_y: self.larix_reserve_y.clone(),
-        })
+        Box::new(self.clone())
     }
 
     fn program_id(&self) -> Pubkey {
-        PROGRAM_ID
+        self.cluster.program_id
     }
 
     fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
@@ -183,8 +133,11 @@ impl Amm for ObricV3Amm {
                     self.state.protocol_fee_x,
                 )
             };
-        let larix_reserve_x = &self.larix_reserve_x.as_ref().unwrap();
-        let larix_reserve_y = &self.larix_reserve_y.as_ref().unwrap();
+        let (Some(larix_reserve_x), Some(larix_reserve_y)) =
+            (&self.larix_reserve_x, &self.larix_reserve_y)
+        else {
+            bail!("Larix reserves are not loaded, update the pool first");
+        };
         let mut account_metas = vec![
             AccountMeta::new(self.key(), false),
             AccountMeta::new_readonly(self.state.mint_x, false),
@@ -204,28 +157,19 @@ impl Amm for ObricV3Amm {
             AccountMeta::new(larix_reserve_y.liquidity.supply_pubkey, false),
             AccountMeta::new(larix_reserve_x.collateral.supply_pubkey, false),
             AccountMeta::new(larix_reserve_y.collateral.supply_pubkey, false),
-            AccountMeta::new(
-                consts::mint_to_larix_reserve(&self.state.mint_x).unwrap(),
-                false,
-            ),
-            AccountMeta::new(
-                consts::mint_to_larix_reserve(&self.state.mint_y).unwrap(),
-                false,
-            ),
+            AccountMeta::new(self.cluster.larix_reserve(&self.state.mint_x)?, false),
+            AccountMeta::new(self.cluster.larix_reserve(&self.state.mint_y)?, false),
             AccountMeta::new(self.obligation, false),
             AccountMeta::new(larix_reserve_x.lending_market, false),
-            AccountMeta::new(consts::larix::market::authority::id(), false),
+            AccountMeta::new(self.cluster.larix_market_authority, false),
             AccountMeta::new_readonly(larix_reserve_x.liquidity.params_2, false),
             AccountMeta::new_readonly(larix_reserve_y.liquidity.params_2, false),
             AccountMeta::new_readonly(swap_params.token_transfer_authority, true),
             AccountMeta::new_readonly(anchor_spl::token::spl_token::id(), false),
-            AccountMeta::new_readonly(larix_lending::id(), false),
+            AccountMeta::new_readonly(self.cluster.larix_program_id, false),
         ];
         if swap_params.source_mint.eq(&self.state.mint_y) {
-            account_metas.push(AccountMeta::new_readonly(
-                consts::larix::oracle::id(),
-                false,
-            ));
+            account_metas.push(AccountMeta::new_readonly(self.cluster.larix_oracle, false));
             account_metas.push(AccountMeta::new_readonly(consts::mints::larix::id(), false));
             account_metas.push(AccountMeta::new(
                 larix_reserve_x.liquidity.fee_receiver,
@@ -238,3 +182,399 @@ impl Amm for ObricV3Amm {
         })
     }
 }
+
+impl ObricV3Amm {
+    /// `from_keyed_account` for a pool on `cluster`.
+    pub fn from_keyed_account_with_cluster(
+        keyed_account: &KeyedAccount,
+        cluster: &Cluster,
+    ) -> Result<Self> {
+        let cluster = cluster.config()?;
+        let ss_trading_pair = SSTradingPair::decode(&keyed_account.account.data)?;
+        pda::verify_trading_pair(
+            &cluster.program_id,
+            &keyed_account.key,
+            &keyed_account.account.owner,
+            &ss_trading_pair,
+        )?;
+        let (obligation, _) = pda::larix_obligation_address(
+            &cluster.program_id,
+            &ss_trading_pair.mint_x,
+            &ss_trading_pair.mint_y,
+        );
+        Ok(Self {
+            key: keyed_account.key,
+            state: ss_trading_pair,
+            obligation,
+            larix_reserve_x: None,
+            larix_reserve_y: None,
+            pool_values: None,
+            cluster,
+        })
+    }
+
+    /// Data of a price feed, which has to be owned by the cluster's Pyth program.
+    fn price_feed_data<'a>(
+        &self,
+        accounts_map: &'a HashMap<Pubkey, Account>,
+        address: &Pubkey,
+    ) -> Result<&'a [u8]> {
+        let account = accounts_map
+            .get(address)
+            .ok_or_else(|| anyhow!("Could not find address: {address}"))?;
+        if account.owner != self.cluster.pyth_program_id {
+            bail!(
+                "price feed {address} is owned by {}, not Pyth",
+                account.owner
+            );
+        }
+        Ok(&account.data)
+    }
+
+    /// Quotes like `Amm::quote` but keeps the LP fee and protocol fee apart.
+    /// A mint outside the pool yields an empty breakdown.
+    pub fn quote_breakdown(&self, quote_params: &QuoteParams) -> Result<QuoteBreakdown> {
+        let direction = if quote_params.input_mint.eq(&self.state.mint_x) {
+            SwapDirection::XToY
+        } else if quote_params.input_mint.eq(&self.state.mint_y) {
+            SwapDirection::YToX
+        } else {
+            return Ok(QuoteBreakdown::default());
+        };
+        self.breakdown_with_pool_values(&self.pool_values()?, quote_params.in_amount, direction)
+    }
+
+    /// A copy of the updated pool as if an admin had set `concentration` and `fee_millionth`, to
+    /// preview the change. `big_k` is re-derived at the current target.
+    pub fn with_params(&self, concentration: u64, fee_millionth: u64) -> Result<Self> {
+        let mut pool = self.clone();
+        pool.state.set_concentration(concentration)?;
+        pool.state.fee_millionth = fee_millionth;
+        pool.pool_values = pool.state.get_pool_values_for_quoting().ok();
+        Ok(pool)
+    }
+
+    /// `quote` on `with_params`, the live state is left untouched.
+    pub fn quote_with_params(
+        &self,
+        concentration: u64,
+        fee_millionth: u64,
+        quote_params: &QuoteParams,
+    ) -> Result<Quote> {
+        self.with_params(concentration, fee_millionth)?
+            .quote(quote_params)
+    }
+
+    /// A copy of the updated pool as if Pyth had published `price_x` and `price_y`, normalized
+    /// to `FEED_PRICE_DECIMALS` like the prices `update` reads. The target is recomputed at them as
+    /// `update` does, the deposits and borrows stay as the last `update` left them.
+    pub fn with_prices(&self, price_x: u64, price_y: u64) -> Result<Self> {
+        let mut pool = self.clone();
+        pool.state.update_price(price_x, price_y)?;
+        let target_y = pool.state.compute_target_y()?;
+        pool.state.retarget(target_y)?;
+        pool.pool_values = pool.state.get_pool_values_for_quoting().ok();
+        Ok(pool)
+    }
+
+    /// `quote` on `with_prices`, the live state is left untouched.
+    pub fn quote_at_prices(
+        &self,
+        price_x: u64,
+        price_y: u64,
+        quote_params: &QuoteParams,
+    ) -> Result<Quote> {
+        self.with_prices(price_x, price_y)?.quote(quote_params)
+    }
+
+    /// Swaps on a copy of the pool as the program would and returns it with the quote the swap
+    /// filled at. X paid in repays the X borrow before the rest is deposited, X paid out is
+    /// withdrawn from the deposit and borrowed once that runs out, and Y is deposited and
+    /// withdrawn; see `larix` for the reserves. The protocol fee leaves the pool with the output.
+    /// The target and curve are then recomputed as `update` would from the accounts after the
+    /// swap. Swaps the program rejects, including those it pays nothing for, fail.
+    pub fn with_swap(&self, quote_params: &QuoteParams) -> Result<(Self, Quote)> {
+        let x_to_y = if quote_params.input_mint == self.state.mint_x {
+            true
+        } else if quote_params.input_mint == self.state.mint_y {
+            false
+        } else {
+            bail!(
+                "{} is not a mint of pool {}",
+                quote_params.input_mint,
+                self.key
+            );
+        };
+        let breakdown = self.quote_breakdown(quote_params)?;
+        if breakdown.net_output == 0 {
+            bail!(
+                "pool {} pays out nothing for {}",
+                self.key,
+                quote_params.in_amount
+            );
+        }
+
+        let mut pool = self.clone();
+        let (Some(reserve_x), Some(reserve_y)) =
+            (pool.larix_reserve_x.as_mut(), pool.larix_reserve_y.as_mut())
+        else {
+            bail!("Larix reserves are not loaded, update the pool first");
+        };
+        let state = &mut pool.state;
+        let in_amount = quote_params.in_amount;
+        let paid_out = breakdown.net_output + breakdown.protocol_fee;
+        if x_to_y {
+            let repaid = in_amount.min(state.borrow_x);
+            larix::repay(reserve_x, repaid)?;
+            state.borrow_x -= repaid;
+            if in_amount > repaid {
+                state.deposit_x += larix::deposit(reserve_x, in_amount - repaid)?;
+            }
+            state.deposit_y = state
+                .deposit_y
+                .checked_sub(larix::withdraw(reserve_y, paid_out)?)
+                .ok_or_else(|| anyhow!("deposit of Y runs out"))?;
+        } else {
+            state.deposit_y += larix::deposit(reserve_y, in_amount)?;
+            let withdrawn = paid_out.min(state.deposit_x);
+            if withdrawn > 0 {
+                state.deposit_x = state
+                    .deposit_x
+                    .saturating_sub(larix::withdraw(reserve_x, withdrawn)?);
+            }
+            let borrowed = paid_out - withdrawn;
+            if borrowed > 0 {
+                larix::borrow(reserve_x, borrowed)?;
+                state.borrow_x += borrowed;
+            }
+        }
+
+        let target_y = pool.state.compute_target_y()?;
+        pool.state.retarget(target_y)?;
+        pool.pool_values = pool.state.get_pool_values_for_quoting().ok();
+        Ok((pool, breakdown.to_quote(quote_params.output_mint)))
+    }
+
+    /// `with_swap` in place, so quotes that follow see the swap. The pool is left as it was when
+    /// the swap fails.
+    pub fn simulate_swap(&mut self, quote_params: &QuoteParams) -> Result<Quote> {
+        let (pool, quote) = self.with_swap(quote_params)?;
+        *self = pool;
+        Ok(quote)
+    }
+
+    /// Quotes every size in `inputs` from the curve values cached by `update`. Sizes above
+    /// `max_input` come back with a zero output instead of failing the whole batch.
+    pub fn quote_many(&self, inputs: &[u64], direction: SwapDirection) -> Result<Vec<Quote>> {
+        let pool_values = self.pool_values()?;
+        let max_input = self
+            .state
+            .max_input_with_pool_values(&pool_values, direction)?;
+        let fee_mint = match direction {
+            SwapDirection::XToY => self.state.mint_y,
+            SwapDirection::YToX => self.state.mint_x,
+        };
+        inputs
+            .iter()
+            .map(|&input| {
+                if input > max_input {
+                    return Ok(Quote {
+                        in_amount: input,
+                        fee_mint,
+                        ..Quote::default()
+                    });
+                }
+                Ok(self
+                    .breakdown_with_pool_values(&pool_values, input, direction)?
+                    .to_quote(fee_mint))
+            })
+            .collect()
+    }
+
+    fn breakdown_with_pool_values(
+        &self,
+        pool_values: &PoolValues,
+        input: u64,
+        direction: SwapDirection,
+    ) -> Result<QuoteBreakdown> {
+        let (output_after_fee, protocol_fee, lp_fee) =
+            self.state
+                .quote_with_pool_values(pool_values, input, direction)?;
+
+        Ok(QuoteBreakdown::new(
+            input,
+            output_after_fee,
+            protocol_fee,
+            lp_fee,
+        ))
+    }
+
+    /// Curve values cached by `update`, recomputed when the pool was never updated so the error
+    /// surfaces here.
+    fn pool_values(&self) -> Result<PoolValues> {
+        match self.pool_values {
+            Some(pool_values) => Ok(pool_values),
+            None => Ok(self.state.get_pool_values_for_quoting()?),
+        }
+    }
+
+    /// Price of X in Y before fees, fixed-point with `PRICE_DECIMALS` decimals.
+    pub fn spot_price_x_in_y(&self) -> Result<u128> {
+        Ok(self.state.spot_price_x_in_y()?)
+    }
+
+    pub fn spot_price_x_in_y_ui(&self) -> Result<Decimal> {
+        Ok(to_decimal(self.spot_price_x_in_y()?))
+    }
+
+    /// Price of X in Y the pool moves to once `input` is swapped in `direction`.
+    pub fn marginal_price_after(&self, input: u64, direction: SwapDirection) -> Result<u128> {
+        Ok(self.state.marginal_price_after(input, direction)?)
+    }
+
+    pub fn marginal_price_after_ui(&self, input: u64, direction: SwapDirection) -> Result<Decimal> {
+        Ok(to_decimal(self.marginal_price_after(input, direction)?))
+    }
+
+    /// Direction and input that move the pool to `target_price`, fixed-point with `PRICE_DECIMALS`
+    /// decimals. `None` when the caps stop the trade short of it.
+    pub fn input_for_target_price(
+        &self,
+        target_price: u128,
+    ) -> Result<Option<(SwapDirection, u64)>> {
+        Ok(self.state.input_for_target_price(target_price)?)
+    }
+
+    /// Largest input `quote` accepts for `input_mint`, within the `available_*` amount of the other
+    /// side.
+    pub fn max_input(&self, input_mint: &Pubkey) -> Result<u64> {
+        let direction = if input_mint.eq(&self.state.mint_x) {
+            SwapDirection::XToY
+        } else if input_mint.eq(&self.state.mint_y) {
+            SwapDirection::YToX
+        } else {
+            bail!("{input_mint} is not a mint of pool {}", self.key);
+        };
+        Ok(self
+            .state
+            .max_input_with_pool_values(&self.pool_values()?, direction)?)
+    }
+
+    /// Quotes as much of `quote_params.in_amount` as the pool accepts instead of failing with
+    /// `InsufficientActive*`. `in_amount` of the quote is the filled part, the rest has to be
+    /// routed elsewhere.
+    pub fn quote_partial_fill(&self, quote_params: &QuoteParams) -> Result<Quote> {
+        let in_amount = quote_params
+            .in_amount
+            .min(self.max_input(&quote_params.input_mint)?);
+        if in_amount == 0 {
+            return Ok(Quote {
+                fee_mint: quote_params.output_mint,
+                ..Quote::default()
+            });
+        }
+        self.quote(&QuoteParams {
+            in_amount,
+            input_mint: quote_params.input_mint,
+            output_mint: quote_params.output_mint,
+        })
+    }
+
+    /// Token accounts the program pays the protocol share of the fee into, `(x, y)`. `update`
+    /// does not fetch them.
+    pub fn protocol_fee_accounts(&self) -> (Pubkey, Pubkey) {
+        (self.state.protocol_fee_x, self.state.protocol_fee_y)
+    }
+
+    /// Protocol fees accrued by the pool and not withdrawn yet, `(x, y)` in base units, read from
+    /// the `protocol_fee_accounts` in `accounts_map`.
+    pub fn protocol_fees(&self, accounts_map: &HashMap<Pubkey, Account>) -> Result<(u64, u64)> {
+        let balance = |address: &Pubkey| -> Result<u64> {
+            let mut data = get_account_data(accounts_map, address)?;
+            Ok(TokenAccount::try_deserialize(&mut data)?.amount)
+        };
+        Ok((
+            balance(&self.state.protocol_fee_x)?,
+            balance(&self.state.protocol_fee_y)?,
+        ))
+    }
+
+    /// Fills of every size in `inputs` and the largest input within each of `impact_thresholds_bps`, all
+    /// computed from one snapshot of the curve.
+    pub fn depth_profile(
+        &self,
+        input_mint: &Pubkey,
+        inputs: &[u64],
+        impact_thresholds_bps: &[u64],
+    ) -> Result<DepthProfile> {
+        let (direction, output_mint) = if input_mint.eq(&self.state.mint_x) {
+            (SwapDirection::XToY, self.state.mint_y)
+        } else if input_mint.eq(&self.state.mint_y) {
+            (SwapDirection::YToX, self.state.mint_x)
+        } else {
+            bail!("{input_mint} is not a mint of pool {}", self.key);
+        };
+        let pool_values = self.pool_values()?;
+        let spot_price = to_decimal(self.state.spot_price_with_pool_values(&pool_values)?);
+        let quote = |input| {
+            self.state
+                .quote_with_pool_values(&pool_values, input, direction)
+                .ok()
+                .filter(|(output, _, _)| *output > 0)
+        };
+
+        let levels = inputs
+            .iter()
+            .map(|&input| match quote(input) {
+                Some((output, protocol_fee, lp_fee)) => DepthLevel::new(
+                    direction == SwapDirection::XToY,
+                    spot_price,
+                    input,
+                    output,
+                    protocol_fee + lp_fee,
+                    |numerator, denominator| {
+                        fixed_point_price(
+                            numerator.into(),
+                            denominator.into(),
+                            self.state.decimals_x,
+                            self.state.decimals_y,
+                        )
+                    },
+                ),
+                None => DepthLevel::unfilled(input),
+            })
+            .collect();
+        let thresholds = impact_thresholds_bps
+            .iter()
+            .map(|&impact_bps| {
+                let max_input = self
+                    .state
+                    .input_for_price_impact(&pool_values, impact_bps, direction)?
+                    .filter(|&input| quote(input).is_some());
+                Ok(ImpactThreshold {
+                    impact_bps,
+                    max_input,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(DepthProfile {
+            pool: self.key.to_string(),
+            input_mint: input_mint.to_string(),
+            output_mint: output_mint.to_string(),
+            spot_price,
+            levels,
+            thresholds,
+        })
+    }
+}
+
+fn get_account_data<'a>(
+    accounts_map: &'a HashMap<Pubkey, Account>,
+    address: &Pubkey,
+) -> Result<&'a [u8]> {
+    accounts_map
+        .get(address)
+        .map(|account| account.data.as_slice())
+        .ok_or_else(|| anyhow!("Could not find address: {address}"))
+}
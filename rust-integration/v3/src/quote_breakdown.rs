@@ -0,0 +1,73 @@
+use anchor_lang::prelude::Pubkey;
+use jupiter_amm_interface::Quote;
+use rust_decimal::Decimal;
+
+const BPS: u128 = 10_000;
+
+/// Fee accounting for a single v3 quote. Every amount is denominated in the
+/// output token. The pair's `rebate_percentage` is not modeled: the program
+/// source is not available to show how it is applied, so no rebate is reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QuoteBreakdown {
+    pub in_amount: u64,
+    /// Curve output before any fee is taken.
+    pub gross_output: u64,
+    /// Part of the fee that stays in the pool reserves.
+    pub lp_fee: u64,
+    /// Part of the fee sent to the `protocol_fee_x`/`protocol_fee_y` account.
+    pub protocol_fee: u64,
+    /// Amount transferred to the user.
+    pub net_output: u64,
+    /// `(lp_fee + protocol_fee) / gross_output`, in basis points, rounded up.
+    pub effective_fee_bps: u64,
+}
+
+impl QuoteBreakdown {
+    /// Builds the breakdown from the `(output_to_user, protocol_fee, lp_fee)`
+    /// triple returned by `SSTradingPair::quote_x_to_y`/`quote_y_to_x`.
+    pub fn new(in_amount: u64, net_output: u64, protocol_fee: u64, lp_fee: u64) -> Self {
+        let total_fee = protocol_fee as u128 + lp_fee as u128;
+        let gross_output = net_output as u128 + total_fee;
+
+        let effective_fee_bps = if gross_output == 0 {
+            0
+        } else {
+            ((total_fee * BPS + gross_output - 1) / gross_output) as u64
+        };
+
+        Self {
+            in_amount,
+            gross_output: gross_output as u64,
+            lp_fee,
+            protocol_fee,
+            net_output,
+            effective_fee_bps,
+        }
+    }
+
+    /// Every fee taken from the gross output, LP and protocol shares combined.
+    pub fn total_fee(&self) -> u64 {
+        self.lp_fee + self.protocol_fee
+    }
+
+    /// Total fee as a fraction of the gross output, as Jupiter expects in
+    /// `Quote::fee_pct`.
+    pub fn fee_pct(&self) -> Decimal {
+        if self.gross_output == 0 {
+            return Decimal::ZERO;
+        }
+        Decimal::from(self.total_fee()) / Decimal::from(self.gross_output)
+    }
+
+    /// The Jupiter quote for this breakdown, fees denominated in `fee_mint`.
+    pub fn to_quote(&self, fee_mint: Pubkey) -> Quote {
+        Quote {
+            in_amount: self.in_amount,
+            out_amount: self.net_output,
+            fee_amount: self.total_fee(),
+            fee_mint,
+            fee_pct: self.fee_pct(),
+            ..Quote::default()
+        }
+    }
+}
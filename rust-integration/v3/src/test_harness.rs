@@ -1,10 +1,18 @@
+use anchor_lang::Discriminator;
 use anyhow::Result;
 use jupiter_amm_interface::{Amm, KeyedAccount, QuoteParams};
+use obric_solana_v3::state::{SSTradingPair, SwapDirection};
+use solana_account_decoder::UiAccountEncoding;
 use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 use std::env;
 
 use crate::constants::PROGRAM_ID;
+use crate::depth::DEFAULT_IMPACT_THRESHOLDS_BPS;
 use crate::obric_v3_amm::ObricV3Amm;
 
 pub struct AmmTestHarness {
@@ -20,35 +28,49 @@ impl AmmTestHarness {
         }
     }
     pub fn get_all_keyed_account(&self) -> Result<Vec<KeyedAccount>> {
-        let accounts = self.client.get_program_accounts(&PROGRAM_ID).unwrap();
-        let keyed_accounts = &mut vec![];
-        for (key, account) in accounts {
-            if account.data.len() == 762usize {
-                keyed_accounts.push(KeyedAccount {
-                    key,
-                    account,
-                    params: None,
-                })
-            }
-        }
-        Ok(keyed_accounts.clone())
+        // Match on the discriminator rather than the size, so pairs with a newer layout show up
+        // and fail to decode loudly instead of being skipped.
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                0,
+                SSTradingPair::DISCRIMINATOR.to_vec(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .client
+            .get_program_accounts_with_config(&PROGRAM_ID, config)
+            .unwrap();
+        Ok(accounts
+            .into_iter()
+            .map(|(key, account)| KeyedAccount {
+                key,
+                account,
+                params: None,
+            })
+            .collect())
     }
 
-    pub fn update_amm(&self, amm: &mut dyn Amm) {
-        let accounts_to_update = amm.get_accounts_to_update();
-
-        let accounts_map = self
-            .client
-            .get_multiple_accounts(&accounts_to_update)
+    pub fn get_accounts(&self, keys: &[Pubkey]) -> HashMap<Pubkey, Account> {
+        self.client
+            .get_multiple_accounts(keys)
             .unwrap()
             .iter()
             .enumerate()
             .fold(HashMap::new(), |mut m, (index, account)| {
                 if let Some(account) = account {
-                    m.insert(accounts_to_update[index], account.clone());
+                    m.insert(keys[index], account.clone());
                 }
                 m
-            });
+            })
+    }
+
+    pub fn update_amm(&self, amm: &mut dyn Amm) {
+        let accounts_map = self.get_accounts(&amm.get_accounts_to_update());
         amm.update(&accounts_map).unwrap();
     }
 }
@@ -66,15 +88,64 @@ fn test_quote() {
         test_harness.update_amm(amm);
 
         println!("Pool: {}, {}", amm.state.mint_x, amm.state.mint_y);
-
-        let in_amount = pow(10, usize::from(amm.state.decimals_x));
-        let quote = amm
-            .quote(&QuoteParams {
+        println!("  Spot price: {}", amm.spot_price_x_in_y_ui().unwrap());
+        let unit = pow(10u64, usize::from(amm.state.decimals_x));
+        let depth = amm
+            .depth_profile(
+                &amm.state.mint_x,
+                &[unit, 10 * unit, 100 * unit, 1_000 * unit],
+                &DEFAULT_IMPACT_THRESHOLDS_BPS,
+            )
+            .unwrap();
+        print!("{}", depth.to_csv());
+        let partial = amm
+            .quote_partial_fill(&QuoteParams {
                 input_mint: amm.state.mint_x,
-                in_amount,
+                in_amount: u64::MAX,
                 output_mint: amm.state.mint_y,
             })
             .unwrap();
+        assert_eq!(partial.in_amount, amm.max_input(&amm.state.mint_x).unwrap());
+        println!(
+            "  Max input: {}, Out amount: {}",
+            partial.in_amount, partial.out_amount
+        );
+        let sizes = [unit, 10 * unit, 100 * unit];
+        let batch = amm.quote_many(&sizes, SwapDirection::XToY).unwrap();
+        for (&in_amount, quote) in sizes.iter().zip(&batch) {
+            // sizes past the pool's capacity fail alone and come back empty in the batch
+            if let Ok(single) = amm.quote(&QuoteParams {
+                input_mint: amm.state.mint_x,
+                in_amount,
+                output_mint: amm.state.mint_y,
+            }) {
+                assert_eq!(
+                    (quote.out_amount, quote.fee_amount),
+                    (single.out_amount, single.fee_amount)
+                );
+            }
+        }
+
+        let (fee_x, fee_y) = amm.protocol_fee_accounts();
+        let (protocol_fee_x, protocol_fee_y) = amm
+            .protocol_fees(&test_harness.get_accounts(&[fee_x, fee_y]))
+            .unwrap();
+        println!("  Protocol fees: {protocol_fee_x} X, {protocol_fee_y} Y");
+
+        let in_amount = pow(10, usize::from(amm.state.decimals_x));
+        let quote_params = QuoteParams {
+            input_mint: amm.state.mint_x,
+            in_amount,
+            output_mint: amm.state.mint_y,
+        };
+        let quote = amm.quote(&quote_params).unwrap();
+        let breakdown = amm.quote_breakdown(&quote_params).unwrap();
+        assert_eq!(quote.out_amount, breakdown.net_output);
+        assert_eq!(quote.fee_amount, breakdown.lp_fee + breakdown.protocol_fee);
+        assert_eq!(
+            breakdown.gross_output,
+            breakdown.net_output + breakdown.total_fee()
+        );
 
         println!(
             "  Token mints: from {}, to {}",
@@ -85,6 +156,10 @@ fn test_quote() {
             "  Out amount: {:?}, Fee amount: {:?}",
             quote.out_amount, quote.fee_amount
         );
+        println!(
+            "  LP fee: {}, Protocol fee: {}, Effective fee: {} bps",
+            breakdown.lp_fee, breakdown.protocol_fee, breakdown.effective_fee_bps
+        );
 
         let in_amount = pow(10, usize::from(amm.state.decimals_y));
         let quote = amm